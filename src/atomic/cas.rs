@@ -0,0 +1,100 @@
+//! Compare-and-swap that falls back to critical sections on targets without CAS.
+//!
+//! Some targets, such as RISC-V without the A extension, only support atomic loads and stores.
+//! On these targets, compare-and-swap is emulated by disabling interrupts, which is only sound on single-core systems.
+
+use core::sync::atomic::{self, AtomicBool, Ordering};
+
+/// Stores `new` into `atomic` if the current value is `current`.
+///
+/// This behaves like [`AtomicBool::compare_exchange`].
+#[inline]
+pub(crate) fn compare_exchange(
+    atomic: &AtomicBool,
+    current: bool,
+    new: bool,
+    success: Ordering,
+    failure: Ordering,
+) -> Result<bool, bool> {
+    #[cfg(target_has_atomic = "8")]
+    {
+        atomic.compare_exchange(current, new, success, failure)
+    }
+
+    #[cfg(not(target_has_atomic = "8"))]
+    {
+        compare_exchange_emulated(atomic, current, new, success, failure)
+    }
+}
+
+/// Stores `new` into `atomic` if the current value is `current`, possibly failing spuriously.
+///
+/// This behaves like [`AtomicBool::compare_exchange_weak`].
+#[cfg_attr(
+    any(feature = "all-one-shot", feature = "spinning_top"),
+    allow(dead_code)
+)]
+#[inline]
+pub(crate) fn compare_exchange_weak(
+    atomic: &AtomicBool,
+    current: bool,
+    new: bool,
+    success: Ordering,
+    failure: Ordering,
+) -> Result<bool, bool> {
+    #[cfg(target_has_atomic = "8")]
+    {
+        atomic.compare_exchange_weak(current, new, success, failure)
+    }
+
+    #[cfg(not(target_has_atomic = "8"))]
+    {
+        compare_exchange_emulated(atomic, current, new, success, failure)
+    }
+}
+
+#[cfg_attr(target_has_atomic = "8", allow(dead_code))]
+#[inline]
+fn compare_exchange_emulated(
+    atomic: &AtomicBool,
+    current: bool,
+    new: bool,
+    success: Ordering,
+    failure: Ordering,
+) -> Result<bool, bool> {
+    // Nothing can interleave with this critical section on single-core systems.
+    crate::without_interrupts(|| {
+        let value = atomic.load(Ordering::Relaxed);
+        if value != current {
+            if matches!(failure, Ordering::Acquire | Ordering::SeqCst) {
+                atomic::fence(Ordering::Acquire);
+            }
+            return Err(value);
+        }
+
+        if !matches!(success, Ordering::Relaxed) {
+            atomic::fence(Ordering::SeqCst);
+        }
+        atomic.store(new, Ordering::Relaxed);
+        Ok(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emulated() {
+        let atomic = AtomicBool::new(false);
+        let cas = |current, new| {
+            compare_exchange_emulated(&atomic, current, new, Ordering::Acquire, Ordering::Relaxed)
+        };
+
+        assert_eq!(cas(true, false), Err(false));
+        assert_eq!(cas(false, true), Ok(false));
+        assert_eq!(cas(false, true), Err(true));
+        assert_eq!(cas(true, false), Ok(true));
+        assert!(!atomic.load(Ordering::Relaxed));
+    }
+}
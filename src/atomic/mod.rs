@@ -0,0 +1,7 @@
+pub(crate) mod cas;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod option;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub(crate) mod owned;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub(crate) mod rcu;
@@ -0,0 +1,286 @@
+use core::marker::PhantomData;
+use core::num::NonZeroUsize;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{fmt, mem};
+
+/// A type that can be represented as a non-zero [`usize`].
+///
+/// This is implemented for references, [`NonNull`], [`NonZeroUsize`], and, with the `alloc` feature, `Box` and `Arc`.
+///
+/// # Safety
+///
+/// [`from_raw`](Self::from_raw) must return the value that was passed to [`into_raw`](Self::into_raw).
+pub unsafe trait AtomicRepr: Sized {
+    /// Converts this value into its raw representation.
+    fn into_raw(self) -> NonZeroUsize;
+
+    /// Converts a raw representation back into the value.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must have been returned by [`into_raw`](Self::into_raw) and must not be converted back more than once.
+    unsafe fn from_raw(raw: NonZeroUsize) -> Self;
+}
+
+unsafe impl AtomicRepr for NonZeroUsize {
+    #[inline]
+    fn into_raw(self) -> NonZeroUsize {
+        self
+    }
+
+    #[inline]
+    unsafe fn from_raw(raw: NonZeroUsize) -> Self {
+        raw
+    }
+}
+
+unsafe impl<T> AtomicRepr for NonNull<T> {
+    #[inline]
+    fn into_raw(self) -> NonZeroUsize {
+        // SAFETY: `NonNull` is never null.
+        unsafe { NonZeroUsize::new_unchecked(self.as_ptr() as usize) }
+    }
+
+    #[inline]
+    unsafe fn from_raw(raw: NonZeroUsize) -> Self {
+        // SAFETY: `raw` is the non-null address of a `NonNull<T>`.
+        unsafe { NonNull::new_unchecked(raw.get() as *mut T) }
+    }
+}
+
+unsafe impl<T> AtomicRepr for &T {
+    #[inline]
+    fn into_raw(self) -> NonZeroUsize {
+        NonNull::from(self).into_raw()
+    }
+
+    #[inline]
+    unsafe fn from_raw(raw: NonZeroUsize) -> Self {
+        // SAFETY: `raw` was created from `&T`.
+        unsafe { NonNull::from_raw(raw).as_ref() }
+    }
+}
+
+unsafe impl<T> AtomicRepr for &mut T {
+    #[inline]
+    fn into_raw(self) -> NonZeroUsize {
+        NonNull::from(self).into_raw()
+    }
+
+    #[inline]
+    unsafe fn from_raw(raw: NonZeroUsize) -> Self {
+        // SAFETY: `raw` was created from `&mut T` and is converted back only once.
+        unsafe { NonNull::from_raw(raw).as_mut() }
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<T> AtomicRepr for alloc::boxed::Box<T> {
+    #[inline]
+    fn into_raw(self) -> NonZeroUsize {
+        // SAFETY: `Box::into_raw` is never null.
+        unsafe { NonNull::new_unchecked(alloc::boxed::Box::into_raw(self)) }.into_raw()
+    }
+
+    #[inline]
+    unsafe fn from_raw(raw: NonZeroUsize) -> Self {
+        // SAFETY: `raw` was created via `Box::into_raw` and is converted back only once.
+        unsafe { alloc::boxed::Box::from_raw(NonNull::<T>::from_raw(raw).as_ptr()) }
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<T> AtomicRepr for alloc::sync::Arc<T> {
+    #[inline]
+    fn into_raw(self) -> NonZeroUsize {
+        // SAFETY: `Arc::into_raw` is never null.
+        unsafe { NonNull::new_unchecked(alloc::sync::Arc::into_raw(self).cast_mut()) }.into_raw()
+    }
+
+    #[inline]
+    unsafe fn from_raw(raw: NonZeroUsize) -> Self {
+        // SAFETY: `raw` was created via `Arc::into_raw` and is converted back only once.
+        unsafe { alloc::sync::Arc::from_raw(NonNull::<T>::from_raw(raw).as_ptr()) }
+    }
+}
+
+/// An [`Option`] that can be taken and put atomically.
+///
+/// This is useful for handing a single item between an interrupt handler and a thread without a lock.
+/// All operations are lock-free and can therefore be used from interrupt handlers.
+///
+/// `T` has to be representable as a non-zero [`usize`] (see [`AtomicRepr`]).
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::AtomicOption;
+///
+/// #[derive(PartialEq, Debug)]
+/// struct Packet(u32);
+///
+/// static RECEIVED: Packet = Packet(42);
+/// static PACKET: AtomicOption<&'static Packet> = AtomicOption::none();
+///
+/// fn interrupt_handler() {
+///     let _ = PACKET.put(&RECEIVED);
+/// }
+///
+/// interrupt_handler();
+/// assert_eq!(PACKET.take(), Some(&Packet(42)));
+/// assert_eq!(PACKET.take(), None);
+/// ```
+pub struct AtomicOption<T: AtomicRepr> {
+    raw: AtomicUsize,
+    marker: PhantomData<T>,
+}
+
+// SAFETY: Values are moved between threads, but never shared.
+unsafe impl<T: AtomicRepr + Send> Send for AtomicOption<T> {}
+unsafe impl<T: AtomicRepr + Send> Sync for AtomicOption<T> {}
+
+impl<T: AtomicRepr> AtomicOption<T> {
+    /// Creates a new, empty `AtomicOption`.
+    #[inline]
+    pub const fn none() -> Self {
+        Self {
+            raw: AtomicUsize::new(0),
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates a new `AtomicOption`.
+    #[inline]
+    pub fn new(value: Option<T>) -> Self {
+        Self {
+            raw: AtomicUsize::new(Self::into_raw(value)),
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn into_raw(value: Option<T>) -> usize {
+        value.map_or(0, |value| value.into_raw().get())
+    }
+
+    #[inline]
+    unsafe fn from_raw(raw: usize) -> Option<T> {
+        // SAFETY: The caller upholds the contract.
+        NonZeroUsize::new(raw).map(|raw| unsafe { T::from_raw(raw) })
+    }
+
+    /// Takes the value out, leaving `None` in its place.
+    #[inline]
+    pub fn take(&self) -> Option<T> {
+        self.swap(None)
+    }
+
+    /// Puts a value in if empty.
+    ///
+    /// If a value is already present, `value` is returned as an error.
+    #[inline]
+    pub fn put(&self, value: T) -> Result<(), T> {
+        let raw = value.into_raw();
+        self.raw
+            .compare_exchange(0, raw.get(), Ordering::AcqRel, Ordering::Relaxed)
+            .map(drop)
+            // SAFETY: We still own `raw`.
+            .map_err(|_| unsafe { T::from_raw(raw) })
+    }
+
+    /// Replaces the value, returning the previous value.
+    #[inline]
+    pub fn swap(&self, value: Option<T>) -> Option<T> {
+        let raw = self.raw.swap(Self::into_raw(value), Ordering::AcqRel);
+        // SAFETY: We took ownership of `raw`.
+        unsafe { Self::from_raw(raw) }
+    }
+
+    /// Returns `true` if a value is present.
+    ///
+    /// The result might be outdated immediately.
+    #[inline]
+    pub fn is_some(&self) -> bool {
+        self.raw.load(Ordering::Relaxed) != 0
+    }
+
+    /// Consumes this `AtomicOption`, returning the value.
+    #[inline]
+    pub fn into_inner(self) -> Option<T> {
+        let raw = self.raw.load(Ordering::Relaxed);
+        mem::forget(self);
+        // SAFETY: We own `raw`.
+        unsafe { Self::from_raw(raw) }
+    }
+}
+
+impl<T: AtomicRepr> Default for AtomicOption<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl<T: AtomicRepr> From<Option<T>> for AtomicOption<T> {
+    #[inline]
+    fn from(value: Option<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: AtomicRepr> Drop for AtomicOption<T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: We own the value.
+        drop(unsafe { Self::from_raw(*self.raw.get_mut()) });
+    }
+}
+
+impl<T: AtomicRepr> fmt::Debug for AtomicOption<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicOption")
+            .field("is_some", &self.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    #[test]
+    fn put_take() {
+        let option = AtomicOption::none();
+        assert!(!option.is_some());
+        assert_eq!(option.put(value(1)), Ok(()));
+        assert_eq!(option.put(value(2)), Err(value(2)));
+        assert_eq!(option.take(), Some(value(1)));
+        assert_eq!(option.take(), None);
+    }
+
+    #[test]
+    fn swap() {
+        let option = AtomicOption::new(Some(value(1)));
+        assert_eq!(option.swap(Some(value(2))), Some(value(1)));
+        assert_eq!(option.swap(None), Some(value(2)));
+        assert_eq!(option.into_inner(), None);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn drop_box() {
+        use alloc::boxed::Box;
+        use alloc::rc::Rc;
+
+        let rc = Rc::new(());
+        let option = AtomicOption::new(Some(Box::new(rc.clone())));
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(option);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
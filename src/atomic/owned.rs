@@ -0,0 +1,209 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use core::{fmt, mem};
+
+use crate::relax::{Backoff, Relax};
+use crate::{local_irq_restore, local_irq_save, AtomicOption, InterruptSpinMutex};
+
+/// A `Box` that can be replaced and taken atomically.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::AtomicBox;
+///
+/// let config = AtomicBox::new(Some(Box::new(1)));
+/// assert_eq!(config.swap(Some(Box::new(2))), Some(Box::new(1)));
+/// assert_eq!(config.take(), Some(Box::new(2)));
+/// ```
+pub type AtomicBox<T> = AtomicOption<Box<T>>;
+
+/// An `Arc` that can be loaded and replaced atomically.
+///
+/// In contrast to [`AtomicOption`]`<Arc<T>>`, the current value can be loaded without taking it out.
+/// Loading is lock-free and disables interrupts only for a few instructions.
+///
+/// Replacing the value waits for a grace period:
+/// the previous value is only handed out after all concurrent loads that might still see it have finished.
+/// Replacing values is serialized by an [`InterruptSpinMutex`].
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use hermit_sync::AtomicArc;
+///
+/// let config = AtomicArc::new(Arc::new(1));
+///
+/// let old = config.load();
+/// config.store(Arc::new(2));
+/// assert_eq!(*old, 1);
+/// assert_eq!(*config.load(), 2);
+/// ```
+pub struct AtomicArc<T> {
+    ptr: AtomicPtr<T>,
+    epoch: AtomicUsize,
+    readers: [AtomicUsize; 2],
+    writer: InterruptSpinMutex<()>,
+}
+
+// SAFETY: `AtomicArc` hands out clones of `Arc<T>`.
+unsafe impl<T: Send + Sync> Send for AtomicArc<T> {}
+unsafe impl<T: Send + Sync> Sync for AtomicArc<T> {}
+
+impl<T> AtomicArc<T> {
+    /// Creates a new `AtomicArc`.
+    #[inline]
+    pub fn new(value: Arc<T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Arc::into_raw(value).cast_mut()),
+            epoch: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writer: InterruptSpinMutex::new(()),
+        }
+    }
+
+    /// Loads the current value.
+    #[inline]
+    pub fn load(&self) -> Arc<T> {
+        let flags = local_irq_save();
+
+        let readers = loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let readers = &self.readers[epoch % 2];
+            readers.fetch_add(1, Ordering::SeqCst);
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                break readers;
+            }
+            readers.fetch_sub(1, Ordering::Release);
+        };
+
+        let ptr = self.ptr.load(Ordering::SeqCst);
+        // SAFETY: Writers wait for us before releasing `ptr`.
+        let value = unsafe {
+            Arc::increment_strong_count(ptr);
+            Arc::from_raw(ptr)
+        };
+
+        readers.fetch_sub(1, Ordering::Release);
+        local_irq_restore(flags);
+
+        value
+    }
+
+    /// Replaces the value, returning the previous value.
+    ///
+    /// This waits until all concurrent loads that might see the previous value have finished.
+    #[inline]
+    pub fn swap(&self, value: Arc<T>) -> Arc<T> {
+        let _writer = self.writer.lock();
+
+        let ptr = self
+            .ptr
+            .swap(Arc::into_raw(value).cast_mut(), Ordering::SeqCst);
+
+        // Direct new loads to the other reader counter and wait for the old one.
+        // Pairs with the `SeqCst` in `load`: either we see the reader or the reader sees the new epoch.
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+        let mut backoff = Backoff::default();
+        while self.readers[epoch % 2].load(Ordering::SeqCst) != 0 {
+            backoff.relax();
+        }
+
+        // SAFETY: No reader can see `ptr` anymore and we own its reference.
+        unsafe { Arc::from_raw(ptr) }
+    }
+
+    /// Replaces the value, dropping the previous value.
+    #[inline]
+    pub fn store(&self, value: Arc<T>) {
+        drop(self.swap(value));
+    }
+
+    /// Consumes this `AtomicArc`, returning the value.
+    #[inline]
+    pub fn into_inner(self) -> Arc<T> {
+        let ptr = self.ptr.load(Ordering::Relaxed);
+        mem::forget(self);
+        // SAFETY: We own the reference of `ptr`.
+        unsafe { Arc::from_raw(ptr) }
+    }
+}
+
+impl<T: Default> Default for AtomicArc<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Arc::default())
+    }
+}
+
+impl<T> From<Arc<T>> for AtomicArc<T> {
+    #[inline]
+    fn from(value: Arc<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> Drop for AtomicArc<T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: We own the reference of `ptr`.
+        drop(unsafe { Arc::from_raw(*self.ptr.get_mut()) });
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AtomicArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicArc").field(&self.load()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn drop_previous() {
+        let first = Arc::new(1);
+        let atomic = AtomicArc::new(first.clone());
+        assert_eq!(Arc::strong_count(&first), 2);
+
+        atomic.store(Arc::new(2));
+        assert_eq!(Arc::strong_count(&first), 1);
+
+        let second = atomic.load();
+        assert_eq!(*second, 2);
+        drop(atomic);
+        assert_eq!(Arc::strong_count(&second), 1);
+    }
+
+    #[test]
+    fn concurrent() {
+        const N: usize = 1000;
+
+        let atomic = AtomicArc::new(Arc::new(0));
+
+        thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| {
+                    let mut last = 0;
+                    for _ in 0..N {
+                        let value = *atomic.load();
+                        assert!(value >= last);
+                        last = value;
+                    }
+                });
+            }
+
+            for i in 1..=N {
+                atomic.store(Arc::new(i));
+            }
+        });
+
+        assert_eq!(*atomic.into_inner(), N);
+    }
+}
@@ -0,0 +1,128 @@
+use alloc::sync::Arc;
+use core::fmt;
+
+use crate::{AtomicArc, InterruptSpinMutex};
+
+/// A read-mostly value with lock-free reads and serialized updates.
+///
+/// [`read`](Self::read) returns a snapshot of the current version without locking, similar to `rcu_read_lock` and `rcu_dereference`.
+/// [`update`](Self::update) builds a new version from the current one and publishes it, similar to `rcu_assign_pointer` followed by `synchronize_rcu`.
+/// Grace periods and reclamation are handled by [`AtomicArc`]: previous versions are freed once the last snapshot is dropped.
+///
+/// Updates are serialized by an [`InterruptSpinMutex`], so they never lose concurrent updates.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::RcuMutex;
+///
+/// let routes = RcuMutex::new(Vec::new());
+///
+/// let before = routes.read();
+/// routes.update(|routes| {
+///     let mut routes = routes.clone();
+///     routes.push(1);
+///     routes
+/// });
+///
+/// assert!(before.is_empty());
+/// assert_eq!(*routes.read(), [1]);
+/// ```
+pub struct RcuMutex<T> {
+    value: AtomicArc<T>,
+    writer: InterruptSpinMutex<()>,
+}
+
+impl<T> RcuMutex<T> {
+    /// Creates a new `RcuMutex`.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self {
+            value: AtomicArc::new(Arc::new(value)),
+            writer: InterruptSpinMutex::new(()),
+        }
+    }
+
+    /// Returns a snapshot of the current version.
+    ///
+    /// This is lock-free.
+    /// The snapshot stays valid after later updates.
+    #[inline]
+    pub fn read(&self) -> Arc<T> {
+        self.value.load()
+    }
+
+    /// Publishes the version returned by `f` for the current version.
+    ///
+    /// This waits for concurrent reads of the current version to finish and returns the previous version.
+    /// The previous version is freed once it and all other snapshots of it are dropped.
+    #[inline]
+    pub fn update<F>(&self, f: F) -> Arc<T>
+    where
+        F: FnOnce(&T) -> T,
+    {
+        let _writer = self.writer.lock();
+        let new = Arc::new(f(&self.value.load()));
+        self.value.swap(new)
+    }
+
+    /// Consumes this `RcuMutex`, returning the current version.
+    #[inline]
+    pub fn into_inner(self) -> Arc<T> {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Default> Default for RcuMutex<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for RcuMutex<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RcuMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RcuMutex").field(&self.read()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_updates() {
+        const N: usize = 100;
+
+        let rcu = RcuMutex::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..N {
+                        rcu.update(|value| value + 1);
+                    }
+                });
+            }
+            s.spawn(|| {
+                let mut last = 0;
+                for _ in 0..N {
+                    let value = *rcu.read();
+                    assert!(value >= last);
+                    last = value;
+                }
+            });
+        });
+
+        assert_eq!(*rcu.into_inner(), 4 * N);
+    }
+}
@@ -0,0 +1,296 @@
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::now_ns;
+use crate::relax::{Backoff, Relax};
+
+const BROKEN: usize = 1;
+const GENERATION_ONE: usize = 2;
+
+/// A reusable barrier that allows a number of participants to synchronize.
+///
+/// In contrast to [`std::sync::Barrier`], a barrier can be broken.
+/// If a participant fails to arrive, for example, because [`wait_for`](Self::wait_for) timed out or because [`break_barrier`](Self::break_barrier) was called, all current and future waiters return [`BarrierError::Broken`].
+/// A broken barrier stays broken.
+///
+/// Waiting spins with [exponential backoff].
+///
+/// [`std::sync::Barrier`]: https://doc.rust-lang.org/std/sync/struct.Barrier.html
+/// [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use hermit_sync::Barrier;
+///
+/// const N: usize = 4;
+///
+/// static BARRIER: Barrier = Barrier::new(N);
+///
+/// let handles = (0..N)
+///     .map(|_| thread::spawn(|| BARRIER.wait().unwrap().is_leader()))
+///     .collect::<Vec<_>>();
+///
+/// let leaders = handles
+///     .into_iter()
+///     .map(|handle| handle.join().unwrap())
+///     .filter(|&is_leader| is_leader)
+///     .count();
+/// assert_eq!(leaders, 1);
+/// ```
+#[derive(Debug)]
+pub struct Barrier {
+    n: usize,
+    count: AtomicUsize,
+    /// The generation shifted by one with [`BROKEN`] as the lowest bit.
+    state: AtomicUsize,
+}
+
+/// The result of [`Barrier::wait`] and [`Barrier::wait_for`].
+#[derive(Clone, Copy, Debug)]
+pub struct BarrierWaitResult {
+    is_leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// Returns `true` if this participant released all others.
+    ///
+    /// Exactly one participant per generation is the leader.
+    #[inline]
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+}
+
+/// An error returned by [`Barrier::wait`] and [`Barrier::wait_for`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BarrierError {
+    /// The barrier has been broken.
+    Broken,
+    /// The timeout elapsed before all participants arrived.
+    ///
+    /// This breaks the barrier for all other participants.
+    TimedOut,
+}
+
+impl fmt::Display for BarrierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Broken => "the barrier has been broken".fmt(f),
+            Self::TimedOut => "the barrier wait timed out".fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for BarrierError {}
+
+impl Barrier {
+    /// Creates a new barrier for `n` participants.
+    ///
+    /// If `n` is zero or one, waiting never blocks.
+    #[inline]
+    pub const fn new(n: usize) -> Self {
+        Self {
+            n,
+            count: AtomicUsize::new(0),
+            state: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits until all participants have arrived.
+    ///
+    /// The last participant to arrive is the leader and releases all others.
+    #[inline]
+    pub fn wait(&self) -> Result<BarrierWaitResult, BarrierError> {
+        self.wait_until(None)
+    }
+
+    /// Waits until all participants have arrived or `timeout` has elapsed.
+    ///
+    /// If `timeout` elapses, this breaks the barrier and returns [`BarrierError::TimedOut`].
+    /// The timeout is measured via [`now_ns`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no time source has been registered via [`init_smp`](crate::init_smp).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::OnceLock;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use hermit_sync::{init_smp, Barrier, BarrierError, SmpConfig};
+    ///
+    /// fn core_id() -> usize {
+    ///     0
+    /// }
+    ///
+    /// fn now_ns() -> u64 {
+    ///     static START: OnceLock<Instant> = OnceLock::new();
+    ///     START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+    /// }
+    ///
+    /// init_smp(SmpConfig::new(1, core_id).time_source(now_ns)).unwrap();
+    ///
+    /// let barrier = Barrier::new(2);
+    /// let timeout = Duration::from_millis(10);
+    /// assert_eq!(barrier.wait_for(timeout).unwrap_err(), BarrierError::TimedOut);
+    /// assert_eq!(barrier.wait().unwrap_err(), BarrierError::Broken);
+    /// ```
+    #[inline]
+    pub fn wait_for(&self, timeout: Duration) -> Result<BarrierWaitResult, BarrierError> {
+        let now = now_ns().expect("no time source has been registered");
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        self.wait_until(Some(now.saturating_add(timeout)))
+    }
+
+    fn wait_until(&self, deadline: Option<u64>) -> Result<BarrierWaitResult, BarrierError> {
+        // The generation cannot advance before we arrive.
+        let state = self.state.load(Ordering::Acquire);
+        if state & BROKEN != 0 {
+            return Err(BarrierError::Broken);
+        }
+
+        // Each arrival releases its prior writes into the release sequence of `count`.
+        // The leader acquires all of them and releases them to the others via `state`.
+        let arrived = self.count.fetch_add(1, Ordering::AcqRel) + 1;
+        if arrived >= self.n {
+            // Nobody arrives for the next generation before we release the current one.
+            self.count.store(0, Ordering::Relaxed);
+            return self
+                .state
+                .compare_exchange(
+                    state,
+                    state.wrapping_add(GENERATION_ONE),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                )
+                .map(|_| BarrierWaitResult { is_leader: true })
+                .map_err(|_| BarrierError::Broken);
+        }
+
+        let mut backoff = Backoff::default();
+        loop {
+            let current = self.state.load(Ordering::Acquire);
+            if current != state {
+                return Self::outcome(state, current);
+            }
+
+            if deadline.is_some_and(|deadline| now_ns().is_none_or(|now| now >= deadline)) {
+                return match self.state.compare_exchange(
+                    state,
+                    state | BROKEN,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => Err(BarrierError::TimedOut),
+                    Err(current) => Self::outcome(state, current),
+                };
+            }
+
+            backoff.relax();
+        }
+    }
+
+    /// Returns the outcome for a waiter of `state` after the barrier changed to `current`.
+    fn outcome(state: usize, current: usize) -> Result<BarrierWaitResult, BarrierError> {
+        if current & !BROKEN != state {
+            // The barrier was released before it was broken.
+            Ok(BarrierWaitResult { is_leader: false })
+        } else {
+            Err(BarrierError::Broken)
+        }
+    }
+
+    /// Breaks this barrier.
+    ///
+    /// All current and future waiters return [`BarrierError::Broken`].
+    /// This is intended for participants that will not arrive, for example, CPU cores that failed to come online.
+    #[inline]
+    pub fn break_barrier(&self) {
+        self.state.fetch_or(BROKEN, Ordering::AcqRel);
+    }
+
+    /// Returns `true` if this barrier has been broken.
+    #[inline]
+    pub fn is_broken(&self) -> bool {
+        self.state.load(Ordering::Acquire) & BROKEN != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn reusable() {
+        const N: usize = 4;
+        const ROUNDS: usize = 100;
+
+        let barrier = Barrier::new(N);
+        let leaders = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..N {
+                s.spawn(|| {
+                    for _ in 0..ROUNDS {
+                        if barrier.wait().unwrap().is_leader() {
+                            leaders.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(leaders.load(Ordering::Relaxed), ROUNDS);
+        assert!(!barrier.is_broken());
+    }
+
+    #[test]
+    fn publishes_writes() {
+        const N: usize = 4;
+        const ROUNDS: usize = 100;
+
+        let barrier = Barrier::new(N);
+        let data = [const { AtomicUsize::new(0) }; N];
+
+        thread::scope(|s| {
+            for i in 0..N {
+                let barrier = &barrier;
+                let data = &data;
+                s.spawn(move || {
+                    for round in 1..=ROUNDS {
+                        data[i].store(round, Ordering::Relaxed);
+                        barrier.wait().unwrap();
+                        for value in data {
+                            assert_eq!(value.load(Ordering::Relaxed), round);
+                        }
+                        // Do not overwrite the data before everyone has checked it.
+                        barrier.wait().unwrap();
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn broken() {
+        let barrier = Barrier::new(3);
+
+        thread::scope(|s| {
+            // The waiter is broken regardless of whether it arrives before or after breaking.
+            let waiter = s.spawn(|| barrier.wait());
+            barrier.break_barrier();
+            assert_eq!(waiter.join().unwrap().unwrap_err(), BarrierError::Broken);
+        });
+
+        assert!(barrier.is_broken());
+        assert_eq!(barrier.wait().unwrap_err(), BarrierError::Broken);
+    }
+}
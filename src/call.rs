@@ -0,0 +1,204 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::init::ipi_hook;
+use crate::relax::{Backoff, Relax};
+use crate::{core_id, cpu_count, MAX_CPUS};
+
+type Call = NonNull<dyn Fn() + Sync>;
+
+struct CallSlot {
+    /// Serializes callers targeting the same CPU core.
+    busy: AtomicBool,
+    call: UnsafeCell<Option<Call>>,
+    pending: AtomicBool,
+}
+
+// SAFETY: `call` is written by the owner of `busy` before setting `pending` and read by the target CPU core while `pending` is set.
+unsafe impl Sync for CallSlot {}
+
+impl CallSlot {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Self = Self {
+        busy: AtomicBool::new(false),
+        call: UnsafeCell::new(None),
+        pending: AtomicBool::new(false),
+    };
+}
+
+static CALL_SLOTS: [CallSlot; MAX_CPUS] = [CallSlot::EMPTY; MAX_CPUS];
+
+/// An error returned by [`run_on_cpu`] and [`run_on_all_cpus`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CallError {
+    /// The crate has not been initialized via [`init_smp`](crate::init_smp) or no IPI hook has been registered.
+    Uninitialized,
+    /// The CPU core does not exist.
+    NoSuchCpu,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uninitialized => "no IPI hook has been registered".fmt(f),
+            Self::NoSuchCpu => "the CPU core does not exist".fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for CallError {}
+
+/// Claims the call slot of `cpu`, posts `f` to it, and sends an IPI.
+///
+/// The caller has to [`complete`] the call before `f` is dropped.
+fn post(cpu: usize, f: &(dyn Fn() + Sync), send_ipi: fn(usize)) {
+    let slot = &CALL_SLOTS[cpu];
+    let mut backoff = Backoff::default();
+    while slot
+        .busy
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        backoff.relax();
+    }
+
+    // SAFETY: The caller completes the call before `f` is dropped, so erasing the lifetime is fine.
+    let call =
+        unsafe { core::mem::transmute::<NonNull<dyn Fn() + Sync + '_>, Call>(NonNull::from(f)) };
+    // SAFETY: We own `busy` and the slot is not pending.
+    unsafe {
+        *slot.call.get() = Some(call);
+    }
+    slot.pending.store(true, Ordering::Release);
+    send_ipi(cpu);
+}
+
+/// Waits for the call posted to `cpu` to complete and releases the call slot.
+fn complete(cpu: usize) {
+    let slot = &CALL_SLOTS[cpu];
+    let mut backoff = Backoff::default();
+    while slot.pending.load(Ordering::Acquire) {
+        backoff.relax();
+    }
+    slot.busy.store(false, Ordering::Release);
+}
+
+fn check(cpu: usize) -> Result<fn(usize), CallError> {
+    let send_ipi = ipi_hook().ok_or(CallError::Uninitialized)?;
+    let cpu_count = cpu_count().ok_or(CallError::Uninitialized)?;
+    if cpu >= cpu_count {
+        return Err(CallError::NoSuchCpu);
+    }
+    Ok(send_ipi)
+}
+
+/// Runs `f` on the CPU core `cpu` and waits for it to complete.
+///
+/// This corresponds to Linux's `smp_call_function_single` with `wait` set.
+/// The IPI is sent via the hook registered with [`SmpConfig::ipi_hook`](crate::SmpConfig::ipi_hook), and the IPI handler of the target CPU core has to call [`handle_call_ipi`].
+/// If `cpu` is the current CPU core, `f` is run directly.
+///
+/// This must not be called with interrupts disabled or from interrupt handlers.
+/// Otherwise, two CPU cores calling each other would deadlock.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+/// use std::thread;
+///
+/// use hermit_sync::{handle_call_ipi, init_smp, run_on_cpu, SmpConfig};
+///
+/// std::thread_local! {
+///     static CORE_ID: AtomicUsize = AtomicUsize::new(0);
+/// }
+///
+/// fn core_id() -> usize {
+///     CORE_ID.with(|id| id.load(Ordering::Relaxed))
+/// }
+///
+/// fn send_ipi(_cpu: usize) {
+///     // The emulated CPU core below polls instead of receiving interrupts.
+/// }
+///
+/// init_smp(SmpConfig::new(2, core_id).ipi_hook(send_ipi)).unwrap();
+///
+/// static STOP: AtomicBool = AtomicBool::new(false);
+///
+/// let cpu1 = thread::spawn(|| {
+///     CORE_ID.with(|id| id.store(1, Ordering::Relaxed));
+///     while !STOP.load(Ordering::Relaxed) {
+///         handle_call_ipi();
+///     }
+/// });
+///
+/// let ran_on = AtomicUsize::new(usize::MAX);
+/// run_on_cpu(1, &|| ran_on.store(core_id(), Ordering::Relaxed)).unwrap();
+/// assert_eq!(ran_on.load(Ordering::Relaxed), 1);
+///
+/// STOP.store(true, Ordering::Relaxed);
+/// cpu1.join().unwrap();
+/// ```
+pub fn run_on_cpu(cpu: usize, f: &(dyn Fn() + Sync)) -> Result<(), CallError> {
+    let send_ipi = check(cpu)?;
+
+    if cpu == core_id() {
+        f();
+        return Ok(());
+    }
+
+    post(cpu, f, send_ipi);
+    complete(cpu);
+
+    Ok(())
+}
+
+/// Runs `f` on all CPU cores and waits for all of them to complete.
+///
+/// This corresponds to Linux's `on_each_cpu` with `wait` set.
+/// `f` is posted to all other CPU cores first, then run on the current CPU core.
+///
+/// See [`run_on_cpu`] for details.
+pub fn run_on_all_cpus(f: &(dyn Fn() + Sync)) -> Result<(), CallError> {
+    let send_ipi = check(0)?;
+    let cpu_count = cpu_count().unwrap_or(1);
+    let current = core_id();
+
+    for cpu in (0..cpu_count).filter(|&cpu| cpu != current) {
+        // While waiting for a call slot, other callers' IPIs have to be handled on this CPU core.
+        // This is why interrupts must be enabled here (see `run_on_cpu`).
+        post(cpu, f, send_ipi);
+    }
+
+    f();
+
+    for cpu in (0..cpu_count).filter(|&cpu| cpu != current) {
+        complete(cpu);
+    }
+
+    Ok(())
+}
+
+/// Runs the call posted to the current CPU core, if any.
+///
+/// This has to be called from the IPI handler.
+/// Calling this spuriously is fine.
+pub fn handle_call_ipi() {
+    let cpu = core_id();
+    let Some(slot) = CALL_SLOTS.get(cpu) else {
+        return;
+    };
+
+    if !slot.pending.load(Ordering::Acquire) {
+        return;
+    }
+
+    // SAFETY: The call is pending, so the caller waits for us.
+    if let Some(call) = unsafe { *slot.call.get() } {
+        // SAFETY: The caller keeps `f` alive until we clear `pending`.
+        unsafe { call.as_ref()() };
+    }
+    slot.pending.store(false, Ordering::Release);
+}
@@ -0,0 +1,205 @@
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use crate::relax::{Backoff, Relax};
+use crate::CallOnceError;
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+const POISONED: u8 = 3;
+
+/// A [`CallOnce`](crate::CallOnce) that other CPU cores can wait on until the call has completed.
+///
+/// [`CallOnce::call_once`](crate::CallOnce::call_once) only tells losing callers that the call has started.
+/// [`WaitableCallOnce::wait`] additionally lets them spin until the winning call has returned, so that its effects are visible.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::thread;
+///
+/// use hermit_sync::WaitableCallOnce;
+///
+/// static INIT_PAGING: WaitableCallOnce = WaitableCallOnce::new();
+/// static ROOT_TABLE: AtomicUsize = AtomicUsize::new(0);
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             let _ = INIT_PAGING.call_once(|| ROOT_TABLE.store(0x1000, Ordering::Relaxed));
+///             INIT_PAGING.wait();
+///             assert_eq!(ROOT_TABLE.load(Ordering::Relaxed), 0x1000);
+///         });
+///     }
+/// });
+/// ```
+#[derive(Default, Debug)]
+pub struct WaitableCallOnce {
+    state: AtomicU8,
+}
+
+impl WaitableCallOnce {
+    /// Creates a new call that has not been made yet.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(INCOMPLETE),
+        }
+    }
+
+    /// Calls `f` if this is the first call.
+    ///
+    /// If this has been called before, `f` is not called and [`CallOnceError`] is returned immediately.
+    /// If `f` panics, this is poisoned and [`wait`](Self::wait) panics.
+    #[inline]
+    pub fn call_once<F>(&self, f: F) -> Result<(), CallOnceError>
+    where
+        F: FnOnce(),
+    {
+        self.state
+            .compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| CallOnceError)?;
+
+        struct Poison<'a>(&'a AtomicU8);
+
+        impl Drop for Poison<'_> {
+            fn drop(&mut self) {
+                self.0.store(POISONED, Ordering::Release);
+            }
+        }
+
+        let poison = Poison(&self.state);
+        f();
+        core::mem::forget(poison);
+        self.state.store(COMPLETE, Ordering::Release);
+        Ok(())
+    }
+
+    /// Returns `true` if [`call_once`](Self::call_once) has been called.
+    ///
+    /// The call may still be running.
+    #[inline]
+    pub fn was_called(&self) -> bool {
+        self.state.load(Ordering::Relaxed) != INCOMPLETE
+    }
+
+    /// Returns `true` if the call has completed.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// Waits until the call has completed.
+    ///
+    /// This spins with exponential backoff, also if the call has not been started yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the call panicked.
+    #[inline]
+    pub fn wait(&self) {
+        let mut backoff = Backoff::default();
+        loop {
+            match self.state.load(Ordering::Acquire) {
+                COMPLETE => return,
+                POISONED => panic!("WaitableCallOnce instance has previously been poisoned"),
+                _ => backoff.relax(),
+            }
+        }
+    }
+}
+
+/// A [`CallOnce`](crate::CallOnce) that permits at most `N` calls.
+///
+/// Each successful call is assigned a distinct slot in `0..N`.
+/// This is intended for SMP bring-up, where each CPU core performs its initialization once.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::CallN;
+///
+/// static LOCAL_APIC_INIT: CallN<2> = CallN::new();
+///
+/// assert_eq!(LOCAL_APIC_INIT.call().unwrap(), 0);
+/// assert_eq!(LOCAL_APIC_INIT.call().unwrap(), 1);
+/// assert!(LOCAL_APIC_INIT.call().is_err());
+/// assert_eq!(LOCAL_APIC_INIT.calls(), 2);
+/// ```
+#[derive(Default, Debug)]
+pub struct CallN<const N: usize> {
+    calls: AtomicUsize,
+}
+
+impl<const N: usize> CallN<N> {
+    /// Creates a new instance that has not been called yet.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Claims the next slot and returns its index.
+    ///
+    /// If all `N` slots have been claimed, [`CallOnceError`] is returned.
+    #[inline]
+    pub fn call(&self) -> Result<usize, CallOnceError> {
+        self.calls
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |calls| {
+                (calls < N).then_some(calls + 1)
+            })
+            .map_err(|_| CallOnceError)
+    }
+
+    /// Returns the number of successful calls.
+    #[inline]
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if all `N` slots have been claimed.
+    #[inline]
+    pub fn is_exhausted(&self) -> bool {
+        self.calls() == N
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{panic, thread};
+
+    use super::*;
+
+    #[test]
+    fn poisoned() {
+        let call = WaitableCallOnce::new();
+        let result = panic::catch_unwind(|| call.call_once(|| panic!()));
+        assert!(result.is_err());
+        assert!(call.was_called());
+        assert!(!call.is_completed());
+        assert!(call.call_once(|| {}).is_err());
+        assert!(panic::catch_unwind(|| call.wait()).is_err());
+    }
+
+    #[test]
+    fn distinct_slots() {
+        const N: usize = 4;
+
+        let call = CallN::<N>::new();
+        let mut slots = thread::scope(|s| {
+            let handles = (0..2 * N)
+                .map(|_| s.spawn(|| call.call().ok()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        slots.sort_unstable();
+        assert_eq!(slots, [0, 1, 2, 3]);
+        assert!(call.is_exhausted());
+    }
+}
@@ -0,0 +1,3 @@
+//! Compatibility layers mirroring the APIs of other synchronization libraries.
+
+pub mod std;
@@ -0,0 +1,332 @@
+//! A [`std::sync`]-compatible facade.
+//!
+//! This module provides [`Mutex`] and [`RwLock`] with the same API surface as their counterparts in [`std::sync`].
+//! This allows writing code once that is shared between hosted tests and the kernel.
+//!
+//! These locks never poison.
+//! All methods returning [`LockResult`] or [`TryLockResult`] never return [`PoisonError`].
+//!
+//! [`std::sync`]: https://doc.rust-lang.org/std/sync/index.html
+//!
+//! # Examples
+//!
+//! ```
+//! use hermit_sync::compat::std::{Mutex, RwLock};
+//!
+//! static NUMBER: Mutex<usize> = Mutex::new(0);
+//! static NAMES: RwLock<Vec<&str>> = RwLock::new(Vec::new());
+//!
+//! *NUMBER.lock().unwrap() = 2;
+//! NAMES.write().unwrap().push("Ferris");
+//!
+//! assert_eq!(*NUMBER.lock().unwrap(), 2);
+//! assert_eq!(*NAMES.read().unwrap(), ["Ferris"]);
+//! ```
+
+use core::fmt;
+
+use lock_api::{RawMutex, RawRwLock};
+
+use crate::{RawRwSpinLock, RawSpinMutex};
+
+/// A type alias for the result of a lock method which can be poisoned.
+///
+/// See [`std::sync::LockResult`](https://doc.rust-lang.org/std/sync/type.LockResult.html).
+pub type LockResult<Guard> = Result<Guard, PoisonError<Guard>>;
+
+/// A type alias for the result of a nonblocking locking method.
+///
+/// See [`std::sync::TryLockResult`](https://doc.rust-lang.org/std/sync/type.TryLockResult.html).
+pub type TryLockResult<Guard> = Result<Guard, TryLockError<Guard>>;
+
+/// A type of error which can be returned whenever a lock is acquired.
+///
+/// The locks in this module never poison, so this error is never returned.
+///
+/// See [`std::sync::PoisonError`](https://doc.rust-lang.org/std/sync/struct.PoisonError.html).
+pub struct PoisonError<T> {
+    guard: T,
+}
+
+impl<T> PoisonError<T> {
+    /// Creates a `PoisonError`.
+    pub fn new(guard: T) -> PoisonError<T> {
+        PoisonError { guard }
+    }
+
+    /// Consumes this error indicating that a lock is poisoned, returning the underlying guard.
+    pub fn into_inner(self) -> T {
+        self.guard
+    }
+
+    /// Reaches into this error indicating that a lock is poisoned, returning a reference to the underlying guard.
+    pub fn get_ref(&self) -> &T {
+        &self.guard
+    }
+
+    /// Reaches into this error indicating that a lock is poisoned, returning a mutable reference to the underlying guard.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> fmt::Debug for PoisonError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoisonError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for PoisonError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("poisoned lock: another task failed inside")
+    }
+}
+
+impl<T> core::error::Error for PoisonError<T> {}
+
+/// An enumeration of possible errors associated with a [`TryLockResult`].
+///
+/// See [`std::sync::TryLockError`](https://doc.rust-lang.org/std/sync/enum.TryLockError.html).
+pub enum TryLockError<T> {
+    /// The lock could not be acquired because another thread failed while holding the lock.
+    ///
+    /// The locks in this module never poison, so this variant is never returned.
+    Poisoned(PoisonError<T>),
+    /// The lock could not be acquired at this time because the operation would otherwise block.
+    WouldBlock,
+}
+
+impl<T> From<PoisonError<T>> for TryLockError<T> {
+    fn from(err: PoisonError<T>) -> TryLockError<T> {
+        TryLockError::Poisoned(err)
+    }
+}
+
+impl<T> fmt::Debug for TryLockError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryLockError::Poisoned(err) => fmt::Debug::fmt(err, f),
+            TryLockError::WouldBlock => f.write_str("WouldBlock"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TryLockError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryLockError::Poisoned(err) => fmt::Display::fmt(err, f),
+            TryLockError::WouldBlock => {
+                f.write_str("try_lock failed because the operation would block")
+            }
+        }
+    }
+}
+
+impl<T> core::error::Error for TryLockError<T> {}
+
+/// A [`std::sync::Mutex`]-compatible mutex based on [`lock_api::Mutex`].
+///
+/// [`std::sync::Mutex`]: https://doc.rust-lang.org/std/sync/struct.Mutex.html
+pub struct Mutex<T: ?Sized, R = RawSpinMutex> {
+    inner: lock_api::Mutex<R, T>,
+}
+
+/// A [`lock_api::MutexGuard`] returned by [`Mutex`].
+pub type MutexGuard<'a, T, R = RawSpinMutex> = lock_api::MutexGuard<'a, R, T>;
+
+impl<T, R: RawMutex> Mutex<T, R> {
+    /// Creates a new mutex in an unlocked state ready for use.
+    #[inline]
+    pub const fn new(t: T) -> Self {
+        Self {
+            inner: lock_api::Mutex::new(t),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> LockResult<T> {
+        Ok(self.inner.into_inner())
+    }
+}
+
+impl<T: ?Sized, R: RawMutex> Mutex<T, R> {
+    /// Acquires a mutex, blocking the current thread until it is able to do so.
+    #[inline]
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, T, R>> {
+        Ok(self.inner.lock())
+    }
+
+    /// Attempts to acquire this lock.
+    #[inline]
+    pub fn try_lock(&self) -> TryLockResult<MutexGuard<'_, T, R>> {
+        self.inner.try_lock().ok_or(TryLockError::WouldBlock)
+    }
+
+    /// Determines whether the mutex is poisoned.
+    ///
+    /// This always returns `false`.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        false
+    }
+
+    /// Clear the poisoned state from a mutex.
+    ///
+    /// This does nothing.
+    #[inline]
+    pub fn clear_poison(&self) {}
+
+    /// Returns a mutable reference to the underlying data.
+    #[inline]
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        Ok(self.inner.get_mut())
+    }
+}
+
+impl<T: Default, R: RawMutex> Default for Mutex<T, R> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T, R: RawMutex> From<T> for Mutex<T, R> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, R: RawMutex> fmt::Debug for Mutex<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+/// A [`std::sync::RwLock`]-compatible readers-writer lock based on [`lock_api::RwLock`].
+///
+/// [`std::sync::RwLock`]: https://doc.rust-lang.org/std/sync/struct.RwLock.html
+pub struct RwLock<T: ?Sized, R = RawRwSpinLock> {
+    inner: lock_api::RwLock<R, T>,
+}
+
+/// A [`lock_api::RwLockReadGuard`] returned by [`RwLock`].
+pub type RwLockReadGuard<'a, T, R = RawRwSpinLock> = lock_api::RwLockReadGuard<'a, R, T>;
+
+/// A [`lock_api::RwLockWriteGuard`] returned by [`RwLock`].
+pub type RwLockWriteGuard<'a, T, R = RawRwSpinLock> = lock_api::RwLockWriteGuard<'a, R, T>;
+
+impl<T, R: RawRwLock> RwLock<T, R> {
+    /// Creates a new instance of an `RwLock<T>` which is unlocked.
+    #[inline]
+    pub const fn new(t: T) -> Self {
+        Self {
+            inner: lock_api::RwLock::new(t),
+        }
+    }
+
+    /// Consumes this `RwLock`, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> LockResult<T> {
+        Ok(self.inner.into_inner())
+    }
+}
+
+impl<T: ?Sized, R: RawRwLock> RwLock<T, R> {
+    /// Locks this `RwLock` with shared read access, blocking the current thread until it can be acquired.
+    #[inline]
+    pub fn read(&self) -> LockResult<RwLockReadGuard<'_, T, R>> {
+        Ok(self.inner.read())
+    }
+
+    /// Attempts to acquire this `RwLock` with shared read access.
+    #[inline]
+    pub fn try_read(&self) -> TryLockResult<RwLockReadGuard<'_, T, R>> {
+        self.inner.try_read().ok_or(TryLockError::WouldBlock)
+    }
+
+    /// Locks this `RwLock` with exclusive write access, blocking the current thread until it can be acquired.
+    #[inline]
+    pub fn write(&self) -> LockResult<RwLockWriteGuard<'_, T, R>> {
+        Ok(self.inner.write())
+    }
+
+    /// Attempts to lock this `RwLock` with exclusive write access.
+    #[inline]
+    pub fn try_write(&self) -> TryLockResult<RwLockWriteGuard<'_, T, R>> {
+        self.inner.try_write().ok_or(TryLockError::WouldBlock)
+    }
+
+    /// Determines whether the lock is poisoned.
+    ///
+    /// This always returns `false`.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        false
+    }
+
+    /// Clear the poisoned state from a lock.
+    ///
+    /// This does nothing.
+    #[inline]
+    pub fn clear_poison(&self) {}
+
+    /// Returns a mutable reference to the underlying data.
+    #[inline]
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        Ok(self.inner.get_mut())
+    }
+}
+
+impl<T: Default, R: RawRwLock> Default for RwLock<T, R> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T, R: RawRwLock> From<T> for RwLock<T, R> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, R: RawRwLock> fmt::Debug for RwLock<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutex_try_lock() {
+        let mutex = Mutex::<_>::new(42);
+
+        let guard = mutex.try_lock().unwrap();
+        assert!(matches!(mutex.try_lock(), Err(TryLockError::WouldBlock)));
+        drop(guard);
+
+        assert_eq!(*mutex.try_lock().unwrap(), 42);
+        assert!(!mutex.is_poisoned());
+        assert_eq!(mutex.into_inner().unwrap(), 42);
+    }
+
+    #[test]
+    fn rwlock_try_read_write() {
+        let rwlock = RwLock::<_>::new(42);
+
+        let read = rwlock.try_read().unwrap();
+        assert!(rwlock.try_read().is_ok());
+        assert!(matches!(rwlock.try_write(), Err(TryLockError::WouldBlock)));
+        drop(read);
+
+        *rwlock.try_write().unwrap() += 1;
+        assert_eq!(*rwlock.read().unwrap(), 43);
+        assert_eq!(rwlock.into_inner().unwrap(), 43);
+    }
+}
@@ -0,0 +1,74 @@
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::{mem, ptr};
+
+/// The maximum number of CPU cores supported by per-CPU state of this crate.
+///
+/// This limits the CPU count passed to [`init_smp`](crate::init_smp).
+/// It is also the default CPU count of [`BrLock`](crate::BrLock).
+pub const MAX_CPUS: usize = 64;
+
+static CORE_ID_PROVIDER: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Registers the function that returns the ID of the current CPU core.
+///
+/// Primitives that need to know the current CPU core, such as [`RawOwnedMutex`](crate::RawOwnedMutex), call this function.
+/// Until a provider is registered, [`core_id`] returns `0`.
+/// [`init_smp`](crate::init_smp) also registers this provider.
+///
+/// The provider must return IDs that are unique per CPU core and stable while interrupts are disabled.
+///
+/// # Examples
+///
+/// ```
+/// fn core_id() -> usize {
+///     // Read the core ID from a CPU-local register here.
+///     0
+/// }
+///
+/// hermit_sync::set_core_id_provider(core_id);
+/// assert_eq!(hermit_sync::core_id(), 0);
+/// ```
+pub fn set_core_id_provider(provider: fn() -> usize) {
+    CORE_ID_PROVIDER.store(provider as *mut (), Ordering::Release);
+}
+
+/// Returns the ID of the current CPU core.
+///
+/// This calls the function registered via [`set_core_id_provider`].
+/// If no provider has been registered, this returns `0`.
+#[inline]
+pub fn core_id() -> usize {
+    registered_core_id().unwrap_or(0)
+}
+
+/// Returns the ID of the current CPU core, or `None` if no provider has been registered via [`set_core_id_provider`].
+#[inline]
+pub(crate) fn registered_core_id() -> Option<usize> {
+    let provider = CORE_ID_PROVIDER.load(Ordering::Acquire);
+    if provider.is_null() {
+        return None;
+    }
+
+    // SAFETY: Only `fn() -> usize` are stored in `CORE_ID_PROVIDER`.
+    let provider = unsafe { mem::transmute::<*mut (), fn() -> usize>(provider) };
+    Some(provider())
+}
+
+/// Returns the ID of the current CPU core for per-CPU state that must never be shared between CPU cores.
+///
+/// Before [`init_smp`](crate::init_smp), [`core_id`] might return `0` on every CPU core.
+///
+/// # Panics
+///
+/// Panics if [`init_smp`](crate::init_smp) has not been called or if the core ID is not less than the CPU count.
+#[track_caller]
+#[inline]
+pub(crate) fn checked_core_id() -> usize {
+    let cpu_count = crate::cpu_count().expect("per-CPU state used before init_smp");
+    let cpu = core_id();
+    assert!(
+        cpu < cpu_count,
+        "core ID {cpu} exceeds the CPU count {cpu_count}"
+    );
+    cpu
+}
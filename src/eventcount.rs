@@ -0,0 +1,178 @@
+use core::sync::atomic::{self, AtomicUsize, Ordering};
+
+use crate::relax::{Backoff, Relax};
+
+/// An eventcount for waiting on conditions of lock-free data structures.
+///
+/// An eventcount allows consumers to block on a condition without requiring producers to take a lock on the fast path.
+/// A consumer announces that it is about to wait via [`prepare_wait`], rechecks the condition, and then either waits via [`commit_wait`] or cancels via [`cancel_wait`].
+/// A producer changes the data structure and calls [`notify`] afterward.
+/// If no consumer is waiting, [`notify`] is a single load.
+///
+/// Waiting spins with [exponential backoff].
+///
+/// [`prepare_wait`]: Self::prepare_wait
+/// [`commit_wait`]: Self::commit_wait
+/// [`cancel_wait`]: Self::cancel_wait
+/// [`notify`]: Self::notify
+/// [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use hermit_sync::EventCount;
+///
+/// static READY: AtomicBool = AtomicBool::new(false);
+/// static EVENT: EventCount = EventCount::new();
+///
+/// std::thread::spawn(|| {
+///     READY.store(true, Ordering::Release);
+///     EVENT.notify();
+/// });
+///
+/// loop {
+///     if READY.load(Ordering::Acquire) {
+///         break;
+///     }
+///
+///     let key = EVENT.prepare_wait();
+///     if READY.load(Ordering::Acquire) {
+///         EVENT.cancel_wait(key);
+///         break;
+///     }
+///     EVENT.commit_wait(key);
+/// }
+/// ```
+#[derive(Default, Debug)]
+pub struct EventCount {
+    epoch: AtomicUsize,
+    waiters: AtomicUsize,
+}
+
+/// A key returned by [`EventCount::prepare_wait`].
+///
+/// The key has to be passed to either [`EventCount::commit_wait`] or [`EventCount::cancel_wait`].
+#[must_use = "the key has to be passed to either `commit_wait` or `cancel_wait`"]
+#[derive(Debug)]
+pub struct EventKey {
+    epoch: usize,
+}
+
+impl EventCount {
+    /// Creates a new `EventCount`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            epoch: AtomicUsize::new(0),
+            waiters: AtomicUsize::new(0),
+        }
+    }
+
+    /// Announces that the caller is about to wait.
+    ///
+    /// After calling this, the caller has to recheck its condition.
+    /// If the condition holds, the caller should call [`cancel_wait`](Self::cancel_wait).
+    /// Otherwise, the caller should call [`commit_wait`](Self::commit_wait).
+    #[inline]
+    pub fn prepare_wait(&self) -> EventKey {
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        EventKey { epoch }
+    }
+
+    /// Cancels a wait announced via [`prepare_wait`](Self::prepare_wait).
+    #[inline]
+    pub fn cancel_wait(&self, key: EventKey) {
+        let _ = key;
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Waits until [`notify`](Self::notify) has been called since the corresponding [`prepare_wait`](Self::prepare_wait).
+    #[inline]
+    pub fn commit_wait(&self, key: EventKey) {
+        let mut backoff = Backoff::default();
+        while self.epoch.load(Ordering::Acquire) == key.epoch {
+            backoff.relax();
+        }
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Wakes all waiters.
+    ///
+    /// This has to be called after making the change that waiters are waiting for.
+    #[inline]
+    pub fn notify(&self) {
+        // Order the change that waiters are waiting for before reading `waiters`.
+        // This pairs with the `SeqCst` operations in `prepare_wait`.
+        atomic::fence(Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) != 0 {
+            self.epoch.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    /// Waits until `condition` returns `true`.
+    ///
+    /// This wraps the [`prepare_wait`](Self::prepare_wait) protocol in a loop.
+    #[inline]
+    pub fn wait_until<F>(&self, mut condition: F)
+    where
+        F: FnMut() -> bool,
+    {
+        while !condition() {
+            let key = self.prepare_wait();
+            if condition() {
+                self.cancel_wait(key);
+                return;
+            }
+            self.commit_wait(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn notify_without_waiters() {
+        let event = EventCount::new();
+        event.notify();
+        assert_eq!(event.epoch.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn cancel_wait() {
+        let event = EventCount::new();
+        let key = event.prepare_wait();
+        event.cancel_wait(key);
+        assert_eq!(event.waiters.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn producer_consumer() {
+        const N: usize = 100;
+
+        let event = EventCount::new();
+        let items = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                for _ in 0..N {
+                    items.fetch_add(1, Ordering::Release);
+                    event.notify();
+                }
+            });
+
+            for consumed in 1..=N {
+                event.wait_until(|| items.load(Ordering::Acquire) >= consumed);
+            }
+        });
+
+        assert_eq!(event.waiters.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,306 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+#[cfg(target_has_atomic = "ptr")]
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::atomic::cas;
+#[cfg(target_has_atomic = "ptr")]
+use crate::CallOnce;
+
+/// A cell for accessing static data mutably by one owner at a time.
+///
+/// In contrast to [`ExclusiveCell`](crate::ExclusiveCell), which can be taken only once, exclusive access is handed out as an [`ExclusiveToken`].
+/// The token can be returned via [`ExclusiveToken::give_back`], allowing a later phase to take exclusive access again, for example, after scheduler shutdown.
+/// Dropping the token without giving it back keeps the cell taken forever.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{ExclusiveToken, RetakableCell};
+///
+/// static BOOT_STACK: RetakableCell<[u8; 16]> = RetakableCell::new([0; 16]);
+///
+/// let mut stack = BOOT_STACK.take().unwrap();
+/// stack[0] = 1;
+/// assert!(BOOT_STACK.take().is_none());
+///
+/// ExclusiveToken::give_back(stack);
+/// assert_eq!(BOOT_STACK.take().unwrap()[0], 1);
+/// ```
+pub struct RetakableCell<T: ?Sized> {
+    taken: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: Only one `ExclusiveToken` exists at a time.
+unsafe impl<T: ?Sized + Send> Send for RetakableCell<T> {}
+unsafe impl<T: ?Sized + Send> Sync for RetakableCell<T> {}
+
+impl<T> RetakableCell<T> {
+    /// Creates a new cell containing `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            taken: AtomicBool::new(false),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the cell, returning the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RetakableCell<T> {
+    /// Takes exclusive access to the wrapped value.
+    ///
+    /// Returns `None` if the cell is currently taken.
+    #[inline]
+    #[must_use]
+    pub fn take(&self) -> Option<ExclusiveToken<'_, T>> {
+        cas::compare_exchange(
+            &self.taken,
+            false,
+            true,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .ok()
+        .map(|_| ExclusiveToken { cell: self })
+    }
+
+    /// Returns `true` if the cell is currently taken.
+    #[inline]
+    pub fn is_taken(&self) -> bool {
+        self.taken.load(Ordering::Relaxed)
+    }
+
+    /// Returns a mutable reference to the wrapped value.
+    ///
+    /// This does not require taking the cell, since the `&mut` borrow guarantees exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for RetakableCell<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for RetakableCell<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for RetakableCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetakableCell")
+            .field("taken", &self.is_taken())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Exclusive access to the value of a [`RetakableCell`].
+///
+/// This is returned by [`RetakableCell::take`].
+#[must_use = "dropping the token keeps the cell taken forever; use `ExclusiveToken::give_back` to return it"]
+pub struct ExclusiveToken<'a, T: ?Sized> {
+    cell: &'a RetakableCell<T>,
+}
+
+impl<'a, T: ?Sized> ExclusiveToken<'a, T> {
+    /// Returns exclusive access to the cell, so that it can be taken again.
+    #[inline]
+    pub fn give_back(this: Self) {
+        this.cell.taken.store(false, Ordering::Release);
+    }
+
+    /// Converts this token into a mutable reference that lives as long as the cell, keeping the cell taken forever.
+    #[inline]
+    pub fn leak(this: Self) -> &'a mut T {
+        // SAFETY: The cell is never given back, so this is the only reference.
+        unsafe { &mut *this.cell.data.get() }
+    }
+}
+
+impl<T: ?Sized> Deref for ExclusiveToken<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: We have exclusive access.
+        unsafe { &*self.cell.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for ExclusiveToken<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: We have exclusive access.
+        unsafe { &mut *self.cell.data.get() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ExclusiveToken<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Static storage that is initialized in place and handed out as `&'static mut` exactly once.
+///
+/// This is like [`ExclusiveCell`](crate::ExclusiveCell) but does not need a value at compile time, for example, for driver state.
+/// It is modeled after [`static_cell`](https://docs.rs/static_cell) and built on [`CallOnce`].
+///
+/// The value is never dropped.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::StaticCell;
+///
+/// struct Driver {
+///     base: usize,
+/// }
+///
+/// static DRIVER: StaticCell<Driver> = StaticCell::new();
+///
+/// let driver: &'static mut Driver = DRIVER.init(Driver { base: 0xfee0_0000 });
+/// driver.base += 0x20;
+/// assert!(DRIVER.try_init(Driver { base: 0 }).is_none());
+/// ```
+#[cfg(target_has_atomic = "ptr")]
+pub struct StaticCell<T> {
+    taken: CallOnce,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: The value is only accessed by whoever won `taken`.
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<T: Send> Send for StaticCell<T> {}
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<T: Send> Sync for StaticCell<T> {}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<T> StaticCell<T> {
+    /// Creates a new, uninitialized cell.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            taken: CallOnce::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Initializes the cell with `value` and returns a mutable reference to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell has already been initialized.
+    #[inline]
+    #[must_use]
+    pub fn init(&'static self, value: T) -> &'static mut T {
+        self.try_init(value)
+            .expect("StaticCell instance has already been initialized")
+    }
+
+    /// Initializes the cell with `value` and returns a mutable reference to it.
+    ///
+    /// Returns `None` if the cell has already been initialized.
+    #[inline]
+    #[must_use]
+    pub fn try_init(&'static self, value: T) -> Option<&'static mut T> {
+        self.try_uninit().map(|slot| slot.write(value))
+    }
+
+    /// Returns the uninitialized storage.
+    ///
+    /// This allows constructing large values directly in the static storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell has already been taken.
+    #[inline]
+    #[must_use]
+    pub fn uninit(&'static self) -> &'static mut MaybeUninit<T> {
+        self.try_uninit()
+            .expect("StaticCell instance has already been initialized")
+    }
+
+    /// Returns the uninitialized storage.
+    ///
+    /// Returns `None` if the cell has already been taken.
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    #[must_use]
+    pub fn try_uninit(&'static self) -> Option<&'static mut MaybeUninit<T>> {
+        self.taken.call_once().ok()?;
+        // SAFETY: We won `taken`, so this is the only reference.
+        Some(unsafe { &mut *self.value.get() })
+    }
+
+    /// Returns `true` if the cell has been taken.
+    #[inline]
+    pub fn is_taken(&self) -> bool {
+        self.taken.was_called()
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<T> Default for StaticCell<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<T> fmt::Debug for StaticCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticCell")
+            .field("taken", &self.is_taken())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_cell_once() {
+        static CELL: StaticCell<[usize; 4]> = StaticCell::new();
+
+        let slot = CELL.uninit();
+        let value = slot.write([1; 4]);
+        value[0] = 0;
+        assert!(CELL.is_taken());
+        assert!(CELL.try_uninit().is_none());
+        assert!(CELL.try_init([2; 4]).is_none());
+        assert_eq!(*value, [0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn dropped_token_stays_taken() {
+        let cell = RetakableCell::new(0);
+        let token = cell.take().unwrap();
+        ExclusiveToken::give_back(token);
+
+        let mut token = cell.take().unwrap();
+        *token += 1;
+        drop(token);
+        assert!(cell.is_taken());
+        assert!(cell.take().is_none());
+        assert_eq!(cell.into_inner(), 1);
+    }
+}
@@ -0,0 +1,270 @@
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use core::{fmt, mem, ptr};
+
+use crate::atomic::cas;
+use crate::{set_core_id_provider, PreemptHooks, MAX_CPUS};
+
+/// Whether [`init_smp`] has been entered.
+static INITIALIZING: AtomicBool = AtomicBool::new(false);
+/// Whether [`init_smp`] has completed.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+static CPU_COUNT: AtomicUsize = AtomicUsize::new(0);
+static TIME_SOURCE: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+static YIELD_HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+static IPI_HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+static PREEMPT_HOOKS: AtomicPtr<PreemptHooks> = AtomicPtr::new(ptr::null_mut());
+
+/// The configuration passed to [`init_smp`].
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::SmpConfig;
+///
+/// fn core_id() -> usize {
+///     0
+/// }
+///
+/// fn now_ns() -> u64 {
+///     0
+/// }
+///
+/// let config = SmpConfig::new(4, core_id).time_source(now_ns);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SmpConfig {
+    cpu_count: usize,
+    core_id: fn() -> usize,
+    time_source: Option<fn() -> u64>,
+    yield_hook: Option<fn()>,
+    ipi_hook: Option<fn(usize)>,
+    preempt_hooks: Option<&'static PreemptHooks>,
+}
+
+impl SmpConfig {
+    /// Creates a new configuration for `cpu_count` CPU cores.
+    ///
+    /// `core_id` is registered as described in [`set_core_id_provider`].
+    #[inline]
+    pub const fn new(cpu_count: usize, core_id: fn() -> usize) -> Self {
+        Self {
+            cpu_count,
+            core_id,
+            time_source: None,
+            yield_hook: None,
+            ipi_hook: None,
+            preempt_hooks: None,
+        }
+    }
+
+    /// Sets the function that returns a monotonic timestamp in nanoseconds.
+    ///
+    /// See [`now_ns`].
+    #[inline]
+    pub const fn time_source(mut self, time_source: fn() -> u64) -> Self {
+        self.time_source = Some(time_source);
+        self
+    }
+
+    /// Sets the function that yields the current CPU core to the scheduler.
+    ///
+    /// See [`yield_now`].
+    #[inline]
+    pub const fn yield_hook(mut self, yield_hook: fn()) -> Self {
+        self.yield_hook = Some(yield_hook);
+        self
+    }
+
+    /// Sets the function that sends an inter-processor interrupt to the CPU core with the given ID.
+    ///
+    /// The interrupt handler has to call [`handle_call_ipi`](crate::handle_call_ipi).
+    /// See [`run_on_cpu`](crate::run_on_cpu).
+    #[inline]
+    pub const fn ipi_hook(mut self, ipi_hook: fn(usize)) -> Self {
+        self.ipi_hook = Some(ipi_hook);
+        self
+    }
+
+    /// Sets the scheduler hooks for disabling preemption.
+    ///
+    /// See [`RawPreemptMutex`](crate::RawPreemptMutex).
+    #[inline]
+    pub const fn preempt_hooks(mut self, preempt_hooks: &'static PreemptHooks) -> Self {
+        self.preempt_hooks = Some(preempt_hooks);
+        self
+    }
+}
+
+/// An error returned by [`init_smp`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InitError {
+    /// The crate has already been initialized.
+    AlreadyInitialized,
+    /// The CPU count was zero.
+    NoCpus,
+    /// The CPU count exceeded [`MAX_CPUS`].
+    TooManyCpus,
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyInitialized => "hermit-sync has already been initialized".fmt(f),
+            Self::NoCpus => "the CPU count must not be zero".fmt(f),
+            Self::TooManyCpus => "the CPU count must not exceed MAX_CPUS".fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for InitError {}
+
+/// Initializes this crate for multiple CPU cores.
+///
+/// This registers all hooks of `config` at once.
+/// This crate can only be initialized once.
+/// The CPU count must be between one and [`MAX_CPUS`].
+/// Per-CPU primitives whose state must never be shared between CPU cores, such as [`RawInterruptMcsMutex`](crate::RawInterruptMcsMutex), refuse to work before this.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{init_smp, InitError, SmpConfig};
+///
+/// fn core_id() -> usize {
+///     // Read the core ID from a CPU-local register here.
+///     0
+/// }
+///
+/// assert_eq!(hermit_sync::cpu_count(), None);
+/// assert_eq!(
+///     init_smp(SmpConfig::new(hermit_sync::MAX_CPUS + 1, core_id)),
+///     Err(InitError::TooManyCpus)
+/// );
+/// init_smp(SmpConfig::new(1, core_id)).unwrap();
+/// assert_eq!(hermit_sync::cpu_count(), Some(1));
+///
+/// assert_eq!(
+///     init_smp(SmpConfig::new(1, core_id)),
+///     Err(InitError::AlreadyInitialized)
+/// );
+/// ```
+pub fn init_smp(config: SmpConfig) -> Result<(), InitError> {
+    if config.cpu_count == 0 {
+        return Err(InitError::NoCpus);
+    }
+    if config.cpu_count > MAX_CPUS {
+        return Err(InitError::TooManyCpus);
+    }
+
+    cas::compare_exchange(
+        &INITIALIZING,
+        false,
+        true,
+        Ordering::Acquire,
+        Ordering::Relaxed,
+    )
+    .map_err(|_| InitError::AlreadyInitialized)?;
+
+    set_core_id_provider(config.core_id);
+    CPU_COUNT.store(config.cpu_count, Ordering::Relaxed);
+    if let Some(time_source) = config.time_source {
+        TIME_SOURCE.store(time_source as *mut (), Ordering::Relaxed);
+    }
+    if let Some(yield_hook) = config.yield_hook {
+        YIELD_HOOK.store(yield_hook as *mut (), Ordering::Relaxed);
+    }
+    if let Some(ipi_hook) = config.ipi_hook {
+        IPI_HOOK.store(ipi_hook as *mut (), Ordering::Relaxed);
+    }
+    if let Some(preempt_hooks) = config.preempt_hooks {
+        PREEMPT_HOOKS.store(ptr::from_ref(preempt_hooks).cast_mut(), Ordering::Relaxed);
+    }
+
+    INITIALIZED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Returns `true` if [`init_smp`] has completed.
+#[inline]
+pub fn is_initialized() -> bool {
+    INITIALIZED.load(Ordering::Acquire)
+}
+
+/// Returns the number of CPU cores passed to [`init_smp`].
+///
+/// If this crate has not been initialized, this returns `None`.
+#[inline]
+pub fn cpu_count() -> Option<usize> {
+    is_initialized().then(|| CPU_COUNT.load(Ordering::Relaxed))
+}
+
+/// Returns a monotonic timestamp in nanoseconds.
+///
+/// This calls the time source registered via [`init_smp`].
+/// If no time source has been registered, this returns `None`.
+#[inline]
+pub fn now_ns() -> Option<u64> {
+    if !is_initialized() {
+        return None;
+    }
+
+    let time_source = TIME_SOURCE.load(Ordering::Relaxed);
+    if time_source.is_null() {
+        return None;
+    }
+
+    // SAFETY: Only `fn() -> u64` are stored in `TIME_SOURCE`.
+    let time_source = unsafe { mem::transmute::<*mut (), fn() -> u64>(time_source) };
+    Some(time_source())
+}
+
+/// Yields the current CPU core to the scheduler.
+///
+/// This calls the yield hook registered via [`init_smp`].
+/// If no yield hook has been registered, this emits [`core::hint::spin_loop`].
+#[inline]
+pub fn yield_now() {
+    let yield_hook = if is_initialized() {
+        YIELD_HOOK.load(Ordering::Relaxed)
+    } else {
+        ptr::null_mut()
+    };
+
+    if yield_hook.is_null() {
+        core::hint::spin_loop();
+        return;
+    }
+
+    // SAFETY: Only `fn()` are stored in `YIELD_HOOK`.
+    let yield_hook = unsafe { mem::transmute::<*mut (), fn()>(yield_hook) };
+    yield_hook();
+}
+
+/// Returns the IPI hook registered via [`init_smp`].
+#[cfg(target_has_atomic = "ptr")]
+#[inline]
+pub(crate) fn ipi_hook() -> Option<fn(usize)> {
+    if !is_initialized() {
+        return None;
+    }
+
+    let ipi_hook = IPI_HOOK.load(Ordering::Relaxed);
+    if ipi_hook.is_null() {
+        return None;
+    }
+
+    // SAFETY: Only `fn(usize)` are stored in `IPI_HOOK`.
+    Some(unsafe { mem::transmute::<*mut (), fn(usize)>(ipi_hook) })
+}
+
+/// Returns the preemption hooks registered via [`init_smp`].
+#[inline]
+pub(crate) fn preempt_hooks() -> Option<&'static PreemptHooks> {
+    if !is_initialized() {
+        return None;
+    }
+
+    let preempt_hooks = PREEMPT_HOOKS.load(Ordering::Relaxed);
+    // SAFETY: Only `&'static PreemptHooks` are stored in `PREEMPT_HOOKS`.
+    unsafe { preempt_hooks.as_ref() }
+}
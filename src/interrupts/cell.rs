@@ -0,0 +1,132 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::{InterruptControl, NativeInterrupts};
+
+/// A cell whose data is protected only by disabling interrupts.
+///
+/// [`with`](Self::with) disables interrupts via [`InterruptControl`] `C` for the duration of the closure.
+/// In contrast to [`InterruptMutex`](crate::InterruptMutex), there are no atomics involved.
+/// Thus, the cell must only ever be accessed by one and the same CPU core.
+/// This suits, for example, per-CPU scheduler run queues accessed from task and interrupt context.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::InterruptCell;
+///
+/// // SAFETY: Only the boot CPU core accesses this cell.
+/// static RUN_QUEUE_LEN: InterruptCell<usize> = unsafe { InterruptCell::new(0) };
+///
+/// RUN_QUEUE_LEN.with(|len| *len += 1);
+/// assert_eq!(RUN_QUEUE_LEN.with(|len| *len), 1);
+/// ```
+pub struct InterruptCell<T, C = NativeInterrupts> {
+    /// Whether [`with`](Self::with) is currently running.
+    borrowed: UnsafeCell<bool>,
+    value: UnsafeCell<T>,
+    _control: PhantomData<C>,
+}
+
+// SAFETY: The creator guarantees that the cell is only ever accessed by one and the same CPU core.
+unsafe impl<T: Send, C> Sync for InterruptCell<T, C> {}
+
+impl<T, C> InterruptCell<T, C> {
+    /// Creates a new cell containing `value`.
+    ///
+    /// # Safety
+    ///
+    /// The cell must only ever be accessed by one and the same CPU core.
+    /// Disabling interrupts does not keep other CPU cores out.
+    #[inline]
+    pub const unsafe fn new(value: T) -> Self {
+        Self {
+            borrowed: UnsafeCell::new(false),
+            value: UnsafeCell::new(value),
+            _control: PhantomData,
+        }
+    }
+
+    /// Returns a mutable reference to the wrapped value.
+    ///
+    /// This does not disable interrupts, since the `&mut` borrow guarantees exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Consumes the cell, returning the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T, C: InterruptControl> InterruptCell<T, C> {
+    /// Runs `f` with a mutable reference to the wrapped value and disabled interrupts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within `f`.
+    #[inline]
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        struct Borrow<'a, C: InterruptControl> {
+            borrowed: &'a UnsafeCell<bool>,
+            flags: C::Flags,
+        }
+
+        impl<C: InterruptControl> Drop for Borrow<'_, C> {
+            #[inline]
+            fn drop(&mut self) {
+                // SAFETY: Interrupts are still disabled on the only CPU core accessing this cell.
+                unsafe {
+                    *self.borrowed.get() = false;
+                }
+                C::restore(self.flags);
+            }
+        }
+
+        let flags = C::save_disable();
+        // SAFETY: Interrupts are disabled on the only CPU core accessing this cell.
+        if unsafe { self.borrowed.get().replace(true) } {
+            C::restore(flags);
+            panic!("InterruptCell is already borrowed");
+        }
+        let _borrow = Borrow::<C> {
+            borrowed: &self.borrowed,
+            flags,
+        };
+
+        // SAFETY: We are the only borrow on the only CPU core accessing this cell.
+        f(unsafe { &mut *self.value.get() })
+    }
+}
+
+impl<T, C> fmt::Debug for InterruptCell<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterruptCell").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+
+    #[test]
+    fn reentrant_with_panics() {
+        // SAFETY: Only this thread accesses this cell.
+        let cell = unsafe { InterruptCell::<usize>::new(0) };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            cell.with(|_| cell.with(|value| *value += 1));
+        }));
+        assert!(result.is_err());
+        cell.with(|value| *value += 1);
+        assert_eq!(cell.into_inner(), 1);
+    }
+}
@@ -0,0 +1,63 @@
+use core::arch::asm;
+
+pub type Flags = u32;
+
+#[inline]
+pub fn read_disable() -> Flags {
+    let cpsr: Flags;
+    unsafe {
+        asm!(
+            "mrs {}, cpsr",
+            "cpsid if",
+            out(reg) cpsr,
+            // Omit `nomem` to imitate a lock acquire.
+            // Otherwise, the compiler is free to move
+            // reads and writes through this asm block.
+            options(preserves_flags, nostack)
+        );
+    }
+    cpsr
+}
+
+#[inline]
+pub fn restore(cpsr: Flags) {
+    unsafe {
+        asm!(
+            // Only write the control field, which contains the I and F bits.
+            "msr cpsr_c, {}",
+            in(reg) cpsr,
+            // Omit `nomem` to imitate a lock release.
+            // Otherwise, the compiler is free to move
+            // reads and writes through this asm block.
+            options(preserves_flags, nostack)
+        );
+    }
+}
+
+#[inline]
+pub fn read() -> Flags {
+    let cpsr: Flags;
+    unsafe {
+        asm!(
+            "mrs {}, cpsr",
+            out(reg) cpsr,
+            options(nomem, preserves_flags, nostack)
+        );
+    }
+    cpsr
+}
+
+#[inline]
+pub fn are_enabled() -> bool {
+    const IRQ_MASK: Flags = 1 << 7;
+
+    let cpsr: Flags;
+    unsafe {
+        asm!(
+            "mrs {}, cpsr",
+            out(reg) cpsr,
+            options(nomem, preserves_flags, nostack)
+        );
+    }
+    cpsr & IRQ_MASK == 0
+}
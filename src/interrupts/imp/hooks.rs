@@ -0,0 +1,115 @@
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+pub type Flags = usize;
+
+static INTERRUPT_HOOKS: AtomicPtr<InterruptHooks> = AtomicPtr::new(ptr::null_mut());
+
+/// User-provided hooks for controlling interrupts.
+///
+/// With the `interrupt-hooks` feature, these hooks replace the native interrupt backends of this crate.
+/// This allows architectures that are not supported by this crate to supply their own interrupt handling.
+/// Until hooks are registered, interrupts are not touched.
+#[derive(Clone, Copy, Debug)]
+pub struct InterruptHooks {
+    /// Disables interrupts and returns the previous interrupt state.
+    pub read_disable: fn() -> usize,
+
+    /// Restores the interrupt state returned by [`read_disable`](Self::read_disable).
+    pub restore: fn(flags: usize),
+
+    /// Returns the current interrupt state without changing it.
+    ///
+    /// The returned state must be accepted by [`restore`](Self::restore).
+    pub read: fn() -> usize,
+
+    /// Returns `true` if interrupts are enabled.
+    pub are_enabled: fn() -> bool,
+}
+
+/// Registers hooks for controlling interrupts.
+///
+/// Flags saved before registering are restored via the new hooks, so hooks should be registered before interrupts are first disabled.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicBool, Ordering};
+///
+/// use hermit_sync::InterruptHooks;
+///
+/// /// The interrupt enable bit of our made-up architecture.
+/// static ENABLED: AtomicBool = AtomicBool::new(true);
+///
+/// static INTERRUPT_HOOKS: InterruptHooks = InterruptHooks {
+///     read_disable: || usize::from(ENABLED.swap(false, Ordering::Acquire)),
+///     restore: |flags| ENABLED.store(flags != 0, Ordering::Release),
+///     read: || usize::from(ENABLED.load(Ordering::Relaxed)),
+///     are_enabled: || ENABLED.load(Ordering::Relaxed),
+/// };
+///
+/// hermit_sync::set_interrupt_hooks(&INTERRUPT_HOOKS);
+/// ```
+pub fn set_interrupt_hooks(hooks: &'static InterruptHooks) {
+    INTERRUPT_HOOKS.store(ptr::from_ref(hooks).cast_mut(), Ordering::Release);
+}
+
+#[inline]
+fn hooks() -> Option<&'static InterruptHooks> {
+    let hooks = INTERRUPT_HOOKS.load(Ordering::Acquire);
+    // SAFETY: Only `&'static InterruptHooks` are stored in `INTERRUPT_HOOKS`.
+    unsafe { hooks.as_ref() }
+}
+
+#[inline]
+pub fn read_disable() -> Flags {
+    hooks().map_or(0, |hooks| (hooks.read_disable)())
+}
+
+#[inline]
+pub fn restore(flags: Flags) {
+    if let Some(hooks) = hooks() {
+        (hooks.restore)(flags);
+    }
+}
+
+#[inline]
+pub fn read() -> Flags {
+    hooks().map_or(0, |hooks| (hooks.read)())
+}
+
+#[inline]
+pub fn are_enabled() -> bool {
+    hooks().is_some_and(|hooks| (hooks.are_enabled)())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    thread_local! {
+        static ENABLED: Cell<bool> = const { Cell::new(true) };
+    }
+
+    static INTERRUPT_HOOKS: InterruptHooks = InterruptHooks {
+        read_disable: || usize::from(ENABLED.replace(false)),
+        restore: |flags| ENABLED.set(flags != 0),
+        read: || usize::from(ENABLED.get()),
+        are_enabled: || ENABLED.get(),
+    };
+
+    #[test]
+    fn registered_hooks() {
+        set_interrupt_hooks(&INTERRUPT_HOOKS);
+
+        assert!(!crate::irqs_disabled());
+        crate::without_interrupts(|| {
+            assert!(crate::irqs_disabled());
+            crate::without_interrupts(|| assert!(crate::irqs_disabled()));
+            assert!(crate::irqs_disabled());
+        });
+        assert!(!crate::irqs_disabled());
+    }
+}
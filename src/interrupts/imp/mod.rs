@@ -0,0 +1,30 @@
+cfg_if::cfg_if! {
+    if #[cfg(feature = "interrupt-hooks")] {
+        mod hooks;
+        pub use self::hooks::*;
+    } else if #[cfg(all(unix, not(miri)))] {
+        mod unix;
+        pub use self::unix::*;
+    } else if #[cfg(all(any(target_os = "none", target_os = "uefi"), target_arch = "aarch64"))] {
+        mod aarch64;
+        pub use self::aarch64::*;
+    } else if #[cfg(all(any(target_os = "none", target_os = "uefi"), target_arch = "arm", not(target_feature = "mclass")))] {
+        mod arm;
+        pub use self::arm::*;
+    } else if #[cfg(all(any(target_os = "none", target_os = "uefi"), target_arch = "powerpc64"))] {
+        mod powerpc64;
+        pub use self::powerpc64::*;
+    } else if #[cfg(all(any(target_os = "none", target_os = "uefi"), any(target_arch = "riscv32", target_arch = "riscv64")))] {
+        mod riscv;
+        pub use self::riscv::*;
+    } else if #[cfg(all(any(target_os = "none", target_os = "uefi"), target_arch = "x86_64"))] {
+        mod x86_64;
+        pub use self::x86_64::*;
+    } else if #[cfg(all(any(target_os = "none", target_os = "uefi"), target_arch = "x86"))] {
+        mod x86;
+        pub use self::x86::*;
+    } else {
+        mod unsupported;
+        pub use self::unsupported::*;
+    }
+}
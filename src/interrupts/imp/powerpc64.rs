@@ -0,0 +1,67 @@
+use core::arch::asm;
+
+pub type Flags = u64;
+
+/// External interrupt enable
+const MSR_EE: Flags = 1 << 15;
+
+#[inline]
+pub fn read_disable() -> Flags {
+    let msr: Flags;
+    unsafe {
+        asm!(
+            "mfmsr {msr}",
+            "andc {tmp}, {msr}, {ee}",
+            // With L = 1, only MSR.EE and MSR.RI are written.
+            "mtmsrd {tmp}, 1",
+            msr = out(reg) msr,
+            tmp = out(reg) _,
+            ee = in(reg) MSR_EE,
+            // Omit `nomem` to imitate a lock acquire.
+            // Otherwise, the compiler is free to move
+            // reads and writes through this asm block.
+            options(preserves_flags, nostack)
+        );
+    }
+    msr
+}
+
+#[inline]
+pub fn restore(msr: Flags) {
+    unsafe {
+        asm!(
+            "mtmsrd {}, 1",
+            in(reg) msr,
+            // Omit `nomem` to imitate a lock release.
+            // Otherwise, the compiler is free to move
+            // reads and writes through this asm block.
+            options(preserves_flags, nostack)
+        );
+    }
+}
+
+#[inline]
+pub fn read() -> Flags {
+    let msr: Flags;
+    unsafe {
+        asm!(
+            "mfmsr {}",
+            out(reg) msr,
+            options(nomem, preserves_flags, nostack)
+        );
+    }
+    msr
+}
+
+#[inline]
+pub fn are_enabled() -> bool {
+    let msr: Flags;
+    unsafe {
+        asm!(
+            "mfmsr {}",
+            out(reg) msr,
+            options(nomem, preserves_flags, nostack)
+        );
+    }
+    msr & MSR_EE != 0
+}
@@ -0,0 +1,52 @@
+use nix::sys::signal::{SigSet, SigmaskHow, Signal};
+
+pub type Flags = SigSet;
+
+#[inline]
+pub fn read_disable() -> Flags {
+    SigSet::all()
+        .thread_swap_mask(SigmaskHow::SIG_SETMASK)
+        .unwrap()
+}
+
+#[inline]
+pub fn restore(flags: Flags) {
+    flags.thread_set_mask().unwrap();
+}
+
+#[inline]
+pub fn read() -> Flags {
+    SigSet::thread_get_mask().unwrap()
+}
+
+#[inline]
+pub fn are_enabled() -> bool {
+    // `read_disable` blocks all signals, so checking one is enough.
+    !SigSet::thread_get_mask().unwrap().contains(Signal::SIGINT)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn signals() {
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        use nix::libc;
+        use nix::sys::signal::{self, SigHandler, Signal};
+
+        static HANDLER_RAN: AtomicBool = AtomicBool::new(false);
+
+        extern "C" fn handle_sigint(_signal: libc::c_int) {
+            HANDLER_RAN.store(true, Ordering::Relaxed);
+        }
+
+        let handler = SigHandler::Handler(handle_sigint);
+        unsafe { signal::signal(Signal::SIGINT, handler) }.unwrap();
+
+        let flags = crate::local_irq_save();
+        signal::raise(Signal::SIGINT).unwrap();
+        assert!(!HANDLER_RAN.load(Ordering::Relaxed));
+        crate::local_irq_restore(flags);
+        assert!(HANDLER_RAN.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,15 @@
+pub type Flags = ();
+
+#[inline]
+pub fn read_disable() -> Flags {}
+
+#[inline]
+pub fn restore(_flags: Flags) {}
+
+#[inline]
+pub fn read() -> Flags {}
+
+#[inline]
+pub fn are_enabled() -> bool {
+    false
+}
@@ -0,0 +1,61 @@
+use core::arch::asm;
+
+pub type Flags = bool;
+
+const INTERRUPT_FLAG: u32 = 1 << 9;
+
+#[inline]
+pub fn read_disable() -> Flags {
+    let eflags: u32;
+
+    unsafe {
+        asm!(
+            "pushfd",
+            "pop {}",
+            "cli",
+            out(reg) eflags,
+            // Omit `nomem` to imitate a lock acquire.
+            // Otherwise, the compiler is free to move
+            // reads and writes through this asm block.
+            options(preserves_flags)
+        );
+    }
+
+    (eflags & INTERRUPT_FLAG) == INTERRUPT_FLAG
+}
+
+#[inline]
+pub fn restore(enable: Flags) {
+    if enable {
+        unsafe {
+            asm!(
+                "sti",
+                // Omit `nomem` to imitate a lock acquire.
+                // Otherwise, the compiler is free to move
+                // reads and writes through this asm block.
+                options(preserves_flags)
+            );
+        }
+    }
+}
+
+#[inline]
+pub fn read() -> Flags {
+    are_enabled()
+}
+
+#[inline]
+pub fn are_enabled() -> bool {
+    let eflags: u32;
+
+    unsafe {
+        asm!(
+            "pushfd",
+            "pop {}",
+            out(reg) eflags,
+            options(nomem, preserves_flags)
+        );
+    }
+
+    (eflags & INTERRUPT_FLAG) == INTERRUPT_FLAG
+}
@@ -0,0 +1,457 @@
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use crate::now_ns;
+
+pub(crate) mod cell;
+mod imp;
+pub(crate) mod nested;
+pub(crate) mod ref_cell;
+
+#[cfg(feature = "interrupt-hooks")]
+pub use imp::{set_interrupt_hooks, InterruptHooks};
+
+static MANAGED: AtomicBool = AtomicBool::new(true);
+
+/// Sets whether this crate manages interrupts.
+///
+/// While interrupts are unmanaged, [`local_irq_save`] and [`local_irq_restore`] do not touch the interrupt state.
+/// This also applies to [`without_interrupts`] and [`RawInterruptMutex`](crate::RawInterruptMutex).
+/// Flags saved while interrupts are unmanaged are not restored, even if interrupts are managed by then.
+///
+/// Interrupts are managed by default.
+/// Kernels can unmanage them at the beginning of early boot, where manipulating the interrupt state might fault or be meaningless, and manage them once the exception vectors are installed.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{set_interrupts_managed, InterruptSpinMutex};
+///
+/// static NUMBER: InterruptSpinMutex<usize> = InterruptSpinMutex::new(0);
+///
+/// set_interrupts_managed(false);
+/// // interrupts are not touched
+/// *NUMBER.lock() = 1;
+///
+/// // Install exception vectors here.
+///
+/// set_interrupts_managed(true);
+/// // interrupts are disabled while locked
+/// *NUMBER.lock() = 2;
+/// ```
+#[inline]
+pub fn set_interrupts_managed(managed: bool) {
+    MANAGED.store(managed, Ordering::Release);
+}
+
+/// Returns `true` if this crate manages interrupts.
+///
+/// See [`set_interrupts_managed`].
+#[inline]
+pub fn interrupts_managed() -> bool {
+    MANAGED.load(Ordering::Acquire)
+}
+
+/// The saved interrupt state of the current CPU.
+///
+/// This is the interrupt state of the current CPU on bare-metal targets and the signal mask of the current thread on Unix.
+/// If interrupts were unmanaged (see [`set_interrupts_managed`]) when saving, this is empty.
+#[derive(Clone, Copy, Debug)]
+pub struct Flags {
+    inner: Option<imp::Flags>,
+}
+
+impl Flags {
+    /// Flags that do not touch the interrupt state when restored.
+    pub(crate) const EMPTY: Self = Self { inner: None };
+}
+
+/// Disables interrupts and returns the previous interrupt state.
+///
+/// This corresponds to Linux's `local_irq_save`.
+/// The returned [`Flags`] have to be passed to [`local_irq_restore`] on the same CPU to restore the previous state.
+///
+/// Prefer [`without_interrupts`] where possible.
+/// This function is intended for FFI and assembly-adjacent code that cannot use closures.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{local_irq_restore, local_irq_save};
+///
+/// // interrupts may or may not be enabled
+/// let flags = local_irq_save();
+/// // interrupts are disabled
+/// local_irq_restore(flags);
+/// // interrupts are restored to the previous state
+/// ```
+#[inline]
+pub fn local_irq_save() -> Flags {
+    let inner = interrupts_managed().then(imp::read_disable);
+    Flags { inner }
+}
+
+/// Restores the interrupt state saved by [`local_irq_save`].
+///
+/// This corresponds to Linux's `local_irq_restore`.
+///
+/// Restoring flags in the wrong order or on a different CPU may enable interrupts prematurely.
+#[inline]
+pub fn local_irq_restore(flags: Flags) {
+    if let Some(flags) = flags.inner {
+        #[allow(clippy::unit_arg)]
+        imp::restore(flags);
+    }
+}
+
+/// Returns `true` if interrupts are disabled on the current CPU.
+///
+/// This corresponds to Linux's `irqs_disabled`.
+/// On Unix, this checks the signal mask of the current thread.
+/// On targets where this crate does not touch interrupts, this returns `true`.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{irqs_disabled, without_interrupts};
+///
+/// without_interrupts(|| assert!(irqs_disabled()));
+/// ```
+#[inline]
+pub fn irqs_disabled() -> bool {
+    !imp::are_enabled()
+}
+
+/// Returns `true` if interrupts are enabled on the current CPU.
+///
+/// This is the inverse of [`irqs_disabled`].
+/// On targets where this crate does not touch interrupts, this returns `false`.
+#[inline]
+pub fn interrupts_enabled() -> bool {
+    imp::are_enabled()
+}
+
+/// Returns the current interrupt state without changing it.
+///
+/// The returned [`Flags`] can be passed to [`local_irq_restore`] on the same CPU to re-enable interrupts if they were enabled when reading them.
+/// This does not set a known state, though: restoring flags from a region with disabled interrupts is not guaranteed to disable interrupts again.
+/// On some architectures, such as x86 and RISC-V, restoring only ever enables interrupts.
+/// If interrupts are unmanaged (see [`set_interrupts_managed`]), this is empty.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{interrupts_enabled, local_irq_restore, local_irq_save, read_flags};
+///
+/// let enabled = interrupts_enabled();
+/// let flags = read_flags();
+///
+/// let saved = local_irq_save();
+/// local_irq_restore(flags);
+/// assert_eq!(interrupts_enabled(), enabled);
+/// # local_irq_restore(saved);
+/// ```
+#[inline]
+pub fn read_flags() -> Flags {
+    let inner = interrupts_managed().then(imp::read);
+    Flags { inner }
+}
+
+/// A way of controlling interrupts.
+///
+/// This is implemented by [`NativeInterrupts`], which controls interrupts via [`local_irq_save`] and [`local_irq_restore`].
+/// Hypervisor guests with paravirtual interrupt interfaces can implement this trait to save and restore their interrupt state instead of the CPU's.
+/// Other state that is disabled while locked can be controlled this way, too, such as preemption via [`PreemptControl`](crate::PreemptControl).
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use hermit_sync::{InterruptControl, RawInterruptMutex, RawSpinMutex};
+///
+/// /// The event channel upcall mask in the shared info page.
+/// static UPCALL_MASK: AtomicBool = AtomicBool::new(false);
+///
+/// struct XenInterrupts;
+///
+/// impl InterruptControl for XenInterrupts {
+///     type Flags = bool;
+///
+///     fn save_disable() -> Self::Flags {
+///         UPCALL_MASK.swap(true, Ordering::Acquire)
+///     }
+///
+///     fn restore(flags: Self::Flags) {
+///         UPCALL_MASK.store(flags, Ordering::Release);
+///     }
+/// }
+///
+/// type XenMutex<T> = lock_api::Mutex<RawInterruptMutex<RawSpinMutex, XenInterrupts>, T>;
+///
+/// static NUMBER: XenMutex<usize> = XenMutex::new(0);
+///
+/// let guard = NUMBER.lock();
+/// assert!(UPCALL_MASK.load(Ordering::Relaxed));
+/// drop(guard);
+/// assert!(!UPCALL_MASK.load(Ordering::Relaxed));
+/// ```
+pub trait InterruptControl {
+    /// The saved interrupt state.
+    type Flags: Copy;
+
+    /// Disables interrupts and returns the previous interrupt state.
+    fn save_disable() -> Self::Flags;
+
+    /// Restores the interrupt state saved by [`save_disable`](Self::save_disable).
+    fn restore(flags: Self::Flags);
+}
+
+/// The native [`InterruptControl`] of the current CPU.
+///
+/// This uses [`local_irq_save`] and [`local_irq_restore`].
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NativeInterrupts;
+
+impl InterruptControl for NativeInterrupts {
+    type Flags = Flags;
+
+    #[inline]
+    fn save_disable() -> Self::Flags {
+        local_irq_save()
+    }
+
+    #[inline]
+    fn restore(flags: Self::Flags) {
+        local_irq_restore(flags);
+    }
+}
+
+/// Run a closure with disabled interrupts if `cond` is `true`.
+///
+/// If `cond` is `true`, this behaves like [`without_interrupts`].
+/// Otherwise, the closure is run without touching the interrupt state.
+///
+/// This is useful for code paths that are sometimes run before interrupts are set up or inside of regions that already have interrupts disabled.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::without_interrupts_if;
+///
+/// let smp = true;
+/// let answer = without_interrupts_if(smp, || {
+///     // interrupts are disabled if `smp` is `true`
+///     42
+/// });
+/// assert_eq!(answer, 42);
+/// ```
+pub fn without_interrupts_if<F, R>(cond: bool, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    if cond {
+        without_interrupts(f)
+    } else {
+        f()
+    }
+}
+
+/// An interrupt guard.
+///
+/// While an instance of this guard is held, interrupts are disabled.
+/// When this guard is dropped, interrupts are restored to the state before disabling.
+///
+/// In contrast to [`without_interrupts`], this guard can be held across early returns and stored in other structs, such as other guards.
+/// Guards have to be dropped in reverse order of creation.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{irqs_disabled, InterruptGuard};
+///
+/// fn poll(ready: bool) -> Option<usize> {
+///     let _guard = InterruptGuard::disable();
+///     if !ready {
+///         return None;
+///     }
+///     assert!(irqs_disabled());
+///     Some(42)
+/// }
+///
+/// assert_eq!(poll(true), Some(42));
+/// ```
+// Adapted from `interrupts::Guard`.
+#[must_use = "if unused interrupts will be restored immediately"]
+#[derive(Debug)]
+pub struct InterruptGuard {
+    flags: Flags,
+    /// Interrupts are per hardware thread.
+    ///
+    /// Making InterruptGuard `!Send` avoids disabling interrupts on one hardware thread and restoring on another.
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl InterruptGuard {
+    /// Disables interrupts, returning a guard that restores the previous interrupt state on drop.
+    #[inline]
+    pub fn disable() -> Self {
+        Self {
+            flags: local_irq_save(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Runs a closure with the interrupt state from before disabling.
+    ///
+    /// If interrupts were enabled before [`disable`](Self::disable), they are enabled while running the closure and disabled again afterward.
+    /// This is intended for long operations inside of interrupt-free regions, such as waiting for other CPU cores, where keeping interrupts disabled the whole time would hurt latency.
+    ///
+    /// # Safety
+    ///
+    /// Nothing that relies on interrupts being disabled may be in use while running the closure.
+    /// In particular, no [`RawInterruptMutex`](crate::RawInterruptMutex) that was locked after this guard was created may be held, since interrupt handlers on this CPU core might deadlock on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::{irqs_disabled, InterruptGuard};
+    ///
+    /// let enabled = !irqs_disabled();
+    ///
+    /// let mut guard = InterruptGuard::disable();
+    /// // SAFETY: Nothing relies on interrupts being disabled here.
+    /// unsafe {
+    ///     guard.with_interrupts_enabled(|| {
+    ///         // Wait for other CPU cores here.
+    ///         assert_eq!(!irqs_disabled(), enabled);
+    ///     });
+    /// }
+    /// assert!(irqs_disabled());
+    /// drop(guard);
+    /// ```
+    #[inline]
+    pub unsafe fn with_interrupts_enabled<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        local_irq_restore(self.flags);
+
+        let ret = f();
+
+        self.flags = local_irq_save();
+
+        ret
+    }
+}
+
+impl Drop for InterruptGuard {
+    #[inline]
+    fn drop(&mut self) {
+        local_irq_restore(self.flags);
+    }
+}
+
+/// Run a closure with disabled interrupts.
+///
+/// Run the given closure, disabling interrupts before running it (if they aren't already disabled).
+/// Afterward, interrupts are enabled again if they were enabled before.
+///
+/// If you have other `enable` and `disable` calls _within_ the closure, things may not work as expected.
+/// If the closure panics, interrupts are restored while unwinding.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::without_interrupts;
+///
+/// // interrupts may or may not be enabled
+/// without_interrupts(|| {
+///     // interrupts are disabled
+/// });
+/// // interrupts are restored to the previous state
+/// ```
+// Docs adapted from `interrupts::without`.
+#[inline]
+pub fn without_interrupts<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let guard = InterruptGuard::disable();
+
+    let ret = f();
+
+    drop(guard);
+
+    ret
+}
+
+/// Run a closure with disabled interrupts and measure how long interrupts were disabled.
+///
+/// This behaves like [`without_interrupts`] and additionally returns the time spent with disabled interrupts.
+/// The time is measured via the time source registered with [`init_smp`](crate::init_smp).
+/// If no time source has been registered, the duration is `None`.
+///
+/// This allows enforcing budgets on critical sections.
+///
+/// # Examples
+///
+/// ```
+/// use core::time::Duration;
+///
+/// use hermit_sync::without_interrupts_timed;
+///
+/// let (answer, duration) = without_interrupts_timed(|| {
+///     // interrupts are disabled
+///     42
+/// });
+/// assert_eq!(answer, 42);
+///
+/// if let Some(duration) = duration {
+///     if duration > Duration::from_micros(100) {
+///         // log the offender
+///     }
+/// }
+/// ```
+#[inline]
+pub fn without_interrupts_timed<F, R>(f: F) -> (R, Option<Duration>)
+where
+    F: FnOnce() -> R,
+{
+    let guard = InterruptGuard::disable();
+
+    let start = now_ns();
+    let ret = f();
+    let end = now_ns();
+
+    drop(guard);
+
+    let duration = start
+        .zip(end)
+        .map(|(start, end)| Duration::from_nanos(end.saturating_sub(start)));
+    (ret, duration)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(unix, not(miri), not(feature = "interrupt-hooks")))]
+    #[test]
+    fn without_interrupts_unwind() {
+        use std::panic;
+
+        use nix::sys::signal::SigSet;
+
+        let mask = SigSet::thread_get_mask().unwrap();
+
+        let result = panic::catch_unwind(|| {
+            super::without_interrupts(|| {
+                assert_ne!(SigSet::thread_get_mask().unwrap(), mask);
+                panic!("unwinding");
+            })
+        });
+
+        assert!(result.is_err());
+        assert_eq!(SigSet::thread_get_mask().unwrap(), mask);
+    }
+}
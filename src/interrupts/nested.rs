@@ -0,0 +1,211 @@
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+use crate::cpu::checked_core_id;
+use crate::{local_irq_restore, local_irq_save, Flags, InterruptControl, MAX_CPUS};
+
+/// The interrupt nesting state of a CPU core for [`NestedInterrupts`].
+///
+/// Each CPU core needs its own state, which is selected via [`IrqNestingStorage`].
+pub struct IrqNesting {
+    /// The number of regions with disabled interrupts on this CPU core.
+    depth: Cell<usize>,
+    /// The interrupt state from before the outermost region.
+    flags: UnsafeCell<MaybeUninit<Flags>>,
+}
+
+// SAFETY: `IrqNestingStorage` ensures that each state is only accessed by its CPU core with disabled interrupts.
+unsafe impl Sync for IrqNesting {}
+
+impl IrqNesting {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self::new();
+
+    /// Creates a new nesting state without any regions.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            depth: Cell::new(0),
+            flags: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+}
+
+impl Default for IrqNesting {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for IrqNesting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IrqNesting").finish_non_exhaustive()
+    }
+}
+
+/// A way of locating the [`IrqNesting`] state of the current CPU core for [`NestedInterrupts`].
+///
+/// This allows keeping the nesting state in CPU-local storage, such as a per-CPU area addressed via a segment or thread pointer register.
+///
+/// # Safety
+///
+/// While interrupts are disabled, [`current`](Self::current) must always return the same state on the same CPU core.
+/// Different CPU cores must never get the same state.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{IrqNesting, IrqNestingStorage, NestedInterrupts, RawInterruptMutex, RawSpinMutex};
+///
+/// struct CpuLocalNesting;
+///
+/// // SAFETY: There is only one CPU core in this example.
+/// unsafe impl IrqNestingStorage for CpuLocalNesting {
+///     fn current() -> &'static IrqNesting {
+///         // Read the state from CPU-local storage here.
+///         static NESTING: IrqNesting = IrqNesting::new();
+///         &NESTING
+///     }
+/// }
+///
+/// type NestedMutex<T> =
+///     lock_api::Mutex<RawInterruptMutex<RawSpinMutex, NestedInterrupts<CpuLocalNesting>>, T>;
+///
+/// static NUMBER: NestedMutex<usize> = NestedMutex::new(0);
+///
+/// *NUMBER.lock() = 1;
+/// ```
+pub unsafe trait IrqNestingStorage {
+    /// Returns the nesting state of the current CPU core.
+    ///
+    /// This is only called with interrupts disabled.
+    fn current() -> &'static IrqNesting;
+}
+
+/// The default [`IrqNestingStorage`], which selects one of [`MAX_CPUS`] static states via [`core_id`](crate::core_id).
+///
+/// # Panics
+///
+/// [`current`](IrqNestingStorage::current) panics if [`init_smp`](crate::init_smp) has not been called, since CPU cores might not be told apart yet.
+/// It also panics if [`core_id`](crate::core_id) is not less than the CPU count.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CoreIdNesting;
+
+static NESTING: [IrqNesting; MAX_CPUS] = [IrqNesting::INIT; MAX_CPUS];
+
+// SAFETY: After `init_smp`, core IDs are unique per CPU core and stable while interrupts are disabled.
+unsafe impl IrqNestingStorage for CoreIdNesting {
+    #[inline]
+    fn current() -> &'static IrqNesting {
+        &NESTING[checked_core_id()]
+    }
+}
+
+/// An [`InterruptControl`] that counts nested regions with disabled interrupts per CPU core.
+///
+/// Only the outermost region saves the interrupt state and only the last region to end restores it.
+/// Thus, in contrast to [`NativeInterrupts`](crate::NativeInterrupts), guards of [`RawInterruptMutex`](crate::RawInterruptMutex)es may be dropped in any order without enabling interrupts prematurely.
+/// This corresponds to FreeBSD's `spinlock_enter` and `spinlock_exit`.
+///
+/// The nesting state of the current CPU core is located via [`IrqNestingStorage`] `S`, which defaults to [`CoreIdNesting`].
+/// Since the saved interrupt state belongs to the CPU core instead of the region, it is not handed over via [`RawInterruptMutex::save_irq_state`](crate::RawInterruptMutex::save_irq_state).
+///
+/// This is not the default [`InterruptControl`] of [`RawInterruptMutex`](crate::RawInterruptMutex), since it prevents handing over the saved interrupt state across context switches.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{
+///     init_smp, irqs_disabled, NestedInterrupts, RawInterruptMutex, RawSpinMutex, SmpConfig,
+/// };
+///
+/// init_smp(SmpConfig::new(1, || 0)).unwrap();
+///
+/// type NestedMutex<T> = lock_api::Mutex<RawInterruptMutex<RawSpinMutex, NestedInterrupts>, T>;
+///
+/// static A: NestedMutex<usize> = NestedMutex::new(0);
+/// static B: NestedMutex<usize> = NestedMutex::new(0);
+///
+/// let enabled = !irqs_disabled();
+///
+/// let a = A.lock();
+/// let b = B.lock();
+///
+/// // Drop out of order.
+/// drop(a);
+/// assert!(irqs_disabled());
+/// drop(b);
+/// assert_eq!(!irqs_disabled(), enabled);
+/// ```
+pub struct NestedInterrupts<S = CoreIdNesting> {
+    _storage: PhantomData<fn() -> S>,
+}
+
+impl<S> Clone for NestedInterrupts<S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for NestedInterrupts<S> {}
+
+impl<S> Default for NestedInterrupts<S> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<S> fmt::Debug for NestedInterrupts<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NestedInterrupts").finish_non_exhaustive()
+    }
+}
+
+impl<S: IrqNestingStorage> InterruptControl for NestedInterrupts<S> {
+    type Flags = ();
+
+    #[inline]
+    fn save_disable() -> Self::Flags {
+        let flags = local_irq_save();
+        let nesting = S::current();
+        let depth = nesting.depth.get();
+        nesting.depth.set(depth + 1);
+        if depth == 0 {
+            // SAFETY: Interrupts are disabled and we are the outermost region on this CPU core.
+            unsafe {
+                nesting.flags.get().write(MaybeUninit::new(flags));
+            }
+        }
+    }
+
+    #[inline]
+    fn restore(_flags: Self::Flags) {
+        let nesting = S::current();
+        let depth = nesting.depth.get();
+        debug_assert_ne!(depth, 0, "unbalanced interrupt restore");
+        nesting.depth.set(depth - 1);
+        if depth == 1 {
+            // SAFETY: Interrupts are disabled and the outermost region saved the flags.
+            let flags = unsafe { nesting.flags.get().read().assume_init() };
+            local_irq_restore(flags);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic = "per-CPU state used before init_smp"]
+    fn nesting_before_init() {
+        CoreIdNesting::current();
+    }
+}
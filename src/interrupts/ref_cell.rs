@@ -0,0 +1,294 @@
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+
+use crate::{local_irq_restore, local_irq_save, Flags};
+
+/// A [`RefCell`](core::cell::RefCell) whose borrows disable interrupts.
+///
+/// On uniprocessor systems and for data that is only accessed by one CPU core, disabling interrupts is sufficient for mutual exclusion.
+/// Borrows are tracked at runtime without atomics, like [`RefCell`](core::cell::RefCell).
+/// Interrupts are disabled while any borrow is held and restored once the last borrow is released, so borrows may be released in any order.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{irqs_disabled, InterruptRefCell};
+///
+/// // SAFETY: This is a uniprocessor system.
+/// static TIMER_QUEUE: InterruptRefCell<[u64; 4]> = unsafe { InterruptRefCell::new([0; 4]) };
+///
+/// TIMER_QUEUE.borrow_mut()[0] = 1000;
+///
+/// let queue = TIMER_QUEUE.borrow();
+/// assert!(irqs_disabled());
+/// assert_eq!(queue[0], 1000);
+/// assert!(TIMER_QUEUE.try_borrow_mut().is_none());
+/// ```
+pub struct InterruptRefCell<T: ?Sized> {
+    /// The number of shared borrows, or `-1` if mutably borrowed.
+    borrow: Cell<isize>,
+    /// The interrupt state from before the first borrow.
+    flags: Cell<MaybeUninit<Flags>>,
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: The creator guarantees that only one CPU core accesses this cell, which serializes accesses by disabling interrupts.
+unsafe impl<T: ?Sized + Send> Sync for InterruptRefCell<T> {}
+
+impl<T> InterruptRefCell<T> {
+    /// Creates a new cell containing `value`.
+    ///
+    /// # Safety
+    ///
+    /// The cell must only ever be accessed by one CPU core, for example, because the system has a single CPU core.
+    #[inline]
+    pub const unsafe fn new(value: T) -> Self {
+        Self {
+            borrow: Cell::new(0),
+            flags: Cell::new(MaybeUninit::uninit()),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the cell, returning the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> InterruptRefCell<T> {
+    /// Disables interrupts and registers a borrow if `acquire` accepts the current borrow state.
+    #[inline]
+    fn acquire(&self, acquire: impl FnOnce(isize) -> Option<isize>) -> bool {
+        let flags = local_irq_save();
+        let borrow = self.borrow.get();
+        let Some(new) = acquire(borrow) else {
+            local_irq_restore(flags);
+            return false;
+        };
+        if borrow == 0 {
+            self.flags.set(MaybeUninit::new(flags));
+        }
+        self.borrow.set(new);
+        true
+    }
+
+    /// Releases a borrow, restoring interrupts if it was the last one.
+    #[inline]
+    fn release(&self, new: isize) {
+        self.borrow.set(new);
+        if new == 0 {
+            // SAFETY: The first borrow saved the flags.
+            let flags = unsafe { self.flags.get().assume_init() };
+            local_irq_restore(flags);
+        }
+    }
+
+    /// Immutably borrows the wrapped value, disabling interrupts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed or if the number of shared borrows overflows.
+    #[inline]
+    #[track_caller]
+    pub fn borrow(&self) -> InterruptRef<'_, T> {
+        self.try_borrow().expect("already mutably borrowed")
+    }
+
+    /// Immutably borrows the wrapped value, disabling interrupts.
+    ///
+    /// Returns `None` if the value is currently mutably borrowed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of shared borrows overflows, for example, because borrows have been leaked.
+    #[inline]
+    #[track_caller]
+    pub fn try_borrow(&self) -> Option<InterruptRef<'_, T>> {
+        let mut overflowed = false;
+        let ok = self.acquire(|borrow| {
+            if borrow < 0 {
+                return None;
+            }
+            let new = borrow.checked_add(1);
+            overflowed = new.is_none();
+            new
+        });
+        // Panic only after `acquire` has restored interrupts.
+        assert!(!overflowed, "too many immutable borrows");
+        ok.then(|| InterruptRef {
+            cell: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Mutably borrows the wrapped value, disabling interrupts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn borrow_mut(&self) -> InterruptRefMut<'_, T> {
+        self.try_borrow_mut().expect("already borrowed")
+    }
+
+    /// Mutably borrows the wrapped value, disabling interrupts.
+    ///
+    /// Returns `None` if the value is currently borrowed.
+    #[inline]
+    pub fn try_borrow_mut(&self) -> Option<InterruptRefMut<'_, T>> {
+        self.acquire(|borrow| (borrow == 0).then_some(-1))
+            .then(|| InterruptRefMut {
+                cell: self,
+                _not_send: PhantomData,
+            })
+    }
+
+    /// Returns a mutable reference to the wrapped value.
+    ///
+    /// This does not disable interrupts, since the `&mut` borrow guarantees exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for InterruptRefCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("InterruptRefCell");
+        match self.try_borrow() {
+            Some(value) => d.field("value", &&*value),
+            None => d.field("value", &format_args!("<borrowed>")),
+        };
+        d.finish()
+    }
+}
+
+/// An immutable borrow of an [`InterruptRefCell`].
+///
+/// Interrupts stay disabled until the last borrow of the cell is dropped.
+#[must_use = "if unused the borrow will be released immediately"]
+pub struct InterruptRef<'a, T: ?Sized> {
+    cell: &'a InterruptRefCell<T>,
+    /// Borrows disable interrupts on the current CPU core.
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<T: ?Sized> Deref for InterruptRef<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: There are only shared borrows.
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for InterruptRef<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.cell.release(self.cell.borrow.get() - 1);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for InterruptRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A mutable borrow of an [`InterruptRefCell`].
+///
+/// Interrupts stay disabled until this borrow is dropped.
+#[must_use = "if unused the borrow will be released immediately"]
+pub struct InterruptRefMut<'a, T: ?Sized> {
+    cell: &'a InterruptRefCell<T>,
+    /// Borrows disable interrupts on the current CPU core.
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<T: ?Sized> Deref for InterruptRefMut<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: This is the only borrow.
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for InterruptRefMut<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: This is the only borrow.
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for InterruptRefMut<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.cell.release(0);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for InterruptRefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_borrows() {
+        // SAFETY: Only this thread accesses this cell.
+        let cell = unsafe { InterruptRefCell::new(0) };
+
+        let a = cell.borrow();
+        let b = cell.borrow();
+        assert!(cell.try_borrow_mut().is_none());
+        // Release out of order.
+        drop(a);
+        drop(b);
+
+        *cell.borrow_mut() += 1;
+        let guard = cell.borrow_mut();
+        assert!(cell.try_borrow().is_none());
+        drop(guard);
+        assert_eq!(cell.into_inner(), 1);
+    }
+
+    #[test]
+    #[should_panic = "too many immutable borrows"]
+    fn borrow_overflow() {
+        // SAFETY: Only this thread accesses this cell.
+        let cell = unsafe { InterruptRefCell::new(()) };
+
+        // Simulate leaked borrows.
+        cell.borrow.set(isize::MAX);
+        let _ = cell.try_borrow();
+    }
+
+    #[cfg(all(unix, not(miri), not(feature = "interrupt-hooks")))]
+    #[test]
+    fn restores_after_last_borrow() {
+        // SAFETY: Only this thread accesses this cell.
+        let cell = unsafe { InterruptRefCell::new(()) };
+
+        assert!(!crate::irqs_disabled());
+        let a = cell.borrow();
+        let b = cell.borrow();
+        drop(a);
+        assert!(crate::irqs_disabled());
+        drop(b);
+        assert!(!crate::irqs_disabled());
+    }
+}
@@ -2,24 +2,83 @@
 //!
 //! hermit-sync provides synchronization primitives targeted at operating system kernels.
 //!
+//! # Initialization
+//!
+//! [`init_smp`] registers the CPU count, the [`core_id`] provider, a time source, and scheduler hooks at once.
+//! The registered hooks are available via [`cpu_count`], [`now_ns`], and [`yield_now`].
+//!
+//! With an IPI hook registered, [`run_on_cpu`] and [`run_on_all_cpus`] run closures on other CPU cores and wait for them to complete.
+//!
 //! # Interrupts
 //!
 //! [`without_interrupts`] runs a closure with disabled interrupts.
+//! [`without_interrupts_if`] does so only if a condition holds.
+//! [`without_interrupts_timed`] additionally measures how long interrupts were disabled.
+//! [`InterruptGuard`] disables interrupts until it is dropped, for regions that span early returns or are stored in other structs.
+//! [`InterruptGuard::with_interrupts_enabled`] temporarily restores the previous interrupt state inside such a region.
+//! [`local_irq_save`] and [`local_irq_restore`] disable and restore interrupts without closures.
+//! [`irqs_disabled`] and [`interrupts_enabled`] check whether interrupts are disabled.
+//! [`read_flags`] reads the interrupt state without changing it.
+//!
+//! On bare-metal targets (`target_os = "none"` and `target_os = "uefi"`) for aarch64, arm (except M-profile), powerpc64, riscv32, riscv64, x86, and x86_64, this controls the interrupts of the current CPU.
+//! On Unix, this controls the signal mask of the current thread.
+//! On other targets, interrupts are not touched.
+//! The `interrupt-hooks` feature replaces all of these with hooks registered via `set_interrupt_hooks`, for example, for architectures that are not supported by this crate.
+//! During early boot, [`set_interrupts_managed`] can stop this crate from touching interrupts at all.
+//! [`InterruptControl`] allows [`RawInterruptMutex`] to use other interrupt interfaces, such as paravirtual ones, or to disable preemption instead (see [`PreemptControl`]).
+//! [`NestedInterrupts`] counts nested regions per CPU core, so that guards can be dropped in any order.
+//! It is opt-in: [`InterruptMutex`] and the other interrupt mutexes use [`NativeInterrupts`] by default, so their guards have to be dropped in reverse order of locking.
+//! The nesting state lives in a static array indexed by [`core_id`] unless an [`IrqNestingStorage`] places it in CPU-local storage.
+//! [`InterruptRefCell`] tracks borrows without atomics while disabling interrupts, for uniprocessor systems or data that is only accessed by one CPU core.
+//! [`InterruptCell`] protects data that is only accessed by one CPU core solely by disabling interrupts.
 //!
 //! # Mutexes
 //!
-//! This crate provides three kinds of mutexes based on [`lock_api::RawMutex`]:
+//! This crate provides several kinds of mutexes based on [`lock_api::RawMutex`]:
 //! * [`RawSpinMutex`] is a simple [test and test-and-set] [spinlock] with [exponential backoff].
+//! * [`RawNakedSpinMutex`] is a bare test and test-and-set spinlock without backoff for code-size-sensitive users.
 //! * [`RawTicketMutex`] is a [fair] [ticket lock] with [exponential backoff].
+//! * [`RawMcsMutex`] is an [MCS lock] whose waiters each spin on their own wait node on the stack.
+//!   [`RawInterruptMcsMutex`] keeps its wait nodes in per-CPU statics instead.
+//!   It always disables interrupts, so that waiters cannot migrate between CPU cores.
+//! * [`RawInterruptCnaMutex`] is a [compact NUMA-aware lock] that prefers handing over to waiters on the same NUMA node (see [`NumaNodeId`]).
+//!   Like [`RawInterruptMcsMutex`], it always disables interrupts.
+//! * [`RawCohortMutex`] is a [cohort lock] that composes a global mutex and per-NUMA-node local mutexes.
+//! * [`RawAdaptiveMutex`] switches between test-and-set and ticket locking depending on contention.
 //! * [`RawInterruptMutex`] wraps another mutex and disables interrupts while locked.
+//!   Its saved interrupt state can be handed over across context switches.
+//!   [`SendInterruptMutexGuard`] allows migrating its guards between CPUs.
+//!   [`InterruptMutexExt::lock_from_irq`] skips saving the interrupt state in interrupt handlers.
+//! * [`RawInterruptPriorityMutex`] only masks interrupts up to a priority threshold while locked (see [`InterruptPriority`]).
+//! * [`RawPreemptMutex`] wraps another mutex and disables preemption via scheduler hooks while locked (see [`SmpConfig::preempt_hooks`]).
+//!   [`RawPreemptInterruptMutex`] additionally disables interrupts in the correct order.
+//! * [`RawDynMutex`] switches its locking strategy at runtime, for example, from disabling interrupts during early boot to yielding to the scheduler.
+//! * [`IrqOffChecked`] wraps another mutex and debug-asserts that interrupts are already disabled when locking.
+//! * [`RawPiMutex`] wraps another mutex and boosts its holder via scheduler hooks for priority inheritance (see [`set_pi_hooks`]).
+//! * [`ReentrantSpinMutex`] can be reentered from the same CPU core and disables interrupts while locked.
+//!   [`ThreadReentrantSpinMutex`] is a [`lock_api::ReentrantMutex`] that can be reentered from the same thread, as identified by a [`ThreadIdProvider`].
+//! * [`RawOwnedMutex`] wraps another mutex and tracks the CPU core holding it (see [`assert_lock_held!`]).
 //!
 //! [test and test-and-set]: https://en.wikipedia.org/wiki/Test_and_test-and-set
 //! [spinlock]: https://en.wikipedia.org/wiki/Spinlock
 //! [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
 //! [fair]: https://en.wikipedia.org/wiki/Unbounded_nondeterminism
 //! [ticket lock]: https://en.wikipedia.org/wiki/Ticket_lock
+//! [MCS lock]: https://lwn.net/Articles/590243/
+//! [compact NUMA-aware lock]: https://arxiv.org/abs/1810.05600
+//! [cohort lock]: https://doi.org/10.1145/2686884
 //!
 //! For API documentation see [`lock_api::Mutex`].
+//! [`MutexExt`] provides short-lived accessors such as [`set`](MutexExt::set), [`replace`](MutexExt::replace), and [`take`](MutexExt::take).
+//!
+//! [`ProjectedMutexGuard`] narrows mutex guards onto parts of the locked data and splits them into guards of disjoint parts.
+//! [`LockCoupling`] traverses linked structures of per-node mutexes hand over hand.
+//!
+//! Under hypervisors, [`set_pv_hooks`] allows spinning mutexes to halt waiting vCPUs until they are kicked.
+//! [`stats::sample`] periodically samples the queue depths of registered mutexes, showing which ones are hot.
+//!
+//! On targets without compare-and-swap, such as RISC-V without the A extension, [`RawSpinMutex`] and thus [`OnceCell`] and [`Lazy`] disable interrupts instead.
+//! This is only sound on single-core systems.
 //!
 //! ## Examples
 //!
@@ -36,12 +95,49 @@
 //! assert_eq!(2, answer);
 //! ```
 //!
+//! # Readers-Writer Locks
+//!
+//! [`RawRwSpinLock`] is a simple spinning, read-preferring readers-writer lock with [exponential backoff] based on [`lock_api::RawRwLock`].
+//! While an upgradable reader is upgrading, new readers are held back, so upgrading is not starved by readers (see [Features]).
+//! [`RawRwSpinLockWritePref`] is a write-preferring variant that holds back new readers while writers are waiting.
+//! [`RawPhaseFairRwLock`] alternates between reader and writer phases, so neither readers nor writers are starved.
+//! [`BrLock`] is a big-reader lock whose readers only touch per-CPU counters.
+//! [`SeqLock`] is a sequence lock for small [`Copy`] data whose readers never block writers.
+//! [`SeqCount`] is a standalone sequence counter for irregular data layouts.
+//! [`ExclusiveOnly`] exposes any raw mutex as a readers-writer lock for generic code whose instances never have concurrent readers.
+//! Conversely, [`WriteOnly`] exposes any raw readers-writer lock as a mutex that locks exclusively.
+//!
+//! For API documentation see [`lock_api::RwLock`].
+//! [`RwLockExt`] provides short-lived accessors such as [`get_cloned`](RwLockExt::get_cloned) and [`set`](RwLockExt::set).
+//!
+//! ## Examples
+//!
+//! [`RwLockUpgradableReadGuard::with_upgraded`] upgrades temporarily for running a closure:
+//!
+//! ```
+//! use hermit_sync::RwSpinLock;
+//!
+//! static COUNTER: RwSpinLock<usize> = RwSpinLock::new(0);
+//!
+//! let mut guard = COUNTER.upgradable_read();
+//! if *guard == 0 {
+//!     guard.with_upgraded(|counter| *counter += 1);
+//! }
+//! assert_eq!(*guard, 1);
+//! ```
+//!
+//! [`RwLockUpgradableReadGuard::with_upgraded`]: lock_api::RwLockUpgradableReadGuard::with_upgraded
+//!
 //! # Initializing Static Data
 //!
 //! There are two primitives for safely initializing static data based on [`generic_once_cell`] and [`RawSpinMutex`]:
 //! * [`OnceCell`] can be written to only once and can then be accessed without locking.
 //! * [`Lazy`] wraps a [`OnceCell`] and is initialized on the first access from a closure.
 //!
+//! Their interrupt-safe counterparts [`InterruptOnceCell`] and [`InterruptLazy`] can be used with any raw mutex.
+//! [`TicketOnceCell`] and [`TicketLazy`] are initialized fairly, while [`OneShotOnceCell`] and [`OneShotLazy`] panic on recursive or concurrent initialization.
+//! [`TryLazy`] and [`InterruptTryLazy`] cache the result of fallible initializers, including errors.
+//!
 //! For API documentation see [`generic_once_cell::OnceCell`] and [`generic_once_cell::Lazy`].
 //!
 //! ## Examples
@@ -62,81 +158,399 @@
 //! assert_eq!("Ferris", MAP.get(&42).unwrap());
 //! ```
 //!
+//! Using a [`RawTicketMutex`] for initialization:
+//!
+//! ```
+//! use hermit_sync::{InterruptOnceCell, RawTicketMutex};
+//!
+//! static CELL: InterruptOnceCell<usize, RawTicketMutex> = InterruptOnceCell::new();
+//!
+//! assert_eq!(*CELL.get_or_init(|| 42), 42);
+//! ```
+//!
+//! [`OnceCell::try_insert`] returns both the existing value and the rejected value when losing an initialization race:
+//!
+//! ```
+//! use hermit_sync::OnceCell;
+//!
+//! static CELL: OnceCell<usize> = OnceCell::new();
+//!
+//! assert_eq!(CELL.try_insert(1), Ok(&1));
+//! assert_eq!(CELL.try_insert(2), Err((&1, 2)));
+//! ```
+//!
+//! [`OnceCell::try_insert`]: generic_once_cell::OnceCell::try_insert
+//!
+//! [`OnceCell::get_or_try_init`] propagates errors of fallible initializers and leaves the cell uninitialized:
+//!
+//! ```
+//! use hermit_sync::OnceCell;
+//!
+//! static RSDP: OnceCell<usize> = OnceCell::new();
+//!
+//! fn parse_rsdp(valid: bool) -> Result<usize, &'static str> {
+//!     valid.then_some(0xe0000).ok_or("invalid checksum")
+//! }
+//!
+//! assert_eq!(RSDP.get_or_try_init(|| parse_rsdp(false)), Err("invalid checksum"));
+//! assert_eq!(RSDP.get(), None);
+//! assert_eq!(RSDP.get_or_try_init(|| parse_rsdp(true)), Ok(&0xe0000));
+//! ```
+//!
+//! [`OnceCell::get_or_try_init`]: generic_once_cell::OnceCell::get_or_try_init
+//!
+//! [`Lazy::get`] accesses a [`Lazy`] only if it has already been initialized and never runs the initializer, for example, in interrupt handlers:
+//!
+//! ```
+//! use hermit_sync::InterruptLazy;
+//!
+//! static TABLE: InterruptLazy<[usize; 4]> = InterruptLazy::new(|| [1, 2, 3, 4]);
+//!
+//! fn interrupt_handler() -> Option<usize> {
+//!     InterruptLazy::get(&TABLE).map(|table| table[0])
+//! }
+//!
+//! assert_eq!(interrupt_handler(), None);
+//! assert_eq!(TABLE[0], 1);
+//! assert_eq!(interrupt_handler(), Some(1));
+//! ```
+//!
+//! [`Lazy::get`]: generic_once_cell::Lazy::get
+//!
+//! [`Lazy::force_mut`] and [`Lazy::get_mut`] access a [`Lazy`] mutably without locking, for example, in single-threaded boot phases:
+//!
+//! ```
+//! use hermit_sync::Lazy;
+//!
+//! let mut memory_map = Lazy::<Vec<usize>>::new(|| vec![0x1000]);
+//! assert_eq!(Lazy::get_mut(&mut memory_map), None);
+//!
+//! Lazy::force_mut(&mut memory_map).push(0x2000);
+//! assert_eq!(Lazy::get_mut(&mut memory_map).unwrap(), &[0x1000, 0x2000]);
+//! ```
+//!
+//! [`Lazy::force_mut`]: generic_once_cell::Lazy::force_mut
+//! [`Lazy::get_mut`]: generic_once_cell::Lazy::get_mut
+//!
+//! [`OnceCellExt::wait`] waits until another CPU core has initialized a [`OnceCell`].
+//!
+//! [`OnceCell::take`] and [`OnceCellExt::reset`] return a [`OnceCell`] to the uninitialized state, for example, before a soft reboot.
+//! [`LazyExt::take`] and [`LazyExt::reset`] do the same for [`Lazy`] with a new initializer.
+//!
+//! [`OnceCell::take`]: generic_once_cell::OnceCell::take
+//!
+//! [`DoubleCheckedCell`] lets initializers race without a lock; the first to finish publishes its value.
+//! [`RacyLazy`] wraps a [`DoubleCheckedCell`] and is initialized on the first access from an idempotent closure.
+//! [`OnceBool`], [`OnceNonZeroUsize`], and [`OnceRef`] are initialized by a single compare-and-swap without any locking, for example, for `&'static` references discovered during boot.
+//!
+//! [`CallOnce`] allows a call only once.
+//! [`WaitableCallOnce`] additionally allows other CPU cores to wait until the call has completed.
+//! [`CallN`] allows up to `N` calls, for example, one per CPU core, and tells each caller which slot it won.
+//!
 //! # Accessing Static Data Mutably
 //!
 //! There is [`ExclusiveCell`] for safely accessing static data mutable _once_.
+//! [`RetakableCell`] hands out exclusive access as an [`ExclusiveToken`] that can be given back, so that a later phase can take it again.
+//! [`StaticCell`] is initialized at runtime and hands out a `&'static mut` reference exactly once.
+//!
+//! [`SyncUnsafeCell`] replaces `static mut` where accesses are synchronized externally.
+//!
+//! # Per-CPU Data
+//!
+//! [`PerCpu`] holds a value per CPU core, each on its own cache line.
+//! The current CPU core is determined via [`CoreIdProvider`], by default via [`set_core_id_provider`].
+//! [`CoreLocal`] is initialized lazily by each CPU core on its first access.
+//!
+//! [`PerCpuCounter`] is updated without contention by the current CPU core and summed up across all CPU cores.
+//! [`PerCpuRef`] is a reference counter that counts per CPU core until it is killed before teardown.
+//!
+//! # Waiting for Events
+//!
+//! [`EventCount`] allows waiting for conditions of lock-free data structures without a lock on the producer's fast path.
+//! [`WaitBitset`] additionally allows waiters to subscribe to subsets of events.
+//!
+//! [`Barrier`] synchronizes a number of participants, for example, CPU cores during bring-up.
+//! It supports timeouts via [`Barrier::wait_for`] and is broken for all participants if one of them fails to arrive.
+//!
+//! [`Semaphore`] bounds concurrent access to a pool of resources, such as DMA buffers.
+//! [`InterruptSemaphore`] additionally allows interrupt handlers to release permits to threads.
+//!
+//! # Handing Over Values
+//!
+//! [`AtomicOption`] allows taking and putting a single value atomically, for example, between an interrupt handler and a thread.
+//! With the `alloc` feature, `AtomicBox` replaces heap-allocated values atomically and `AtomicArc` additionally allows loading the current value without taking it out.
+//! `RcuMutex` builds on `AtomicArc` for read-mostly values: reads are lock-free snapshots and updates are serialized by a mutex.
+//!
+//! # Compatibility
+//!
+//! [`compat::std`] mirrors the API of `std::sync` for code that is shared between hosted tests and the kernel.
+//!
+//! # Features
+//!
+//! By default, all spinning locks are implemented in this crate.
+//! The following features change the implementations of [`RawSpinMutex`] and [`RawRwSpinLock`] consistently:
+//! * `spinning_top` uses the implementations of [`spinning_top`](https://docs.rs/spinning_top) with exponential backoff.
+//!   [`RawTicketMutex`] stays implemented in this crate.
+//! * `all-one-shot` uses [`RawOneShotMutex`] and [`RawOneShotRwLock`] for all locks, including [`RawNakedSpinMutex`], [`RawTicketMutex`], [`RawMcsMutex`], [`RawInterruptMcsMutex`], [`RawAdaptiveMutex`], [`RawRwSpinLockWritePref`], and [`RawPhaseFairRwLock`].
+//!   [`RawInterruptCnaMutex`] stays implemented in this crate.
+//!   This takes precedence over `spinning_top`.
+//! * `uniprocessor` uses an interrupt-disabling lock without atomic read-modify-write operations for [`RawSpinMutex`] and [`RawTicketMutex`].
+//!   Contention can only come from reentrancy, which panics in debug builds.
+//!   This is only sound on single-core systems.
+//!   [`RawRwSpinLock`] stays unchanged.
+//!   This takes precedence over `spinning_top`, while `all-one-shot` takes precedence over this.
+//!
+//! APIs beyond [`lock_api`], such as [`RawTicketMutex::lock_cancelable`], are only available for implementations of this crate.
+//!
+//! The `rtm` feature enables [hardware lock elision] via `RawElidedSpinMutex` on x86_64.
+//!
+//! [hardware lock elision]: https://en.wikipedia.org/wiki/Transactional_Synchronization_Extensions
+//!
+//! The `interrupt-hooks` feature replaces the interrupt backends of this crate with hooks registered via `set_interrupt_hooks` (see [Interrupts]).
+//!
+//! [Interrupts]: #interrupts
+//!
+//! The `held-locks` feature records held [`RawOwnedMutex`]es for `panic::dump_held_locks`, which lists them in panic handlers.
+//! Since this touches shared state on every lock and unlock, it is intended for debugging.
+//!
+//! The `alloc` feature enables APIs that depend on the [`alloc`](https://doc.rust-lang.org/alloc/) crate.
+//!
+//! On targets without atomic compare-and-swap, such as `riscv32i-unknown-none-elf`, only the locks that get by with atomic loads and stores are available.
+//! These include [`RawSpinMutex`], [`RawNakedSpinMutex`], and everything built on them, which emulate compare-and-swap by disabling interrupts and are thus only sound on single-core systems.
+//! All other types require `cfg(target_has_atomic = "ptr")`, as does the `spinning_top` feature.
+//! On these targets, the `all-one-shot` feature has no effect, since one-shot locks need compare-and-swap themselves.
 //!
 //! # Type Definitions
 //!
 //! This crate provides a lot of type definitions for ease of use:
 //!
-//! | [`RawMutex`]        | Base                  | With [`RawInterruptMutex`]     |
-//! | ------------------- | --------------------- | ------------------------------ |
-//! | `R`                 | [`Mutex`]             | [`InterruptMutex`]             |
-//! | [`RawSpinMutex`]    |                       | [`RawInterruptSpinMutex`]      |
-//! |                     | [`SpinMutex`]         | [`InterruptSpinMutex`]         |
-//! |                     | [`SpinMutexGuard`]    | [`InterruptSpinMutexGuard`]    |
-//! |                     | [`OnceCell`]          | [`InterruptOnceCell`]          |
-//! |                     | [`Lazy`]              | [`InterruptLazy`]              |
-//! | [`RawOneShotMutex`] |                       | [`RawInterruptOneShotMutex`]   |
-//! |                     | [`OneShotMutex`]      | [`InterruptOneShotMutex`]      |
-//! |                     | [`OneShotMutexGuard`] | [`InterruptOneShotMutexGuard`] |
-//! | [`RawTicketMutex`]  |                       | [`RawInterruptTicketMutex`]    |
-//! |                     | [`TicketMutex`]       | [`InterruptTicketMutex`]       |
-//! |                     | [`TicketMutexGuard`]  | [`InterruptTicketMutexGuard`]  |
+//! | [`RawMutex`]          | Base                    | With [`RawInterruptMutex`]       |
+//! | --------------------- | ----------------------- | -------------------------------- |
+//! | `R`                   | [`Mutex`]               | [`InterruptMutex`]               |
+//! | [`RawSpinMutex`]      |                         | [`RawInterruptSpinMutex`]        |
+//! |                       | [`SpinMutex`]           | [`InterruptSpinMutex`]           |
+//! |                       | [`SpinMutexGuard`]      | [`InterruptSpinMutexGuard`]      |
+//! |                       | [`OnceCell`]            | [`InterruptOnceCell`]            |
+//! |                       | [`Lazy`]                | [`InterruptLazy`]                |
+//! |                       | [`TryLazy`]             | [`InterruptTryLazy`]             |
+//! | [`RawNakedSpinMutex`] |                         | [`RawInterruptNakedSpinMutex`]   |
+//! |                       | [`NakedSpinMutex`]      | [`InterruptNakedSpinMutex`]      |
+//! |                       | [`NakedSpinMutexGuard`] | [`InterruptNakedSpinMutexGuard`] |
+//! | [`RawOneShotMutex`]   |                         | [`RawInterruptOneShotMutex`]     |
+//! |                       | [`OneShotMutex`]        | [`InterruptOneShotMutex`]        |
+//! |                       | [`OneShotMutexGuard`]   | [`InterruptOneShotMutexGuard`]   |
+//! |                       | [`OneShotOnceCell`]     |                                  |
+//! |                       | [`OneShotLazy`]         |                                  |
+//! | [`RawTicketMutex`]    |                         | [`RawInterruptTicketMutex`]      |
+//! |                       | [`TicketMutex`]         | [`InterruptTicketMutex`]         |
+//! |                       | [`TicketMutexGuard`]    | [`InterruptTicketMutexGuard`]    |
+//! |                       | [`TicketOnceCell`]      |                                  |
+//! |                       | [`TicketLazy`]          |                                  |
+//! | [`RawMcsMutex`]       |                         |                                  |
+//! |                       | [`McsMutex`]            |                                  |
+//! |                       | [`McsMutexGuard`]       |                                  |
+//! |                       |                         | [`RawInterruptMcsMutex`]         |
+//! |                       |                         | [`InterruptMcsMutex`]            |
+//! |                       |                         | [`InterruptMcsMutexGuard`]       |
+//! |                       |                         | [`RawInterruptCnaMutex`]         |
+//! |                       |                         | [`InterruptCnaMutex`]            |
+//! |                       |                         | [`InterruptCnaMutexGuard`]       |
+//! | [`RawCohortMutex`]    |                         | [`RawInterruptCohortMutex`]      |
+//! |                       | [`CohortMutex`]         | [`InterruptCohortMutex`]         |
+//! |                       | [`CohortMutexGuard`]    | [`InterruptCohortMutexGuard`]    |
+//! | [`RawAdaptiveMutex`]  |                         | [`RawInterruptAdaptiveMutex`]    |
+//! |                       | [`AdaptiveMutex`]       | [`InterruptAdaptiveMutex`]       |
+//! |                       | [`AdaptiveMutexGuard`]  | [`InterruptAdaptiveMutexGuard`]  |
 //!
+//! [Features]: #features
 //! [`RawMutex`]: lock_api::RawMutex
 //! [`Mutex`]: lock_api::Mutex
 
 #![cfg_attr(not(test), no_std)]
 #![warn(unsafe_op_in_unsafe_fn)]
 
-pub(crate) mod mutex;
-#[cfg(not(feature = "all-one-shot"))]
-pub(crate) mod rwlock {
-    /// A simple spinning, read-preferring readers-writer lock with exponential backoff.
-    pub type RawRwSpinLock = spinning_top::RawRwSpinlock<spinning_top::relax::Backoff>;
-
-    /// A [`lock_api::RwLock`] based on [`RawRwSpinLock`].
-    pub type RwSpinLock<T> = lock_api::RwLock<RawRwSpinLock, T>;
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-    /// A [`lock_api::RwLockReadGuard`] based on [`RawRwSpinLock`].
-    pub type RwSpinLockReadGuard<'a, T> = lock_api::RwLockReadGuard<'a, RawRwSpinLock, T>;
-
-    /// A [`lock_api::RwLockUpgradableReadGuard`] based on [`RawRwSpinLock`].
-    pub type RwSpinLockUpgradableReadGuard<'a, T> =
-        lock_api::RwLockUpgradableReadGuard<'a, RawRwSpinLock, T>;
-
-    /// A [`lock_api::RwLockWriteGuard`] based on [`RawRwSpinLock`].
-    pub type RwSpinLockWriteGuard<'a, T> = lock_api::RwLockWriteGuard<'a, RawRwSpinLock, T>;
-}
-#[cfg(feature = "all-one-shot")]
-pub(crate) mod rwlock {
-    pub use one_shot_mutex::{
-        OneShotRwLock as RwSpinLock, OneShotRwLockReadGuard as RwSpinLockReadGuard,
-        OneShotRwLockUpgradableReadGuard as RwSpinLockUpgradableReadGuard,
-        OneShotRwLockWriteGuard as RwSpinLockWriteGuard, RawOneShotRwLock as RawRwSpinLock,
-    };
-}
+pub(crate) mod atomic;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod barrier;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod call;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod call_once;
+#[cfg(target_has_atomic = "ptr")]
+pub mod compat;
+pub(crate) mod cpu;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod eventcount;
+pub(crate) mod exclusive;
+pub(crate) mod init;
+pub(crate) mod interrupts;
+pub(crate) mod mutex;
+pub(crate) mod once;
+#[cfg(all(feature = "held-locks", target_has_atomic = "ptr"))]
+pub mod panic;
+pub(crate) mod percpu;
+pub(crate) mod pv;
+pub(crate) mod relax;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod rwlock;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod semaphore;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod seqlock;
+pub mod stats;
+pub(crate) mod unsafe_cell;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod waitbitset;
 
+#[cfg(target_has_atomic = "ptr")]
+pub use atomic::option::{AtomicOption, AtomicRepr};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use atomic::owned::{AtomicArc, AtomicBox};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use atomic::rcu::RcuMutex;
+#[cfg(target_has_atomic = "ptr")]
+pub use barrier::{Barrier, BarrierError, BarrierWaitResult};
+#[cfg(target_has_atomic = "ptr")]
+pub use call::{handle_call_ipi, run_on_all_cpus, run_on_cpu, CallError};
+#[cfg(target_has_atomic = "ptr")]
+pub use call_once::{CallN, WaitableCallOnce};
+pub use cpu::{core_id, set_core_id_provider, MAX_CPUS};
+#[cfg(target_has_atomic = "ptr")]
+pub use eventcount::{EventCount, EventKey};
+#[cfg(target_has_atomic = "ptr")]
+pub use exclusive::StaticCell;
+pub use exclusive::{ExclusiveToken, RetakableCell};
+#[cfg(target_has_atomic = "ptr")]
 pub use exclusive_cell::{CallOnce, CallOnceError, ExclusiveCell};
-pub use interrupt_mutex::{InterruptMutex, InterruptMutexGuard, RawInterruptMutex};
-pub use interrupts::without as without_interrupts;
+pub use init::{cpu_count, init_smp, is_initialized, now_ns, yield_now, InitError, SmpConfig};
+pub use interrupts::cell::InterruptCell;
+pub use interrupts::nested::{CoreIdNesting, IrqNesting, IrqNestingStorage, NestedInterrupts};
+pub use interrupts::ref_cell::{InterruptRef, InterruptRefCell, InterruptRefMut};
+pub use interrupts::{
+    interrupts_enabled, interrupts_managed, irqs_disabled, local_irq_restore, local_irq_save,
+    read_flags, set_interrupts_managed, without_interrupts, without_interrupts_if,
+    without_interrupts_timed, Flags, InterruptControl, InterruptGuard, NativeInterrupts,
+};
+#[cfg(feature = "interrupt-hooks")]
+pub use interrupts::{set_interrupt_hooks, InterruptHooks};
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::adaptive::{AdaptiveMutex, AdaptiveMutexGuard, RawAdaptiveMutex};
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::cna::{NumaNodeId, SingleNode, CNA_LOCAL_HANDOFFS};
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::cohort::{
+    CohortMutex, CohortMutexGuard, RawCohortMutex, COHORT_LOCAL_HANDOFFS, MAX_NUMA_NODES,
+};
+pub use mutex::coupling::LockCoupling;
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::dynamic::{DynMutex, DynMutexGuard, DynStrategy, RawDynMutex};
+#[cfg(all(feature = "rtm", target_arch = "x86_64"))]
+pub use mutex::elided::{
+    set_lock_elision, ElidedSpinMutex, ElidedSpinMutexGuard, RawElidedSpinMutex,
+};
+pub use mutex::ext::MutexExt;
+pub use mutex::interrupt::{
+    InterruptMutex, InterruptMutexExt, InterruptMutexGuard, RawInterruptMutex,
+    SendInterruptMutexGuard,
+};
+pub use mutex::irq_off::IrqOffChecked;
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::mcs::{McsMutex, McsMutexGuard, RawMcsMutex};
+pub use mutex::naked::{NakedSpinMutex, NakedSpinMutexGuard, RawNakedSpinMutex};
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::owned::{MutexOwnedExt, OwnedMutex, OwnedMutexGuard, RawMutexOwned, RawOwnedMutex};
+pub use mutex::pi::{set_pi_hooks, PiHooks, PiMutex, PiMutexGuard, RawPiMutex};
+pub use mutex::preempt::{
+    PreemptControl, PreemptHooks, PreemptInterruptMutex, PreemptInterruptMutexGuard, PreemptMutex,
+    PreemptMutexGuard, RawPreemptInterruptMutex, RawPreemptMutex,
+};
+pub use mutex::priority::{
+    InterruptPriority, InterruptPriorityMutex, InterruptPriorityMutexGuard, PriorityMasked,
+    RawInterruptPriorityMutex,
+};
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::projected::{GuardSplit, ProjectedMutexGuard};
+#[cfg(target_has_atomic = "ptr")]
+pub use mutex::queue::MAX_MCS_NESTING;
+pub use mutex::reentrant::{
+    InterruptThreadReentrantSpinMutex, InterruptThreadReentrantSpinMutexGuard, ProvidedThreadId,
+    RawInterruptThreadReentrantSpinMutex, RawReentrantSpinMutex, RawThreadReentrantSpinMutex,
+    ReentrantSpinMutex, ReentrantSpinMutexGuard, ThreadIdProvider, ThreadReentrantSpinMutex,
+    ThreadReentrantSpinMutexGuard,
+};
 pub use mutex::spin::{RawSpinMutex, SpinMutex, SpinMutexGuard};
+#[cfg(all(
+    not(any(feature = "all-one-shot", feature = "uniprocessor")),
+    target_has_atomic = "ptr"
+))]
+pub use mutex::ticket::RawTicket;
+#[cfg(target_has_atomic = "ptr")]
 pub use mutex::ticket::{RawTicketMutex, TicketMutex, TicketMutexGuard};
+#[cfg(target_has_atomic = "ptr")]
 pub use mutex::{
-    InterruptOneShotMutex, InterruptOneShotMutexGuard, InterruptSpinMutex, InterruptSpinMutexGuard,
-    InterruptTicketMutex, InterruptTicketMutexGuard, RawInterruptOneShotMutex,
-    RawInterruptSpinMutex, RawInterruptTicketMutex,
+    InterruptAdaptiveMutex, InterruptAdaptiveMutexGuard, InterruptCnaMutex, InterruptCnaMutexGuard,
+    InterruptCohortMutex, InterruptCohortMutexGuard, InterruptMcsMutex, InterruptMcsMutexGuard,
+    InterruptOneShotMutex, InterruptOneShotMutexGuard, InterruptTicketMutex,
+    InterruptTicketMutexGuard, RawInterruptAdaptiveMutex, RawInterruptCnaMutex,
+    RawInterruptCohortMutex, RawInterruptMcsMutex, RawInterruptOneShotMutex,
+    RawInterruptTicketMutex,
 };
+pub use mutex::{
+    InterruptNakedSpinMutex, InterruptNakedSpinMutexGuard, InterruptSpinMutex,
+    InterruptSpinMutexGuard, RawInterruptNakedSpinMutex, RawInterruptSpinMutex,
+};
+#[cfg(target_has_atomic = "ptr")]
+pub use once::double_checked::DoubleCheckedCell;
+pub use once::ext::{LazyExt, OnceCellExt};
+#[cfg(target_has_atomic = "ptr")]
+pub use once::race::{OnceBool, OnceNonZeroUsize, OnceRef};
+#[cfg(target_has_atomic = "ptr")]
+pub use once::racy_lazy::RacyLazy;
+pub use once::try_lazy::{InterruptTryLazy, TryLazy};
+#[cfg(target_has_atomic = "ptr")]
 pub use one_shot_mutex::{
     OneShotMutex, OneShotMutexGuard, OneShotRwLock, OneShotRwLockReadGuard,
     OneShotRwLockUpgradableReadGuard, OneShotRwLockWriteGuard, RawOneShotMutex, RawOneShotRwLock,
 };
-pub use rwlock::{
+pub use percpu::core_local::CoreLocal;
+#[cfg(target_has_atomic = "ptr")]
+pub use percpu::counter::PerCpuCounter;
+pub use percpu::per_cpu::{CoreIdProvider, PerCpu, PerCpuIter, RegisteredCoreId};
+#[cfg(target_has_atomic = "ptr")]
+pub use percpu::refcount::PerCpuRef;
+pub use pv::{set_pv_hooks, PvHooks};
+#[cfg(target_has_atomic = "ptr")]
+pub use rwlock::adapter::{ExclusiveOnly, WriteOnly};
+#[cfg(target_has_atomic = "ptr")]
+pub use rwlock::br::{BrLock, BrLockReadGuard, BrLockWriteGuard};
+#[cfg(target_has_atomic = "ptr")]
+pub use rwlock::ext::RwLockExt;
+#[cfg(target_has_atomic = "ptr")]
+pub use rwlock::phase_fair::{
+    PhaseFairRwLock, PhaseFairRwLockReadGuard, PhaseFairRwLockWriteGuard, RawPhaseFairRwLock,
+};
+#[cfg(target_has_atomic = "ptr")]
+pub use rwlock::spin::{
     RawRwSpinLock, RwSpinLock, RwSpinLockReadGuard, RwSpinLockUpgradableReadGuard,
     RwSpinLockWriteGuard,
 };
+#[cfg(target_has_atomic = "ptr")]
+pub use rwlock::write_pref::{
+    RawRwSpinLockWritePref, RwSpinLockWritePref, RwSpinLockWritePrefReadGuard,
+    RwSpinLockWritePrefUpgradableReadGuard, RwSpinLockWritePrefWriteGuard,
+};
+#[cfg(target_has_atomic = "ptr")]
+pub use semaphore::{InterruptSemaphore, Semaphore, SemaphoreGuard};
+#[cfg(target_has_atomic = "ptr")]
+pub use seqlock::{
+    InterruptSeqLock, InterruptSeqLockWriteGuard, SeqCount, SeqLock, SeqLockWriteGuard,
+};
+pub use unsafe_cell::SyncUnsafeCell;
+#[cfg(target_has_atomic = "ptr")]
+pub use waitbitset::{BitsetKey, WaitBitset};
 
 /// A [`generic_once_cell::OnceCell`], initialized using [`RawSpinMutex`].
 pub type OnceCell<T> = generic_once_cell::OnceCell<RawSpinMutex, T>;
@@ -144,8 +558,40 @@ pub type OnceCell<T> = generic_once_cell::OnceCell<RawSpinMutex, T>;
 /// A [`generic_once_cell::Lazy`], initialized using [`RawSpinMutex`].
 pub type Lazy<T, F = fn() -> T> = generic_once_cell::Lazy<RawSpinMutex, T, F>;
 
-/// A [`generic_once_cell::OnceCell`], initialized using [`RawInterruptSpinMutex`].
-pub type InterruptOnceCell<T> = generic_once_cell::OnceCell<RawInterruptSpinMutex, T>;
+/// A [`generic_once_cell::OnceCell`], initialized using [`RawTicketMutex`].
+///
+/// Concurrent initializers wait in FIFO order.
+#[cfg(target_has_atomic = "ptr")]
+pub type TicketOnceCell<T> = generic_once_cell::OnceCell<RawTicketMutex, T>;
+
+/// A [`generic_once_cell::Lazy`], initialized using [`RawTicketMutex`].
+///
+/// Concurrent first accesses wait in FIFO order.
+#[cfg(target_has_atomic = "ptr")]
+pub type TicketLazy<T, F = fn() -> T> = generic_once_cell::Lazy<RawTicketMutex, T, F>;
+
+/// A [`generic_once_cell::OnceCell`], initialized using [`RawOneShotMutex`].
+///
+/// Recursive or concurrent initialization panics instead of deadlocking.
+#[cfg(target_has_atomic = "ptr")]
+pub type OneShotOnceCell<T> = generic_once_cell::OnceCell<RawOneShotMutex, T>;
+
+/// A [`generic_once_cell::Lazy`], initialized using [`RawOneShotMutex`].
+///
+/// Recursive or concurrent initialization panics instead of deadlocking.
+#[cfg(target_has_atomic = "ptr")]
+pub type OneShotLazy<T, F = fn() -> T> = generic_once_cell::Lazy<RawOneShotMutex, T, F>;
+
+/// A [`generic_once_cell::OnceCell`], initialized using [`RawInterruptMutex`]`<R>`.
+///
+/// By default, this is initialized using [`RawInterruptSpinMutex`].
+/// `R` can be set to any other raw mutex, such as [`RawTicketMutex`] or [`RawOneShotMutex`].
+pub type InterruptOnceCell<T, R = RawSpinMutex> =
+    generic_once_cell::OnceCell<RawInterruptMutex<R>, T>;
 
-/// A [`generic_once_cell::Lazy`], initialized using [`RawInterruptSpinMutex`].
-pub type InterruptLazy<T, F = fn() -> T> = generic_once_cell::Lazy<RawInterruptSpinMutex, T, F>;
+/// A [`generic_once_cell::Lazy`], initialized using [`RawInterruptMutex`]`<R>`.
+///
+/// By default, this is initialized using [`RawInterruptSpinMutex`].
+/// `R` can be set to any other raw mutex, such as [`RawTicketMutex`] or [`RawOneShotMutex`].
+pub type InterruptLazy<T, F = fn() -> T, R = RawSpinMutex> =
+    generic_once_cell::Lazy<RawInterruptMutex<R>, T, F>;
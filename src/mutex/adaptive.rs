@@ -0,0 +1,305 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use lock_api::{GuardSend, RawMutex};
+
+use crate::relax::{Backoff, Relax};
+use crate::stats::RawMutexSample;
+
+/// Whether the mutex is in ticket mode.
+const TICKET_MODE: usize = 1;
+
+/// The number of bits of each field.
+const FIELD_BITS: u32 = (usize::BITS - 1) / 3;
+const FIELD_MASK: usize = (1 << FIELD_BITS) - 1;
+
+/// The shift of the first field.
+///
+/// In test-and-set mode, the lowest bit is set while locked.
+/// In ticket mode, this is the next ticket.
+const NEXT_SHIFT: u32 = 1;
+const LOCKED: usize = 1 << NEXT_SHIFT;
+
+/// The shift of the ticket being served in ticket mode.
+const SERVING_SHIFT: u32 = NEXT_SHIFT + FIELD_BITS;
+
+/// The shift of the contention score.
+///
+/// In test-and-set mode, this counts contended acquisitions minus uncontended ones.
+/// In ticket mode, this counts consecutive unlocks without waiters.
+const SCORE_SHIFT: u32 = SERVING_SHIFT + FIELD_BITS;
+
+/// The score at which the mode is switched.
+const SWITCH_THRESHOLD: usize = 16;
+
+#[inline]
+fn field(state: usize, shift: u32) -> usize {
+    (state >> shift) & FIELD_MASK
+}
+
+#[inline]
+fn ticket_state(next: usize, serving: usize, score: usize) -> usize {
+    TICKET_MODE
+        | (next & FIELD_MASK) << NEXT_SHIFT
+        | (serving & FIELD_MASK) << SERVING_SHIFT
+        | score << SCORE_SHIFT
+}
+
+/// A mutex that switches between test-and-set and ticket locking depending on contention.
+///
+/// The mutex starts as a [test and test-and-set] spinlock, like [`RawSpinMutex`](crate::RawSpinMutex).
+/// When acquisitions are contended most of the time, the mutex switches to [fair] [ticket lock] behavior, like [`RawTicketMutex`](crate::RawTicketMutex).
+/// Once the mutex has been unlocked without waiters for a while, it switches back.
+/// Either way, the mutex is a single word.
+///
+/// [test and test-and-set]: https://en.wikipedia.org/wiki/Test_and_test-and-set
+/// [fair]: https://en.wikipedia.org/wiki/Unbounded_nondeterminism
+/// [ticket lock]: https://en.wikipedia.org/wiki/Ticket_lock
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use hermit_sync::AdaptiveMutex;
+///
+/// static COUNTER: AdaptiveMutex<usize> = AdaptiveMutex::new(0);
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             for _ in 0..1000 {
+///                 *COUNTER.lock() += 1;
+///             }
+///         });
+///     }
+/// });
+///
+/// assert_eq!(*COUNTER.lock(), 4000);
+/// ```
+pub struct RawAdaptiveMutex {
+    state: AtomicUsize,
+}
+
+impl RawAdaptiveMutex {
+    /// Returns `true` if this mutex currently behaves like a ticket lock.
+    ///
+    /// This is only a snapshot and might be outdated immediately.
+    #[inline]
+    pub fn is_ticket_mode(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & TICKET_MODE != 0
+    }
+
+    /// Acquires the mutex in test-and-set mode.
+    ///
+    /// Returns `false` if the mutex is in ticket mode.
+    #[inline]
+    fn lock_tas(&self) -> bool {
+        let mut contended = false;
+        let mut backoff = Backoff::default();
+        loop {
+            let state = self.state.load(Ordering::Relaxed);
+            if state & TICKET_MODE != 0 {
+                return false;
+            }
+
+            if state & LOCKED == 0 {
+                let score = field(state, SCORE_SHIFT);
+                let score = if contended {
+                    score + 1
+                } else {
+                    score.saturating_sub(1)
+                };
+                let new = if score >= SWITCH_THRESHOLD {
+                    // We hold ticket 0.
+                    ticket_state(1, 0, 0)
+                } else {
+                    LOCKED | score << SCORE_SHIFT
+                };
+
+                if self
+                    .state
+                    .compare_exchange_weak(state, new, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    return true;
+                }
+            }
+
+            contended = true;
+            backoff.relax();
+        }
+    }
+
+    /// Acquires the mutex in ticket mode.
+    #[inline]
+    fn lock_ticket(&self) {
+        let mut ticket = 0;
+        let taken = self
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |state| {
+                if state & TICKET_MODE == 0 {
+                    return None;
+                }
+                ticket = field(state, NEXT_SHIFT);
+                let serving = field(state, SERVING_SHIFT);
+                let score = field(state, SCORE_SHIFT);
+                Some(ticket_state(ticket + 1, serving, score))
+            });
+
+        if taken.is_err() {
+            // The mutex switched back to test-and-set mode.
+            self.lock();
+            return;
+        }
+
+        let mut backoff = Backoff::default();
+        while field(self.state.load(Ordering::Acquire), SERVING_SHIFT) != ticket {
+            backoff.relax();
+        }
+    }
+}
+
+unsafe impl RawMutex for RawAdaptiveMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        state: AtomicUsize::new(0),
+    };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock(&self) {
+        if !self.lock_tas() {
+            self.lock_ticket();
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.state
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |state| {
+                if state & TICKET_MODE == 0 {
+                    (state & LOCKED == 0).then_some(state | LOCKED)
+                } else {
+                    let next = field(state, NEXT_SHIFT);
+                    let serving = field(state, SERVING_SHIFT);
+                    let score = field(state, SCORE_SHIFT);
+                    (next == serving).then(|| ticket_state(next + 1, serving, score))
+                }
+            })
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let _ = self
+            .state
+            .fetch_update(Ordering::Release, Ordering::Relaxed, |state| {
+                if state & TICKET_MODE == 0 {
+                    return Some(state & !LOCKED);
+                }
+
+                let next = field(state, NEXT_SHIFT);
+                let serving = field(state, SERVING_SHIFT);
+                let waiting = next != (serving + 1) & FIELD_MASK;
+                if waiting {
+                    return Some(ticket_state(next, serving + 1, 0));
+                }
+
+                let score = field(state, SCORE_SHIFT) + 1;
+                if score >= SWITCH_THRESHOLD {
+                    Some(0)
+                } else {
+                    Some(ticket_state(next, serving + 1, score))
+                }
+            });
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        let state = self.state.load(Ordering::Relaxed);
+        if state & TICKET_MODE == 0 {
+            state & LOCKED != 0
+        } else {
+            field(state, NEXT_SHIFT) != field(state, SERVING_SHIFT)
+        }
+    }
+}
+
+impl RawMutexSample for RawAdaptiveMutex {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        let state = self.state.load(Ordering::Relaxed);
+        if state & TICKET_MODE == 0 {
+            usize::from(state & LOCKED != 0)
+        } else {
+            field(state, NEXT_SHIFT).wrapping_sub(field(state, SERVING_SHIFT)) & FIELD_MASK
+        }
+    }
+}
+
+/// A [`lock_api::Mutex`] based on [`RawAdaptiveMutex`].
+pub type AdaptiveMutex<T> = lock_api::Mutex<RawAdaptiveMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawAdaptiveMutex`].
+pub type AdaptiveMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawAdaptiveMutex, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn switches_modes() {
+        let mutex = RawAdaptiveMutex::INIT;
+        let started = AtomicBool::new(false);
+
+        // Whether the waiter observes the mutex locked depends on scheduling, so we retry until it has.
+        while !mutex.is_ticket_mode() {
+            // The mutex is locked and one more contended acquisition switches to ticket mode.
+            mutex.state.store(
+                LOCKED | (SWITCH_THRESHOLD - 1) << SCORE_SHIFT,
+                Ordering::Relaxed,
+            );
+            started.store(false, Ordering::Relaxed);
+
+            thread::scope(|s| {
+                s.spawn(|| {
+                    started.store(true, Ordering::Relaxed);
+                    mutex.lock();
+                    unsafe { mutex.unlock() };
+                });
+                while !started.load(Ordering::Relaxed) {
+                    core::hint::spin_loop();
+                }
+                thread::yield_now();
+                unsafe { mutex.unlock() };
+            });
+        }
+        assert!(mutex.is_ticket_mode());
+        assert!(!mutex.is_locked());
+
+        // Unlocks without waiters switch back to test-and-set mode.
+        for _ in 0..SWITCH_THRESHOLD {
+            mutex.lock();
+            unsafe { mutex.unlock() };
+        }
+        assert!(!mutex.is_ticket_mode());
+        assert!(!mutex.is_locked());
+    }
+
+    #[test]
+    fn ticket_mode() {
+        let mutex = RawAdaptiveMutex {
+            state: AtomicUsize::new(ticket_state(0, 0, 0)),
+        };
+        assert!(mutex.try_lock());
+        assert!(!mutex.try_lock());
+        assert_eq!(mutex.queue_depth(), 1);
+        unsafe { mutex.unlock() };
+        assert!(mutex.is_ticket_mode());
+        assert!(!mutex.is_locked());
+    }
+}
@@ -0,0 +1,229 @@
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::Ordering;
+
+use lock_api::{GuardSend, RawMutex};
+
+use super::queue::{PerCpuNode, Queue, QueueNode};
+use crate::cpu::checked_core_id;
+use crate::stats::RawMutexSample;
+
+/// The number of consecutive handoffs within a NUMA node before waiters of other NUMA nodes are served.
+pub const CNA_LOCAL_HANDOFFS: usize = 256;
+
+/// A provider of the NUMA node of the current CPU core for [`RawInterruptCnaMutex`](crate::RawInterruptCnaMutex).
+pub trait NumaNodeId {
+    /// Returns the ID of the NUMA node of the current CPU core.
+    fn numa_node_id() -> usize;
+}
+
+/// A topology with a single NUMA node.
+///
+/// With this, [`RawInterruptCnaMutex`](crate::RawInterruptCnaMutex) behaves like an MCS lock.
+#[derive(Debug)]
+pub struct SingleNode;
+
+impl NumaNodeId for SingleNode {
+    #[inline]
+    fn numa_node_id() -> usize {
+        0
+    }
+}
+
+/// A compact NUMA-aware lock with wait nodes in per-CPU statics.
+///
+/// The nodes are selected via [`core_id`](crate::core_id), so the caller must not migrate to another CPU core while locking.
+/// Waiting for the lock panics before [`init_smp`](crate::init_smp), since CPU cores might not be told apart yet.
+/// This is only exposed as [`RawInterruptCnaMutex`](crate::RawInterruptCnaMutex), which disables interrupts for this.
+pub struct RawCnaMutex<N: NumaNodeId = SingleNode> {
+    queue: Queue,
+    _numa: PhantomData<fn() -> N>,
+}
+
+impl<N: NumaNodeId> RawCnaMutex<N> {
+    #[cold]
+    fn lock_slow(&self) {
+        let node = PerCpuNode::acquire(checked_core_id(), N::numa_node_id());
+        // SAFETY: `node` is released after handing over the head of the queue.
+        unsafe { self.queue.lock_queued(&node) };
+        self.hand_over(&node);
+    }
+
+    /// Hands the head of the queue over to the next waiter.
+    fn hand_over(&self, node: &QueueNode) {
+        let secondary_head = node.secondary_head.load(Ordering::Relaxed);
+        let secondary_tail = node.secondary_tail.load(Ordering::Relaxed);
+        let local_handoffs = node.local_handoffs.load(Ordering::Relaxed);
+
+        // If the main queue is empty, the secondary queue becomes the main queue.
+        if self
+            .queue
+            .tail
+            .compare_exchange(
+                node.as_ptr(),
+                secondary_tail,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+            if let Some(secondary_head) = unsafe { secondary_head.as_ref() } {
+                secondary_head.make_head((ptr::null_mut(), ptr::null_mut()), 0);
+            }
+            return;
+        }
+
+        let next = node.wait_next();
+
+        if local_handoffs < CNA_LOCAL_HANDOFFS {
+            if let Some((local, skipped)) = Self::find_local(node, next) {
+                let secondary = match skipped {
+                    None => (secondary_head, secondary_tail),
+                    Some(last) => {
+                        // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+                        unsafe { &*last }
+                            .next
+                            .store(ptr::null_mut(), Ordering::Relaxed);
+                        // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+                        match unsafe { secondary_tail.as_ref() } {
+                            Some(tail) => {
+                                tail.next.store(next, Ordering::Relaxed);
+                                (secondary_head, last)
+                            }
+                            None => (next, last),
+                        }
+                    }
+                };
+                local.make_head(secondary, local_handoffs + 1);
+                return;
+            }
+        }
+
+        // Serve the secondary queue before the rest of the main queue.
+        // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+        match unsafe { secondary_head.as_ref() } {
+            Some(secondary_head) => {
+                // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+                unsafe { &*secondary_tail }
+                    .next
+                    .store(next, Ordering::Relaxed);
+                secondary_head.make_head((ptr::null_mut(), ptr::null_mut()), 0);
+            }
+            None => {
+                // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+                unsafe { &*next }.make_head((ptr::null_mut(), ptr::null_mut()), 0);
+            }
+        }
+    }
+
+    /// Finds the first waiter in the main queue on the NUMA node of `node`, starting at `next`.
+    ///
+    /// Returns the waiter and the last skipped waiter before it, if any.
+    /// Only waiters that are already linked are searched.
+    fn find_local<'a>(
+        node: &QueueNode,
+        next: *mut QueueNode,
+    ) -> Option<(&'a QueueNode, Option<*mut QueueNode>)> {
+        let numa_node = node.numa_node.load(Ordering::Relaxed);
+        let mut prev = None;
+        let mut current = next;
+        loop {
+            // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+            let waiter = unsafe { &*current };
+            if waiter.numa_node.load(Ordering::Relaxed) == numa_node {
+                return Some((waiter, prev));
+            }
+
+            let after = waiter.next.load(Ordering::Acquire);
+            if after.is_null() {
+                return None;
+            }
+            prev = Some(current);
+            current = after;
+        }
+    }
+}
+
+unsafe impl<N: NumaNodeId> RawMutex for RawCnaMutex<N> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        queue: Queue::INIT,
+        _numa: PhantomData,
+    };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock(&self) {
+        if !self.queue.lock_fast() {
+            self.lock_slow();
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.queue.try_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.queue.unlock();
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.queue.is_locked()
+    }
+}
+
+impl<N: NumaNodeId> RawMutexSample for RawCnaMutex<N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InterruptCnaMutex;
+
+    #[test]
+    fn smoke() {
+        let mutex = InterruptCnaMutex::<_>::new(0);
+        *mutex.lock() += 1;
+        *mutex.lock() += 1;
+        assert_eq!(*mutex.lock(), 2);
+    }
+
+    #[test]
+    fn prefers_local_waiters() {
+        let mutex = RawCnaMutex::<SingleNode>::INIT;
+        let [remote, local, holder] = [QueueNode::INIT; 3];
+        let ptr = QueueNode::as_ptr;
+
+        for (node, numa_node) in [(&remote, 1), (&local, 0), (&holder, 0)] {
+            node.numa_node.store(numa_node, Ordering::Relaxed);
+        }
+        holder.make_head((ptr::null_mut(), ptr::null_mut()), 0);
+
+        // Queue: holder -> remote -> local
+        holder.next.store(ptr(&remote), Ordering::Relaxed);
+        remote.next.store(ptr(&local), Ordering::Relaxed);
+        mutex.queue.tail.store(ptr(&local), Ordering::Relaxed);
+
+        mutex.hand_over(&holder);
+        assert!(local.head.load(Ordering::Relaxed));
+        assert!(!remote.head.load(Ordering::Relaxed));
+        assert_eq!(local.secondary_head.load(Ordering::Relaxed), ptr(&remote));
+        assert_eq!(local.local_handoffs.load(Ordering::Relaxed), 1);
+
+        // The secondary queue becomes the main queue once the main queue is empty.
+        mutex.hand_over(&local);
+        assert!(remote.head.load(Ordering::Relaxed));
+        assert_eq!(mutex.queue.tail.load(Ordering::Relaxed), ptr(&remote));
+    }
+
+    #[test]
+    #[should_panic = "per-CPU state used before init_smp"]
+    fn queue_before_init() {
+        let mutex = RawCnaMutex::<SingleNode>::INIT;
+        mutex.lock_slow();
+    }
+}
@@ -0,0 +1,231 @@
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use lock_api::{GuardNoSend, RawMutex};
+
+use crate::stats::RawMutexSample;
+use crate::{NumaNodeId, RawSpinMutex, RawTicketMutex, SingleNode};
+
+/// The number of NUMA nodes with a separate local lock in [`RawCohortMutex`].
+///
+/// NUMA nodes with larger IDs share local locks.
+pub const MAX_NUMA_NODES: usize = 8;
+
+/// The number of consecutive handoffs within a NUMA node before the global lock is released.
+pub const COHORT_LOCAL_HANDOFFS: usize = 64;
+
+struct Cohort<L> {
+    local: L,
+    /// The number of threads on this NUMA node waiting for `local`.
+    waiting: AtomicUsize,
+    /// Whether the global lock has been passed on with `local`.
+    global_passed: AtomicBool,
+    /// The number of consecutive handoffs within this cohort.
+    handoffs: AtomicUsize,
+}
+
+impl<L: RawMutex> Cohort<L> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        local: L::INIT,
+        waiting: AtomicUsize::new(0),
+        global_passed: AtomicBool::new(false),
+        handoffs: AtomicUsize::new(0),
+    };
+}
+
+/// A [cohort lock] composed of a global mutex and per-NUMA-node local mutexes.
+///
+/// Threads first acquire the local mutex of their NUMA node and then the global mutex.
+/// On unlock, if other threads of the same NUMA node are waiting, the global mutex is passed on with the local mutex instead of being released.
+/// This keeps the lock and the data it protects within a NUMA node for up to [`COHORT_LOCAL_HANDOFFS`] consecutive acquisitions.
+///
+/// The NUMA node of the current CPU core is provided by `N`.
+/// By default, the global mutex is a [`RawTicketMutex`] for fairness between NUMA nodes, and local mutexes are [`RawSpinMutex`]es.
+///
+/// [cohort lock]: https://doi.org/10.1145/2686884
+///
+/// # Examples
+///
+/// With the `uniprocessor` and `all-one-shot` features, the default local mutexes do not support contention from other threads.
+///
+#[cfg_attr(
+    not(any(feature = "all-one-shot", feature = "uniprocessor")),
+    doc = "```"
+)]
+#[cfg_attr(
+    any(feature = "all-one-shot", feature = "uniprocessor"),
+    doc = "```ignore"
+)]
+/// use std::cell::Cell;
+/// use std::thread;
+///
+/// use hermit_sync::{CohortMutex, NumaNodeId};
+///
+/// std::thread_local! {
+///     static NUMA_NODE: Cell<usize> = const { Cell::new(0) };
+/// }
+///
+/// struct ThreadNode;
+///
+/// impl NumaNodeId for ThreadNode {
+///     fn numa_node_id() -> usize {
+///         NUMA_NODE.get()
+///     }
+/// }
+///
+/// static COUNTER: CohortMutex<usize, ThreadNode> = CohortMutex::new(0);
+///
+/// thread::scope(|s| {
+///     for numa_node in 0..4 {
+///         s.spawn(move || {
+///             NUMA_NODE.set(numa_node % 2);
+///             for _ in 0..1000 {
+///                 *COUNTER.lock() += 1;
+///             }
+///         });
+///     }
+/// });
+///
+/// assert_eq!(*COUNTER.lock(), 4000);
+/// ```
+pub struct RawCohortMutex<G = RawTicketMutex, L = RawSpinMutex, N: NumaNodeId = SingleNode> {
+    global: G,
+    cohorts: [Cohort<L>; MAX_NUMA_NODES],
+    /// The cohort of the current holder.
+    holder: AtomicUsize,
+    _numa: PhantomData<fn() -> N>,
+}
+
+impl<G: RawMutex, L: RawMutex, N: NumaNodeId> RawCohortMutex<G, L, N> {
+    #[inline]
+    fn cohort() -> usize {
+        N::numa_node_id() % MAX_NUMA_NODES
+    }
+}
+
+unsafe impl<G: RawMutex, L: RawMutex, N: NumaNodeId> RawMutex for RawCohortMutex<G, L, N> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        global: G::INIT,
+        cohorts: [Cohort::INIT; MAX_NUMA_NODES],
+        holder: AtomicUsize::new(0),
+        _numa: PhantomData,
+    };
+
+    type GuardMarker = GuardNoSend;
+
+    #[inline]
+    fn lock(&self) {
+        let index = Self::cohort();
+        let cohort = &self.cohorts[index];
+
+        cohort.waiting.fetch_add(1, Ordering::Relaxed);
+        cohort.local.lock();
+        cohort.waiting.fetch_sub(1, Ordering::Relaxed);
+
+        if !cohort.global_passed.load(Ordering::Relaxed) {
+            self.global.lock();
+        }
+        self.holder.store(index, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        let index = Self::cohort();
+        let cohort = &self.cohorts[index];
+
+        if !cohort.local.try_lock() {
+            return false;
+        }
+
+        if !cohort.global_passed.load(Ordering::Relaxed) && !self.global.try_lock() {
+            // SAFETY: We have just locked `local`.
+            unsafe { cohort.local.unlock() };
+            return false;
+        }
+        self.holder.store(index, Ordering::Relaxed);
+        true
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let cohort = &self.cohorts[self.holder.load(Ordering::Relaxed)];
+
+        let handoffs = cohort.handoffs.load(Ordering::Relaxed);
+        let pass = handoffs < COHORT_LOCAL_HANDOFFS && cohort.waiting.load(Ordering::Relaxed) > 0;
+        if pass {
+            cohort.handoffs.store(handoffs + 1, Ordering::Relaxed);
+        } else {
+            cohort.handoffs.store(0, Ordering::Relaxed);
+            // SAFETY: We hold the global mutex.
+            unsafe { self.global.unlock() };
+        }
+        cohort.global_passed.store(pass, Ordering::Relaxed);
+
+        // SAFETY: We hold the local mutex.
+        unsafe { cohort.local.unlock() };
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.global.is_locked()
+    }
+}
+
+impl<G: RawMutex, L: RawMutex, N: NumaNodeId> RawMutexSample for RawCohortMutex<G, L, N> {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        let waiting: usize = self
+            .cohorts
+            .iter()
+            .map(|cohort| cohort.waiting.load(Ordering::Relaxed))
+            .sum();
+        usize::from(self.is_locked()) + waiting
+    }
+}
+
+/// A [`lock_api::Mutex`] based on [`RawCohortMutex`].
+pub type CohortMutex<T, N = SingleNode> =
+    lock_api::Mutex<RawCohortMutex<RawTicketMutex, RawSpinMutex, N>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawCohortMutex`].
+pub type CohortMutexGuard<'a, T, N = SingleNode> =
+    lock_api::MutexGuard<'a, RawCohortMutex<RawTicketMutex, RawSpinMutex, N>, T>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_global_within_cohort() {
+        let mutex = RawCohortMutex::<RawTicketMutex, RawSpinMutex>::INIT;
+        mutex.lock();
+
+        // Pretend another thread of this NUMA node is waiting.
+        mutex.cohorts[0].waiting.store(1, Ordering::Relaxed);
+        unsafe { mutex.unlock() };
+        assert!(mutex.global.is_locked());
+        assert!(!mutex.cohorts[0].local.is_locked());
+
+        mutex.cohorts[0].waiting.store(0, Ordering::Relaxed);
+        assert!(mutex.try_lock());
+        unsafe { mutex.unlock() };
+        assert!(!mutex.is_locked());
+    }
+
+    #[test]
+    fn bounded_handoffs() {
+        let mutex = RawCohortMutex::<RawTicketMutex, RawSpinMutex>::INIT;
+        mutex.cohorts[0].waiting.store(1, Ordering::Relaxed);
+        for _ in 0..COHORT_LOCAL_HANDOFFS {
+            mutex.lock();
+            unsafe { mutex.unlock() };
+            assert!(mutex.global.is_locked());
+        }
+
+        mutex.lock();
+        unsafe { mutex.unlock() };
+        assert!(!mutex.global.is_locked());
+    }
+}
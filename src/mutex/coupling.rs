@@ -0,0 +1,163 @@
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use lock_api::{Mutex, MutexGuard, RawMutex};
+
+/// A cursor for traversing linked structures of per-node mutexes hand over hand.
+///
+/// A `LockCoupling` holds the lock of exactly one node.
+/// [`step`](Self::step) locks the next node before unlocking the current one.
+/// Since stepping consumes the cursor, at most two node locks are held at once, and they are always released in order.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{LockCoupling, SpinMutex};
+///
+/// struct Node {
+///     value: usize,
+///     next: Option<&'static SpinMutex<Node>>,
+/// }
+///
+/// static TAIL: SpinMutex<Node> = SpinMutex::new(Node {
+///     value: 2,
+///     next: None,
+/// });
+/// static HEAD: SpinMutex<Node> = SpinMutex::new(Node {
+///     value: 1,
+///     next: Some(&TAIL),
+/// });
+///
+/// let mut cursor = LockCoupling::new(HEAD.lock());
+/// let mut sum = cursor.value;
+/// while let Ok(next) = cursor.step(|node| node.next) {
+///     cursor = next;
+///     sum += cursor.value;
+/// }
+/// assert_eq!(sum, 3);
+/// ```
+#[must_use = "if unused the Mutex will immediately unlock"]
+pub struct LockCoupling<'a, R: RawMutex, T: ?Sized> {
+    guard: MutexGuard<'a, R, T>,
+}
+
+impl<'a, R: RawMutex, T: ?Sized> LockCoupling<'a, R, T> {
+    /// Starts a traversal at the locked node.
+    #[inline]
+    pub fn new(guard: MutexGuard<'a, R, T>) -> Self {
+        Self { guard }
+    }
+
+    /// Steps to the next node.
+    ///
+    /// `next` returns the mutex of the next node.
+    /// The next node is locked before the current node is unlocked.
+    /// If there is no next node, the cursor is returned unchanged as an error.
+    #[inline]
+    pub fn step<F>(mut self, next: F) -> Result<Self, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&'a Mutex<R, T>>,
+    {
+        match next(&mut self.guard) {
+            Some(next) => {
+                let guard = next.lock();
+                drop(self);
+                Ok(Self { guard })
+            }
+            None => Err(self),
+        }
+    }
+
+    /// Steps to the next node, running `f` while both nodes are locked.
+    ///
+    /// This behaves like [`step`](Self::step) but allows accessing the current and the next node at the same time, for example, for unlinking nodes.
+    #[inline]
+    pub fn step_with<F, G>(mut self, next: F, f: G) -> Result<Self, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&'a Mutex<R, T>>,
+        G: FnOnce(&mut T, &mut T),
+    {
+        match next(&mut self.guard) {
+            Some(next) => {
+                let mut guard = next.lock();
+                f(&mut self.guard, &mut guard);
+                drop(self);
+                Ok(Self { guard })
+            }
+            None => Err(self),
+        }
+    }
+
+    /// Returns the guard of the current node.
+    #[inline]
+    pub fn into_guard(self) -> MutexGuard<'a, R, T> {
+        self.guard
+    }
+}
+
+impl<'a, R: RawMutex, T: ?Sized> Deref for LockCoupling<'a, R, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<'a, R: RawMutex, T: ?Sized> DerefMut for LockCoupling<'a, R, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<'a, R: RawMutex, T: fmt::Debug + ?Sized> fmt::Debug for LockCoupling<'a, R, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpinMutex;
+
+    struct Node<'a> {
+        value: usize,
+        next: Option<&'a SpinMutex<Node<'a>>>,
+    }
+
+    #[test]
+    fn step_releases_previous() {
+        let tail = SpinMutex::new(Node {
+            value: 2,
+            next: None,
+        });
+        let head = SpinMutex::new(Node {
+            value: 1,
+            next: Some(&tail),
+        });
+
+        let cursor = LockCoupling::new(head.lock());
+        let cursor = cursor
+            .step_with(
+                |node| node.next,
+                |prev, next| {
+                    assert!(head.is_locked());
+                    prev.value += next.value;
+                },
+            )
+            .unwrap_or_else(|_| unreachable!());
+        assert!(!head.is_locked());
+        assert!(tail.is_locked());
+        assert_eq!(cursor.value, 2);
+
+        let cursor = match cursor.step(|node| node.next) {
+            Ok(_) => unreachable!(),
+            Err(cursor) => cursor,
+        };
+        drop(cursor);
+        assert!(!tail.is_locked());
+        assert_eq!(head.lock().value, 3);
+    }
+}
@@ -0,0 +1,276 @@
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use lock_api::{GuardNoSend, RawMutex};
+
+use crate::stats::RawMutexSample;
+use crate::{local_irq_restore, local_irq_save, yield_now, Flags, RawTicketMutex};
+
+/// A locking strategy of [`RawDynMutex`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum DynStrategy {
+    /// Only disables interrupts.
+    ///
+    /// This is intended for early boot, while only one CPU core runs.
+    IrqOff,
+    /// Disables interrupts and spins on a ticket lock.
+    Spin,
+    /// Spins on a ticket lock and calls [`yield_now`] while waiting.
+    ///
+    /// Interrupts are not disabled.
+    /// This is intended for once the scheduler is running.
+    Yield,
+}
+
+impl DynStrategy {
+    #[inline]
+    fn from_u8(strategy: u8) -> Self {
+        match strategy {
+            0 => Self::IrqOff,
+            1 => Self::Spin,
+            _ => Self::Yield,
+        }
+    }
+}
+
+/// A mutex whose locking strategy can be switched at runtime.
+///
+/// Kernels can boot with [`DynStrategy::IrqOff`] and switch to [`DynStrategy::Yield`] once all CPU cores and the scheduler are up, without special-casing boot in every lock user.
+/// Switching via [`set_strategy`](Self::set_strategy) locks the mutex with the previous strategy, so no holder observes the switch.
+/// The mutex is always unlocked with the strategy it was locked with.
+///
+/// New mutexes start with [`DynStrategy::Spin`].
+/// With [`DynStrategy::IrqOff`], [`is_locked`](RawMutex::is_locked) always returns `false`.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{DynMutex, DynStrategy};
+///
+/// static RUN_QUEUE: DynMutex<usize> = DynMutex::new(0);
+///
+/// // SAFETY: Only the boot CPU core runs.
+/// unsafe { RUN_QUEUE.raw().set_strategy(DynStrategy::IrqOff) };
+/// *RUN_QUEUE.lock() += 1;
+///
+/// // Bring up the other CPU cores and the scheduler here.
+///
+/// // SAFETY: `Yield` has no requirements.
+/// unsafe { RUN_QUEUE.raw().set_strategy(DynStrategy::Yield) };
+/// *RUN_QUEUE.lock() += 1;
+/// assert_eq!(*RUN_QUEUE.lock(), 2);
+/// ```
+pub struct RawDynMutex {
+    strategy: AtomicU8,
+    /// The strategy this mutex was locked with.
+    locked_with: UnsafeCell<DynStrategy>,
+    irq_state: UnsafeCell<MaybeUninit<Flags>>,
+    ticket: RawTicketMutex,
+}
+
+// SAFETY: The `UnsafeCell`s are only accessed by the holder of this mutex.
+unsafe impl Sync for RawDynMutex {}
+
+impl RawDynMutex {
+    /// Returns the current locking strategy.
+    #[inline]
+    pub fn strategy(&self) -> DynStrategy {
+        DynStrategy::from_u8(self.strategy.load(Ordering::Relaxed))
+    }
+
+    /// Switches the locking strategy.
+    ///
+    /// This waits until the mutex can be locked with the current strategy.
+    ///
+    /// # Safety
+    ///
+    /// While the strategy is [`DynStrategy::IrqOff`], only one CPU core may lock this mutex.
+    /// The other strategies have no requirements.
+    #[inline]
+    pub unsafe fn set_strategy(&self, strategy: DynStrategy) {
+        self.lock();
+        self.strategy.store(strategy as u8, Ordering::Relaxed);
+        // SAFETY: We locked the mutex above with the strategy recorded in `locked_with`.
+        unsafe {
+            self.unlock();
+        }
+    }
+
+    #[inline]
+    fn lock_yield(&self) {
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "all-one-shot", feature = "uniprocessor"))] {
+                while !self.ticket.try_lock() {
+                    yield_now();
+                }
+            } else {
+                let mut ticket = self.ticket.lock_cancelable();
+                while let Err(pending) = ticket.try_acquire() {
+                    ticket = pending;
+                    yield_now();
+                }
+            }
+        }
+    }
+
+    /// Records how this mutex was locked.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold this mutex.
+    #[inline]
+    unsafe fn locked(&self, strategy: DynStrategy, flags: Option<Flags>) {
+        // SAFETY: We have exclusive access through holding the mutex.
+        unsafe {
+            self.locked_with.get().write(strategy);
+            if let Some(flags) = flags {
+                self.irq_state.get().write(MaybeUninit::new(flags));
+            }
+        }
+    }
+}
+
+unsafe impl RawMutex for RawDynMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        strategy: AtomicU8::new(DynStrategy::Spin as u8),
+        locked_with: UnsafeCell::new(DynStrategy::Spin),
+        irq_state: UnsafeCell::new(MaybeUninit::uninit()),
+        ticket: RawTicketMutex::INIT,
+    };
+
+    type GuardMarker = GuardNoSend;
+
+    #[inline]
+    fn lock(&self) {
+        let strategy = self.strategy();
+        let flags = match strategy {
+            DynStrategy::IrqOff => Some(local_irq_save()),
+            DynStrategy::Spin => {
+                let flags = local_irq_save();
+                self.ticket.lock();
+                Some(flags)
+            }
+            DynStrategy::Yield => {
+                self.lock_yield();
+                None
+            }
+        };
+        // SAFETY: We hold the mutex.
+        unsafe { self.locked(strategy, flags) };
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        let strategy = self.strategy();
+        let flags = match strategy {
+            DynStrategy::IrqOff => Some(local_irq_save()),
+            DynStrategy::Spin => {
+                let flags = local_irq_save();
+                if !self.ticket.try_lock() {
+                    local_irq_restore(flags);
+                    return false;
+                }
+                Some(flags)
+            }
+            DynStrategy::Yield => {
+                if !self.ticket.try_lock() {
+                    return false;
+                }
+                None
+            }
+        };
+        // SAFETY: We hold the mutex.
+        unsafe { self.locked(strategy, flags) };
+        true
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        // SAFETY: We have exclusive access through holding the mutex.
+        let strategy = unsafe { self.locked_with.get().read() };
+        match strategy {
+            DynStrategy::IrqOff | DynStrategy::Spin => {
+                // SAFETY: `irq_state` was initialized when locking.
+                let flags = unsafe { self.irq_state.get().read().assume_init() };
+                if strategy == DynStrategy::Spin {
+                    unsafe {
+                        self.ticket.unlock();
+                    }
+                }
+                local_irq_restore(flags);
+            }
+            DynStrategy::Yield => unsafe {
+                self.ticket.unlock();
+            },
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.ticket.is_locked()
+    }
+}
+
+impl RawMutexSample for RawDynMutex {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        self.ticket.queue_depth()
+    }
+}
+
+/// A [`lock_api::Mutex`] based on [`RawDynMutex`].
+pub type DynMutex<T> = lock_api::Mutex<RawDynMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawDynMutex`].
+pub type DynMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawDynMutex, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn switch_while_contended() {
+        const N: usize = 1000;
+
+        let mutex = DynMutex::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for _ in 0..N {
+                        *mutex.lock() += 1;
+                    }
+                });
+            }
+            for _ in 0..N / 10 {
+                // SAFETY: `Spin` and `Yield` have no requirements.
+                unsafe {
+                    mutex.raw().set_strategy(DynStrategy::Yield);
+                    mutex.raw().set_strategy(DynStrategy::Spin);
+                }
+            }
+        });
+
+        assert_eq!(*mutex.lock(), 2 * N);
+    }
+
+    #[cfg(all(unix, not(feature = "interrupt-hooks")))]
+    #[test]
+    fn irq_off() {
+        let mutex = DynMutex::new(());
+        // SAFETY: Only this thread locks the mutex.
+        unsafe { mutex.raw().set_strategy(DynStrategy::IrqOff) };
+        assert_eq!(unsafe { mutex.raw() }.strategy(), DynStrategy::IrqOff);
+
+        let guard = mutex.lock();
+        assert!(crate::irqs_disabled());
+        assert!(!mutex.is_locked());
+        drop(guard);
+        assert!(!crate::irqs_disabled());
+    }
+}
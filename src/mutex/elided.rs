@@ -0,0 +1,243 @@
+use core::arch::asm;
+use core::arch::x86_64::__cpuid_count;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use lock_api::{GuardNoSend, RawMutex};
+
+use crate::stats::RawMutexSample;
+use crate::RawSpinMutex;
+
+/// The number of transactional attempts before falling back to locking.
+const ELISION_RETRIES: usize = 3;
+
+/// The status returned by `xbegin` when a transaction has started.
+const XBEGIN_STARTED: u32 = !0;
+
+/// The status bit set by `xabort` with the code in bits 24 to 31.
+const XABORT_EXPLICIT: u32 = 1 << 0;
+
+/// The status bit set if the transaction might succeed on retry.
+const XABORT_RETRY: u32 = 1 << 1;
+
+const UNKNOWN: u8 = 0;
+const DISABLED: u8 = 1;
+const ENABLED: u8 = 2;
+
+static ELISION: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+/// Enables or disables lock elision for [`RawElidedSpinMutex`].
+///
+/// By default, lock elision is enabled if CPUID reports RTM support.
+/// Kernels can disable elision, for example, to mitigate TSX Asynchronous Abort, or enable it after detecting RTM support differently.
+///
+/// # Safety
+///
+/// If `enabled` is `true`, the CPU must support RTM.
+/// Otherwise, locking executes RTM instructions that the CPU does not support.
+pub unsafe fn set_lock_elision(enabled: bool) {
+    let elision = if enabled { ENABLED } else { DISABLED };
+    ELISION.store(elision, Ordering::Relaxed);
+}
+
+#[inline]
+fn elision_enabled() -> bool {
+    match ELISION.load(Ordering::Relaxed) {
+        ENABLED => true,
+        DISABLED => false,
+        _ => {
+            let enabled = rtm_supported();
+            let elision = if enabled { ENABLED } else { DISABLED };
+            let _ =
+                ELISION.compare_exchange(UNKNOWN, elision, Ordering::Relaxed, Ordering::Relaxed);
+            enabled
+        }
+    }
+}
+
+#[cold]
+fn rtm_supported() -> bool {
+    const RTM: u32 = 1 << 11;
+
+    let max_leaf = __cpuid_count(0, 0).eax;
+    max_leaf >= 7 && __cpuid_count(7, 0).ebx & RTM != 0
+}
+
+/// Starts a transaction.
+///
+/// Returns [`XBEGIN_STARTED`] inside the transaction and the abort status after an abort.
+///
+/// # Safety
+///
+/// The CPU must support RTM.
+#[inline]
+unsafe fn xbegin() -> u32 {
+    let status: u32;
+    unsafe {
+        asm!(
+            "xbegin 2f",
+            "2:",
+            inout("eax") XBEGIN_STARTED => status,
+            options(nostack)
+        );
+    }
+    status
+}
+
+/// Commits the current transaction.
+///
+/// # Safety
+///
+/// The CPU must be executing a transaction.
+#[inline]
+unsafe fn xend() {
+    unsafe {
+        asm!("xend", options(nostack));
+    }
+}
+
+/// Aborts the current transaction because the lock is busy.
+///
+/// # Safety
+///
+/// The CPU must support RTM.
+#[inline]
+unsafe fn xabort_busy() {
+    unsafe {
+        asm!("xabort 0xff", options(nostack));
+    }
+}
+
+/// A [`RawSpinMutex`] with [hardware lock elision] via Intel TSX RTM.
+///
+/// Locking first tries to run the critical section as a hardware transaction that only reads the lock.
+/// Holders of the same mutex then run concurrently unless their critical sections conflict.
+/// After a few aborted transactions, the mutex falls back to locking the [`RawSpinMutex`], which aborts all concurrent transactions.
+///
+/// Elision is enabled if CPUID reports RTM support (see [`set_lock_elision`]).
+/// [`is_locked`](RawMutex::is_locked) does not report elided holders.
+/// Critical sections should be short and avoid instructions that always abort transactions, such as system calls or I/O.
+///
+/// [hardware lock elision]: https://en.wikipedia.org/wiki/Transactional_Synchronization_Extensions
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::ElidedSpinMutex;
+///
+/// static COUNTER: ElidedSpinMutex<usize> = ElidedSpinMutex::new(0);
+///
+/// *COUNTER.lock() += 1;
+/// assert_eq!(*COUNTER.lock(), 1);
+/// ```
+pub struct RawElidedSpinMutex {
+    inner: RawSpinMutex,
+}
+
+impl RawElidedSpinMutex {
+    /// Tries to elide the lock.
+    ///
+    /// Returns `true` if a transaction has been started.
+    #[inline]
+    fn try_elide(&self) -> bool {
+        if !elision_enabled() {
+            return false;
+        }
+
+        for _ in 0..ELISION_RETRIES {
+            // SAFETY: Elision is only enabled with RTM support.
+            let status = unsafe { xbegin() };
+            if status == XBEGIN_STARTED {
+                // Reading the lock adds it to our read set, so locking it aborts us.
+                if !self.inner.is_locked() {
+                    return true;
+                }
+                // SAFETY: Elision is only enabled with RTM support.
+                unsafe { xabort_busy() };
+            }
+
+            if status & XABORT_EXPLICIT != 0 {
+                // The lock was busy.
+                while self.inner.is_locked() {
+                    core::hint::spin_loop();
+                }
+            } else if status & XABORT_RETRY == 0 {
+                return false;
+            }
+        }
+
+        false
+    }
+}
+
+unsafe impl RawMutex for RawElidedSpinMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        inner: RawSpinMutex::INIT,
+    };
+
+    type GuardMarker = GuardNoSend;
+
+    #[inline]
+    fn lock(&self) {
+        if !self.try_elide() {
+            self.inner.lock();
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.inner.try_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        if self.inner.is_locked() {
+            // We hold the lock for real, since locking it would have aborted a transaction.
+            unsafe {
+                self.inner.unlock();
+            }
+        } else {
+            // SAFETY: The lock is unlocked, so we are running a transaction.
+            unsafe { xend() };
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+impl RawMutexSample for RawElidedSpinMutex {}
+
+/// A [`lock_api::Mutex`] based on [`RawElidedSpinMutex`].
+pub type ElidedSpinMutex<T> = lock_api::Mutex<RawElidedSpinMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawElidedSpinMutex`].
+pub type ElidedSpinMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawElidedSpinMutex, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn lots_and_lots() {
+        const J: u32 = 1000;
+        const K: u32 = 3;
+
+        let mutex = ElidedSpinMutex::new(0);
+        thread::scope(|s| {
+            for _ in 0..K {
+                s.spawn(|| {
+                    for _ in 0..J {
+                        *mutex.lock() += 1;
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*mutex.lock(), J * K);
+    }
+}
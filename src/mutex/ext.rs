@@ -0,0 +1,52 @@
+use core::mem;
+
+use lock_api::{Mutex, RawMutex};
+
+/// Convenience methods for [`lock_api::Mutex`]es.
+///
+/// These methods lock, swap the data, and unlock immediately, which avoids holding guards for too long in slot-like usages.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{MutexExt, SpinMutex};
+///
+/// static SLOT: SpinMutex<Option<usize>> = SpinMutex::new(None);
+///
+/// SLOT.set(Some(1));
+/// assert_eq!(SLOT.replace(Some(2)), Some(1));
+/// assert_eq!(SLOT.take(), Some(2));
+/// assert_eq!(*SLOT.lock(), None);
+/// ```
+pub trait MutexExt<T> {
+    /// Sets the data, dropping the previous value after unlocking.
+    fn set(&self, value: T);
+
+    /// Replaces the data, returning the previous value.
+    fn replace(&self, value: T) -> T;
+
+    /// Takes the data, leaving [`Default::default()`] in its place.
+    fn take(&self) -> T
+    where
+        T: Default;
+}
+
+impl<R: RawMutex, T> MutexExt<T> for Mutex<R, T> {
+    #[inline]
+    fn set(&self, value: T) {
+        drop(self.replace(value));
+    }
+
+    #[inline]
+    fn replace(&self, value: T) -> T {
+        mem::replace(&mut *self.lock(), value)
+    }
+
+    #[inline]
+    fn take(&self) -> T
+    where
+        T: Default,
+    {
+        mem::take(&mut *self.lock())
+    }
+}
@@ -0,0 +1,507 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+
+use lock_api::{GuardNoSend, RawMutex, RawMutexFair, RawMutexTimed};
+
+use crate::stats::RawMutexSample;
+use crate::{interrupts_managed, irqs_disabled, Flags, InterruptControl, NativeInterrupts};
+
+/// A mutex for sharing data with interrupt handlers or signal handlers.
+///
+/// This mutex wraps another [`RawMutex`] and disables interrupts while locked.
+/// When the mutex is unlocked, the previous interrupt state is restored.
+///
+/// Locking corresponds to Linux's `spin_lock_irqsave` and unlocking corresponds to `spin_unlock_irqrestore`.
+/// While `spin_lock_irqsave(lock, flags)` saves the interrupt flags in the explicit `flags` argument, this mutex saves the interrupt flags internally.
+///
+/// # Caveats
+///
+/// Interrupts are disabled on a best-effort basis.
+/// Holding a guard does not guarantee that interrupts are disabled.
+/// Dropping guards from different mutexes in the wrong order might enable interrupts prematurely.
+/// [`NestedInterrupts`](crate::NestedInterrupts) as `C` allows dropping guards in any order.
+///
+/// # Context switches
+///
+/// Schedulers often hold a lock across a context switch:
+/// the previous task locks the run queue and the next task unlocks it.
+/// Since the saved interrupt state belongs to the task that locked the mutex, it must not be restored by another task.
+/// [`save_irq_state`] takes the saved interrupt state out of the locked mutex so that the previous task can keep it.
+/// [`adopt_irq_state`] installs the interrupt state of the next task before that task unlocks the mutex.
+///
+/// [`save_irq_state`]: Self::save_irq_state
+/// [`adopt_irq_state`]: Self::adopt_irq_state
+///
+/// # Timeouts
+///
+/// [`RawMutexTimed`] is implemented if `I` implements it, with the timeouts of `I`.
+/// Timed locking is delegated to `I`, so that `I` keeps its own waiting strategy, such as FIFO order.
+/// Like [`lock`](RawMutex::lock), this disables interrupts while waiting for `I`.
+///
+/// # Interrupt control
+///
+/// By default, this mutex controls interrupts via [`local_irq_save`](crate::local_irq_save) and [`local_irq_restore`](crate::local_irq_restore).
+/// `C` can be set to any other [`InterruptControl`], for example, to use paravirtual interrupt interfaces.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{local_irq_save, Flags, InterruptSpinMutex};
+///
+/// struct Task {
+///     irq_state: Option<Flags>,
+/// }
+///
+/// static RUN_QUEUE: InterruptSpinMutex<()> = InterruptSpinMutex::new(());
+///
+/// fn switch(prev: &mut Task, next: &mut Task) {
+///     core::mem::forget(RUN_QUEUE.lock());
+///     // SAFETY: `RUN_QUEUE` is locked by us and the state is adopted again below.
+///     prev.irq_state = Some(unsafe { RUN_QUEUE.raw().save_irq_state() });
+///
+///     // Switch stacks here.
+///
+///     let flags = next.irq_state.take().unwrap();
+///     // SAFETY: `RUN_QUEUE` is locked and `flags` were saved on this CPU.
+///     unsafe {
+///         RUN_QUEUE.raw().adopt_irq_state(flags);
+///         RUN_QUEUE.force_unlock();
+///     }
+/// }
+///
+/// let mut prev = Task { irq_state: None };
+/// // The next task was switched out with interrupts enabled.
+/// let mut next = Task {
+///     irq_state: Some(local_irq_save()),
+/// };
+/// # hermit_sync::local_irq_restore(next.irq_state.unwrap());
+/// switch(&mut prev, &mut next);
+/// assert!(prev.irq_state.is_some());
+/// assert!(!RUN_QUEUE.is_locked());
+/// ```
+pub struct RawInterruptMutex<I, C: InterruptControl = NativeInterrupts> {
+    inner: I,
+    irq_state: UnsafeCell<MaybeUninit<C::Flags>>,
+}
+
+// SAFETY: The `UnsafeCell` is locked by `inner`, initialized on `lock` and read on `unlock`.
+unsafe impl<I: Sync, C: InterruptControl> Sync for RawInterruptMutex<I, C> where C::Flags: Send {}
+// SAFETY: Mutexes cannot be send to other threads while locked.
+// Sending them while unlocked is fine.
+unsafe impl<I: Send, C: InterruptControl> Send for RawInterruptMutex<I, C> {}
+
+impl<I: RawMutex, C: InterruptControl> RawInterruptMutex<I, C> {
+    /// Disables interrupts and tries to lock `inner` via `try_lock`.
+    ///
+    /// Interrupts are restored if `try_lock` fails.
+    #[inline]
+    fn try_lock_with(&self, try_lock: impl FnOnce(&I) -> bool) -> bool {
+        let flags = C::save_disable();
+        let ok = try_lock(&self.inner);
+        if ok {
+            // SAFETY: We have exclusive access through locking `inner`.
+            unsafe {
+                self.irq_state.get().write(MaybeUninit::new(flags));
+            }
+        } else {
+            C::restore(flags);
+        }
+        ok
+    }
+
+    /// Takes the interrupt state that is restored on unlocking.
+    ///
+    /// This is intended for holding the mutex across a context switch.
+    /// Interrupts stay disabled and the mutex stays locked.
+    ///
+    /// # Safety
+    ///
+    /// This mutex must be locked in the current context.
+    /// Before unlocking, an interrupt state must be installed via [`adopt_irq_state`](Self::adopt_irq_state).
+    #[inline]
+    pub unsafe fn save_irq_state(&self) -> C::Flags {
+        // SAFETY: We have exclusive access through locking `inner`.
+        let irq_state = unsafe { self.irq_state.get().replace(MaybeUninit::uninit()) };
+        // SAFETY: `irq_state` was initialized when locking or adopting.
+        unsafe { irq_state.assume_init() }
+    }
+
+    /// Installs the interrupt state that is restored on unlocking.
+    ///
+    /// This is intended for holding the mutex across a context switch.
+    ///
+    /// # Safety
+    ///
+    /// This mutex must be locked in the current context and its interrupt state must have been taken via [`save_irq_state`](Self::save_irq_state).
+    /// `flags` must have been saved on the current CPU, for example by [`save_irq_state`](Self::save_irq_state) or [`InterruptControl::save_disable`].
+    #[inline]
+    pub unsafe fn adopt_irq_state(&self, flags: C::Flags) {
+        // SAFETY: We have exclusive access through locking `inner`.
+        unsafe {
+            self.irq_state.get().write(MaybeUninit::new(flags));
+        }
+    }
+}
+
+impl<I: RawMutex> RawInterruptMutex<I> {
+    /// Locks this mutex from a context that already has interrupts disabled, such as an interrupt handler.
+    ///
+    /// This skips saving the interrupt state, and unlocking leaves interrupts disabled.
+    /// In debug builds, this asserts that interrupts are disabled.
+    #[inline]
+    #[track_caller]
+    pub fn lock_from_irq(&self) {
+        debug_assert!(
+            !interrupts_managed() || irqs_disabled(),
+            "locked from IRQ with interrupts enabled"
+        );
+        self.inner.lock();
+        // SAFETY: We have exclusive access through locking `inner`.
+        unsafe {
+            self.irq_state.get().write(MaybeUninit::new(Flags::EMPTY));
+        }
+    }
+}
+
+unsafe impl<I: RawMutex, C: InterruptControl> RawMutex for RawInterruptMutex<I, C> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        inner: I::INIT,
+        irq_state: UnsafeCell::new(MaybeUninit::uninit()),
+    };
+
+    type GuardMarker = GuardNoSend;
+
+    #[inline]
+    fn lock(&self) {
+        let flags = C::save_disable();
+        self.inner.lock();
+        // SAFETY: We have exclusive access through locking `inner`.
+        unsafe {
+            self.irq_state.get().write(MaybeUninit::new(flags));
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.try_lock_with(I::try_lock)
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        // SAFETY: We have exclusive access through locking `inner`.
+        let flags = unsafe { self.save_irq_state() };
+        unsafe {
+            self.inner.unlock();
+        }
+        C::restore(flags);
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+unsafe impl<I: RawMutexFair, C: InterruptControl> RawMutexFair for RawInterruptMutex<I, C> {
+    #[inline]
+    unsafe fn unlock_fair(&self) {
+        // SAFETY: We have exclusive access through locking `inner`.
+        let flags = unsafe { self.save_irq_state() };
+        unsafe {
+            self.inner.unlock_fair();
+        }
+        C::restore(flags);
+    }
+
+    #[inline]
+    unsafe fn bump(&self) {
+        // Bump `inner` directly, so that we keep our position in its queue.
+        // Interrupts stay disabled, since we get `inner` back without locking it again.
+        // Otherwise, interrupt handlers on this CPU core could queue up behind us and deadlock.
+        unsafe {
+            self.inner.bump();
+        }
+    }
+}
+
+unsafe impl<I: RawMutexTimed, C: InterruptControl> RawMutexTimed for RawInterruptMutex<I, C> {
+    type Duration = I::Duration;
+    type Instant = I::Instant;
+
+    #[inline]
+    fn try_lock_for(&self, timeout: Self::Duration) -> bool {
+        self.try_lock_with(|inner| inner.try_lock_for(timeout))
+    }
+
+    #[inline]
+    fn try_lock_until(&self, timeout: Self::Instant) -> bool {
+        self.try_lock_with(|inner| inner.try_lock_until(timeout))
+    }
+}
+
+impl<I: RawMutexSample, C: InterruptControl> RawMutexSample for RawInterruptMutex<I, C> {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        self.inner.queue_depth()
+    }
+}
+
+/// A [`lock_api::Mutex`] based on [`RawInterruptMutex`].
+pub type InterruptMutex<I, T> = lock_api::Mutex<RawInterruptMutex<I>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawInterruptMutex`].
+pub type InterruptMutexGuard<'a, I, T> = lock_api::MutexGuard<'a, RawInterruptMutex<I>, T>;
+
+/// Extension methods for [`InterruptMutex`]es.
+pub trait InterruptMutexExt<I: RawMutex, T: ?Sized> {
+    /// Locks this mutex from a context that already has interrupts disabled, such as an interrupt handler.
+    ///
+    /// See [`RawInterruptMutex::lock_from_irq`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::{without_interrupts, InterruptMutexExt, InterruptSpinMutex};
+    ///
+    /// static EVENTS: InterruptSpinMutex<usize> = InterruptSpinMutex::new(0);
+    ///
+    /// fn interrupt_handler() {
+    ///     *EVENTS.lock_from_irq() += 1;
+    /// }
+    ///
+    /// without_interrupts(interrupt_handler);
+    /// assert_eq!(*EVENTS.lock(), 1);
+    /// ```
+    fn lock_from_irq(&self) -> InterruptMutexGuard<'_, I, T>;
+}
+
+impl<I: RawMutex, T: ?Sized> InterruptMutexExt<I, T> for InterruptMutex<I, T> {
+    #[inline]
+    #[track_caller]
+    fn lock_from_irq(&self) -> InterruptMutexGuard<'_, I, T> {
+        // SAFETY: We only lock the raw mutex and create a guard for it.
+        unsafe {
+            self.raw().lock_from_irq();
+            self.make_guard_unchecked()
+        }
+    }
+}
+
+/// An [`InterruptMutexGuard`] that can be sent to other CPUs.
+///
+/// [`InterruptMutexGuard`]s are `!Send`, since the saved interrupt state belongs to the CPU that locked the mutex.
+/// Kernels that migrate a locked context between CPUs can opt out of this restriction with this wrapper.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{InterruptSpinMutex, SendInterruptMutexGuard};
+///
+/// static NUMBER: InterruptSpinMutex<usize> = InterruptSpinMutex::new(0);
+///
+/// let mut guard = std::thread::spawn(|| {
+///     // SAFETY: Both threads have the same interrupt state.
+///     unsafe { SendInterruptMutexGuard::new(NUMBER.lock()) }
+/// })
+/// .join()
+/// .unwrap();
+/// *guard = 1;
+/// drop(guard);
+/// assert_eq!(*NUMBER.lock(), 1);
+/// ```
+#[must_use = "if unused the Mutex will immediately unlock"]
+pub struct SendInterruptMutexGuard<'a, I: RawMutex, T: ?Sized>(InterruptMutexGuard<'a, I, T>);
+
+// SAFETY: The creator of this guard guarantees that migrating it is fine.
+unsafe impl<'a, I: RawMutex + Sync + 'a, T: ?Sized + Send + 'a> Send
+    for SendInterruptMutexGuard<'a, I, T>
+{
+}
+
+impl<'a, I: RawMutex + 'a, T: ?Sized + 'a> SendInterruptMutexGuard<'a, I, T> {
+    /// Wraps an [`InterruptMutexGuard`] to make it `Send`.
+    ///
+    /// # Safety
+    ///
+    /// If this guard is sent to another CPU, the caller must ensure that
+    /// * interrupts stay disabled on the current CPU until the guard is dropped or interrupts are restored otherwise,
+    /// * the saved interrupt state is valid for the CPU that drops the guard, and
+    /// * the inner mutex `I` can be unlocked from a different CPU than the locking one.
+    #[inline]
+    pub unsafe fn new(guard: InterruptMutexGuard<'a, I, T>) -> Self {
+        Self(guard)
+    }
+
+    /// Unwraps the [`InterruptMutexGuard`].
+    #[inline]
+    pub fn into_inner(s: Self) -> InterruptMutexGuard<'a, I, T> {
+        s.0
+    }
+}
+
+impl<'a, I: RawMutex + 'a, T: ?Sized + 'a> Deref for SendInterruptMutexGuard<'a, I, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<'a, I: RawMutex + 'a, T: ?Sized + 'a> DerefMut for SendInterruptMutexGuard<'a, I, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<'a, I: RawMutex + 'a, T: fmt::Debug + ?Sized + 'a> fmt::Debug
+    for SendInterruptMutexGuard<'a, I, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RawSpinMutex;
+
+    #[test]
+    fn irq_state_handoff() {
+        let mutex = InterruptMutex::<RawSpinMutex, _>::new(());
+
+        core::mem::forget(mutex.lock());
+        let flags = unsafe { mutex.raw().save_irq_state() };
+        assert!(mutex.is_locked());
+
+        unsafe {
+            mutex.raw().adopt_irq_state(flags);
+            mutex.force_unlock();
+        }
+        assert!(!mutex.is_locked());
+        drop(mutex.lock());
+    }
+
+    #[test]
+    fn unlock_fair_restores() {
+        let mutex = InterruptMutex::<crate::RawTicketMutex, _>::new(0);
+
+        let mut guard = mutex.lock();
+        *guard += 1;
+        lock_api::MutexGuard::bump(&mut guard);
+        *guard += 1;
+        lock_api::MutexGuard::unlock_fair(guard);
+
+        assert!(!mutex.is_locked());
+        assert_eq!(*mutex.lock(), 2);
+    }
+
+    #[cfg(not(any(feature = "all-one-shot", feature = "uniprocessor")))]
+    #[test]
+    fn bump_keeps_queue_position() {
+        use std::thread;
+
+        let mutex = InterruptMutex::<crate::RawTicketMutex, Vec<u32>>::new(Vec::new());
+        let raw = unsafe { mutex.raw() };
+        let wait_for_queue_depth = |n| {
+            while raw.queue_depth() != n {
+                thread::yield_now();
+            }
+        };
+
+        let mut guard = mutex.lock();
+        thread::scope(|s| {
+            s.spawn(|| mutex.lock().push(1));
+            wait_for_queue_depth(2);
+            s.spawn(|| mutex.lock().push(2));
+            wait_for_queue_depth(3);
+
+            lock_api::MutexGuard::bump(&mut guard);
+            guard.push(0);
+            drop(guard);
+        });
+
+        assert_eq!(*mutex.lock(), [1, 0, 2]);
+    }
+
+    #[test]
+    fn try_lock_for() {
+        use std::time::{Duration, Instant};
+
+        /// A [`RawSpinMutex`] with timeouts.
+        struct RawTimedMutex(RawSpinMutex);
+
+        unsafe impl RawMutex for RawTimedMutex {
+            #[allow(clippy::declare_interior_mutable_const)]
+            const INIT: Self = Self(RawSpinMutex::INIT);
+
+            type GuardMarker = GuardNoSend;
+
+            fn lock(&self) {
+                self.0.lock();
+            }
+
+            fn try_lock(&self) -> bool {
+                self.0.try_lock()
+            }
+
+            unsafe fn unlock(&self) {
+                unsafe { self.0.unlock() }
+            }
+        }
+
+        unsafe impl RawMutexTimed for RawTimedMutex {
+            type Duration = Duration;
+            type Instant = Instant;
+
+            fn try_lock_for(&self, timeout: Self::Duration) -> bool {
+                self.try_lock_until(Instant::now() + timeout)
+            }
+
+            fn try_lock_until(&self, timeout: Self::Instant) -> bool {
+                while !self.try_lock() {
+                    if Instant::now() >= timeout {
+                        return false;
+                    }
+                    core::hint::spin_loop();
+                }
+                true
+            }
+        }
+
+        let mutex = InterruptMutex::<RawTimedMutex, _>::new(0);
+
+        let guard = mutex.lock();
+        assert!(mutex.try_lock_for(Duration::from_millis(10)).is_none());
+        drop(guard);
+
+        *mutex.try_lock_for(Duration::from_millis(10)).unwrap() += 1;
+        assert!(!mutex.is_locked());
+        assert_eq!(*mutex.lock(), 1);
+    }
+
+    #[cfg(all(unix, not(miri), not(feature = "interrupt-hooks")))]
+    #[test]
+    fn lock_from_irq() {
+        let mutex = InterruptMutex::<RawSpinMutex, _>::new(());
+
+        crate::without_interrupts(|| {
+            drop(mutex.lock_from_irq());
+            assert!(irqs_disabled());
+        });
+        assert!(!irqs_disabled());
+    }
+
+    #[cfg(all(unix, not(miri), not(feature = "interrupt-hooks"), debug_assertions))]
+    #[test]
+    #[should_panic = "locked from IRQ with interrupts enabled"]
+    fn lock_from_irq_enabled() {
+        let mutex = InterruptMutex::<RawSpinMutex, _>::new(());
+        drop(mutex.lock_from_irq());
+    }
+}
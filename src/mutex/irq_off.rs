@@ -0,0 +1,99 @@
+use lock_api::RawMutex;
+
+use crate::stats::RawMutexSample;
+use crate::{interrupts_managed, irqs_disabled};
+
+/// A mutex that debug-asserts that interrupts are disabled whenever it is locked.
+///
+/// This mutex wraps another [`RawMutex`].
+/// It is intended for locks that are only ever taken in interrupt handlers or with interrupts disabled.
+/// In contrast to [`RawInterruptMutex`](crate::RawInterruptMutex), this does not save and restore the interrupt state but only catches misuse.
+/// In release builds, this is as cheap as the wrapped mutex.
+///
+/// While interrupts are unmanaged (see [`set_interrupts_managed`](crate::set_interrupts_managed)), nothing is asserted.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{without_interrupts, IrqOffChecked, RawSpinMutex};
+///
+/// static TIMERS: lock_api::Mutex<IrqOffChecked<RawSpinMutex>, usize> = lock_api::Mutex::new(0);
+///
+/// without_interrupts(|| *TIMERS.lock() += 1);
+/// ```
+pub struct IrqOffChecked<I> {
+    inner: I,
+}
+
+impl<I> IrqOffChecked<I> {
+    #[inline]
+    #[track_caller]
+    fn check() {
+        debug_assert!(
+            !interrupts_managed() || irqs_disabled(),
+            "locked with interrupts enabled"
+        );
+    }
+}
+
+unsafe impl<I: RawMutex> RawMutex for IrqOffChecked<I> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self { inner: I::INIT };
+
+    type GuardMarker = I::GuardMarker;
+
+    #[inline]
+    #[track_caller]
+    fn lock(&self) {
+        Self::check();
+        self.inner.lock();
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_lock(&self) -> bool {
+        Self::check();
+        self.inner.try_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        unsafe {
+            self.inner.unlock();
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+impl<I: RawMutexSample> RawMutexSample for IrqOffChecked<I> {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        self.inner.queue_depth()
+    }
+}
+
+#[cfg(all(test, unix, debug_assertions, not(feature = "interrupt-hooks")))]
+mod tests {
+    use lock_api::Mutex;
+
+    use super::*;
+    use crate::{without_interrupts, RawSpinMutex};
+
+    #[test]
+    fn interrupts_disabled() {
+        let mutex = Mutex::<IrqOffChecked<RawSpinMutex>, _>::new(0);
+        without_interrupts(|| *mutex.lock() += 1);
+        assert_eq!(without_interrupts(|| *mutex.lock()), 1);
+    }
+
+    #[test]
+    #[should_panic = "locked with interrupts enabled"]
+    fn interrupts_enabled() {
+        let mutex = Mutex::<IrqOffChecked<RawSpinMutex>, _>::new(0);
+        *mutex.lock() += 1;
+    }
+}
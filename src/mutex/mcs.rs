@@ -0,0 +1,208 @@
+use core::ptr;
+use core::sync::atomic::Ordering;
+
+use lock_api::{GuardSend, RawMutex};
+
+use super::queue::{PerCpuNode, Queue, QueueNode};
+use crate::cpu::checked_core_id;
+use crate::stats::RawMutexSample;
+
+/// An [MCS lock] with wait nodes on the stack of each waiter.
+///
+/// Waiters queue up and each spins on its own node, so contended locks do not bounce a shared cache line between waiters.
+/// A node is only needed until its waiter has locked the mutex, so it lives in the stack frame of [`lock`](RawMutex::lock).
+/// Thus, this is a plain [`RawMutex`] with plain [`lock_api`] guards that neither depends on [`core_id`](crate::core_id) nor disables interrupts.
+/// For wait nodes in per-CPU statics, see [`RawInterruptMcsMutex`](crate::RawInterruptMcsMutex).
+///
+/// [MCS lock]: https://lwn.net/Articles/590243/
+///
+/// # Examples
+///
+/// With the `all-one-shot` feature, this mutex does not support contention from other threads.
+///
+#[cfg_attr(not(feature = "all-one-shot"), doc = "```")]
+#[cfg_attr(feature = "all-one-shot", doc = "```ignore")]
+/// use std::thread;
+///
+/// use hermit_sync::McsMutex;
+///
+/// static COUNTER: McsMutex<usize> = McsMutex::new(0);
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             for _ in 0..1000 {
+///                 *COUNTER.lock() += 1;
+///             }
+///         });
+///     }
+/// });
+///
+/// assert_eq!(*COUNTER.lock(), 4000);
+/// ```
+// Based on Linux's `qspinlock`, which also releases its node once it has acquired the lock.
+pub struct RawMcsMutex {
+    queue: Queue,
+}
+
+impl RawMcsMutex {
+    #[cold]
+    fn lock_slow(&self) {
+        let node = QueueNode::INIT;
+        // SAFETY: `node` outlives this call.
+        unsafe { lock_queued(&self.queue, &node) }
+    }
+}
+
+/// An MCS lock with wait nodes in per-CPU statics.
+///
+/// The nodes are selected via [`core_id`](crate::core_id), so the caller must not migrate to another CPU core while locking.
+/// Waiting for the lock panics before [`init_smp`](crate::init_smp), since CPU cores might not be told apart yet.
+/// This is only exposed as [`RawInterruptMcsMutex`](crate::RawInterruptMcsMutex), which disables interrupts for this.
+pub struct RawPerCpuMcsMutex {
+    queue: Queue,
+}
+
+impl RawPerCpuMcsMutex {
+    #[cold]
+    fn lock_slow(&self) {
+        let node = PerCpuNode::acquire(checked_core_id(), 0);
+        // SAFETY: `node` is released after handing over the head of the queue.
+        unsafe { lock_queued(&self.queue, &node) }
+    }
+}
+
+/// Queues up `node` on `queue`, waits until we have locked, and hands the head of the queue over to our successor.
+///
+/// # Safety
+///
+/// `node` must stay valid until this returns.
+pub(crate) unsafe fn lock_queued(queue: &Queue, node: &QueueNode) {
+    // SAFETY: We hand over the head of the queue before returning.
+    unsafe { queue.lock_queued(node) };
+
+    if queue
+        .tail
+        .compare_exchange(
+            node.as_ptr(),
+            ptr::null_mut(),
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        )
+        .is_err()
+    {
+        let next = node.wait_next();
+        // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+        unsafe { &*next }.head.store(true, Ordering::Release);
+    }
+}
+
+unsafe impl RawMutex for RawMcsMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self { queue: Queue::INIT };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock(&self) {
+        if !self.queue.lock_fast() {
+            self.lock_slow();
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.queue.try_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.queue.unlock();
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.queue.is_locked()
+    }
+}
+
+impl RawMutexSample for RawMcsMutex {}
+
+unsafe impl RawMutex for RawPerCpuMcsMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self { queue: Queue::INIT };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock(&self) {
+        if !self.queue.lock_fast() {
+            self.lock_slow();
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.queue.try_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.queue.unlock();
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.queue.is_locked()
+    }
+}
+
+impl RawMutexSample for RawPerCpuMcsMutex {}
+
+/// A [`lock_api::Mutex`] based on [`RawMcsMutex`].
+pub type McsMutex<T> = lock_api::Mutex<RawMcsMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawMcsMutex`].
+pub type McsMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawMcsMutex, T>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InterruptMcsMutex;
+
+    #[test]
+    fn smoke() {
+        let mutex = McsMutex::new(0);
+        *mutex.lock() += 1;
+        *mutex.lock() += 1;
+        assert_eq!(*mutex.lock(), 2);
+    }
+
+    #[test]
+    fn interrupt_smoke() {
+        let mutex = InterruptMcsMutex::new(0);
+        *mutex.lock() += 1;
+        *mutex.lock() += 1;
+        assert_eq!(*mutex.lock(), 2);
+    }
+
+    #[test]
+    fn queue_is_emptied() {
+        let mutex = RawMcsMutex::INIT;
+        let node = QueueNode::INIT;
+        unsafe { lock_queued(&mutex.queue, &node) };
+        assert!(mutex.is_locked());
+        assert!(!mutex.try_lock());
+        assert!(mutex.queue.tail.load(Ordering::Relaxed).is_null());
+        unsafe { mutex.unlock() };
+        assert!(mutex.try_lock());
+        unsafe { mutex.unlock() };
+    }
+
+    #[test]
+    #[should_panic = "per-CPU state used before init_smp"]
+    fn queue_before_init() {
+        let mutex = RawPerCpuMcsMutex::INIT;
+        mutex.lock_slow();
+    }
+}
@@ -1,43 +1,152 @@
-#[cfg(not(feature = "all-one-shot"))]
+#[cfg(all(not(feature = "all-one-shot"), target_has_atomic = "ptr"))]
+pub(crate) mod adaptive;
+#[cfg(all(feature = "all-one-shot", target_has_atomic = "ptr"))]
+pub(crate) mod adaptive {
+    pub use one_shot_mutex::{
+        OneShotMutex as AdaptiveMutex, OneShotMutexGuard as AdaptiveMutexGuard,
+        RawOneShotMutex as RawAdaptiveMutex,
+    };
+}
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod cna;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod cohort;
+pub(crate) mod coupling;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod dynamic;
+#[cfg(all(feature = "rtm", target_arch = "x86_64"))]
+pub(crate) mod elided;
+pub(crate) mod ext;
+pub(crate) mod interrupt;
+pub(crate) mod irq_off;
+#[cfg(all(not(feature = "all-one-shot"), target_has_atomic = "ptr"))]
+pub(crate) mod mcs;
+#[cfg(all(feature = "all-one-shot", target_has_atomic = "ptr"))]
+pub(crate) mod mcs {
+    pub use one_shot_mutex::{
+        OneShotMutex as McsMutex, OneShotMutexGuard as McsMutexGuard,
+        RawOneShotMutex as RawMcsMutex, RawOneShotMutex as RawPerCpuMcsMutex,
+    };
+}
+#[cfg(not(all(feature = "all-one-shot", target_has_atomic = "ptr")))]
+pub(crate) mod naked;
+#[cfg(all(feature = "all-one-shot", target_has_atomic = "ptr"))]
+pub(crate) mod naked {
+    pub use one_shot_mutex::{
+        OneShotMutex as NakedSpinMutex, OneShotMutexGuard as NakedSpinMutexGuard,
+        RawOneShotMutex as RawNakedSpinMutex,
+    };
+}
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod owned;
+pub(crate) mod pi;
+pub(crate) mod preempt;
+pub(crate) mod priority;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod projected;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod queue;
+pub(crate) mod reentrant;
+#[cfg(not(any(
+    all(feature = "all-one-shot", target_has_atomic = "ptr"),
+    feature = "spinning_top",
+    feature = "uniprocessor"
+)))]
+pub(crate) mod spin;
+#[cfg(all(feature = "all-one-shot", target_has_atomic = "ptr"))]
+pub(crate) mod spin {
+    pub use one_shot_mutex::{
+        OneShotMutex as SpinMutex, OneShotMutexGuard as SpinMutexGuard,
+        RawOneShotMutex as RawSpinMutex,
+    };
+}
+#[cfg(all(
+    feature = "spinning_top",
+    not(any(
+        all(feature = "all-one-shot", target_has_atomic = "ptr"),
+        feature = "uniprocessor"
+    ))
+))]
 pub(crate) mod spin {
-    /// A simple spinlock with exponential backoff.
+    pub use spinning_top::guard::BackoffSpinlockGuard as SpinMutexGuard;
+    pub use spinning_top::BackoffSpinlock as SpinMutex;
+
+    /// A simple spinlock with exponential backoff from [`spinning_top`].
     pub type RawSpinMutex = spinning_top::RawSpinlock<spinning_top::relax::Backoff>;
 
+    impl crate::stats::RawMutexSample for RawSpinMutex {}
+}
+#[cfg(all(
+    feature = "uniprocessor",
+    not(all(feature = "all-one-shot", target_has_atomic = "ptr"))
+))]
+pub(crate) mod spin {
+    pub use super::uniprocessor::RawUniprocessorMutex as RawSpinMutex;
+
     /// A [`lock_api::Mutex`] based on [`RawSpinMutex`].
     pub type SpinMutex<T> = lock_api::Mutex<RawSpinMutex, T>;
 
     /// A [`lock_api::MutexGuard`] based on [`RawSpinMutex`].
     pub type SpinMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawSpinMutex, T>;
 }
-#[cfg(feature = "all-one-shot")]
-pub(crate) mod spin {
-    pub use one_shot_mutex::{
-        OneShotMutex as SpinMutex, OneShotMutexGuard as SpinMutexGuard,
-        RawOneShotMutex as RawSpinMutex,
-    };
-}
-#[cfg(not(feature = "all-one-shot"))]
+#[cfg(all(
+    not(any(feature = "all-one-shot", feature = "uniprocessor")),
+    target_has_atomic = "ptr"
+))]
 pub(crate) mod ticket;
-#[cfg(feature = "all-one-shot")]
+#[cfg(all(feature = "all-one-shot", target_has_atomic = "ptr"))]
 pub(crate) mod ticket {
     pub use one_shot_mutex::{
         OneShotMutex as TicketMutex, OneShotMutexGuard as TicketMutexGuard,
         RawOneShotMutex as RawTicketMutex,
     };
 }
+#[cfg(all(
+    feature = "uniprocessor",
+    not(feature = "all-one-shot"),
+    target_has_atomic = "ptr"
+))]
+pub(crate) mod ticket {
+    pub use super::uniprocessor::RawUniprocessorMutex as RawTicketMutex;
+
+    /// A [`lock_api::Mutex`] based on [`RawTicketMutex`].
+    pub type TicketMutex<T> = lock_api::Mutex<RawTicketMutex, T>;
 
-use interrupt_mutex::RawInterruptMutex;
+    /// A [`lock_api::MutexGuard`] based on [`RawTicketMutex`].
+    pub type TicketMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawTicketMutex, T>;
+}
+#[cfg(all(
+    feature = "uniprocessor",
+    not(all(feature = "all-one-shot", target_has_atomic = "ptr"))
+))]
+pub(crate) mod uniprocessor;
+
+#[cfg(target_has_atomic = "ptr")]
+use adaptive::RawAdaptiveMutex;
+#[cfg(target_has_atomic = "ptr")]
+use cna::{RawCnaMutex, SingleNode};
+#[cfg(target_has_atomic = "ptr")]
+use cohort::RawCohortMutex;
+use interrupt::RawInterruptMutex;
+#[cfg(target_has_atomic = "ptr")]
+use mcs::RawPerCpuMcsMutex;
+use naked::RawNakedSpinMutex;
+#[cfg(target_has_atomic = "ptr")]
 use one_shot_mutex::RawOneShotMutex;
 use spin::RawSpinMutex;
+#[cfg(target_has_atomic = "ptr")]
 use ticket::RawTicketMutex;
 
 /// An interrupt-safe [`RawOneShotMutex`].
+#[cfg(target_has_atomic = "ptr")]
 pub type RawInterruptOneShotMutex = RawInterruptMutex<RawOneShotMutex>;
 
 /// A [`lock_api::Mutex`] based on [`RawInterruptOneShotMutex`].
+#[cfg(target_has_atomic = "ptr")]
 pub type InterruptOneShotMutex<T> = lock_api::Mutex<RawInterruptOneShotMutex, T>;
 
 /// A [`lock_api::MutexGuard`] based on [`RawInterruptOneShotMutex`].
+#[cfg(target_has_atomic = "ptr")]
 pub type InterruptOneShotMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawInterruptOneShotMutex, T>;
 
 /// An interrupt-safe [`RawSpinMutex`].
@@ -49,11 +158,184 @@ pub type InterruptSpinMutex<T> = lock_api::Mutex<RawInterruptSpinMutex, T>;
 /// A [`lock_api::MutexGuard`] based on [`RawInterruptSpinMutex`].
 pub type InterruptSpinMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawInterruptSpinMutex, T>;
 
+/// An interrupt-safe [`RawNakedSpinMutex`].
+pub type RawInterruptNakedSpinMutex = RawInterruptMutex<RawNakedSpinMutex>;
+
+/// A [`lock_api::Mutex`] based on [`RawInterruptNakedSpinMutex`].
+pub type InterruptNakedSpinMutex<T> = lock_api::Mutex<RawInterruptNakedSpinMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawInterruptNakedSpinMutex`].
+pub type InterruptNakedSpinMutexGuard<'a, T> =
+    lock_api::MutexGuard<'a, RawInterruptNakedSpinMutex, T>;
+
 /// An interrupt-safe [`RawTicketMutex`].
+#[cfg(target_has_atomic = "ptr")]
 pub type RawInterruptTicketMutex = RawInterruptMutex<RawTicketMutex>;
 
 /// A [`lock_api::Mutex`] based on [`RawInterruptTicketMutex`].
+#[cfg(target_has_atomic = "ptr")]
 pub type InterruptTicketMutex<T> = lock_api::Mutex<RawInterruptTicketMutex, T>;
 
 /// A [`lock_api::MutexGuard`] based on [`RawInterruptTicketMutex`].
+#[cfg(target_has_atomic = "ptr")]
 pub type InterruptTicketMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawInterruptTicketMutex, T>;
+
+/// An interrupt-safe [MCS lock] with wait nodes in per-CPU statics.
+///
+/// Like with [`RawMcsMutex`](crate::RawMcsMutex), waiters queue up and each spins on its own node.
+/// The wait nodes live in per-CPU statics with one node per nesting level (see [`MAX_MCS_NESTING`](crate::MAX_MCS_NESTING)) instead of on the caller's stack.
+/// Since a node is only needed while waiting, this is a plain [`RawMutex`](lock_api::RawMutex) with plain [`lock_api`] guards.
+///
+/// The nodes are selected via [`core_id`](crate::core_id).
+/// Since the caller must not migrate to another CPU core while locking, this mutex always disables interrupts.
+///
+/// [MCS lock]: https://lwn.net/Articles/590243/
+///
+/// # Panics
+///
+/// Waiting for the lock panics if [`init_smp`](crate::init_smp) has not been called, since two CPU cores sharing a wait node would corrupt the queue.
+/// It also panics if [`core_id`](crate::core_id) is not less than the CPU count or if queue locks nest deeper than [`MAX_MCS_NESTING`](crate::MAX_MCS_NESTING) on one CPU core.
+///
+/// # Examples
+///
+/// With the `all-one-shot` feature, this mutex does not support contention from other threads.
+///
+#[cfg_attr(not(feature = "all-one-shot"), doc = "```")]
+#[cfg_attr(feature = "all-one-shot", doc = "```ignore")]
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::thread;
+///
+/// use hermit_sync::{init_smp, InterruptMcsMutex, SmpConfig};
+///
+/// static NEXT_CORE_ID: AtomicUsize = AtomicUsize::new(0);
+///
+/// std::thread_local! {
+///     static CORE_ID: usize = NEXT_CORE_ID.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// // The main thread and four workers.
+/// init_smp(SmpConfig::new(5, || CORE_ID.with(|id| *id))).unwrap();
+///
+/// static COUNTER: InterruptMcsMutex<usize> = InterruptMcsMutex::new(0);
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             for _ in 0..1000 {
+///                 *COUNTER.lock() += 1;
+///             }
+///         });
+///     }
+/// });
+///
+/// assert_eq!(*COUNTER.lock(), 4000);
+/// ```
+#[cfg(target_has_atomic = "ptr")]
+pub type RawInterruptMcsMutex = RawInterruptMutex<RawPerCpuMcsMutex>;
+
+/// A [`lock_api::Mutex`] based on [`RawInterruptMcsMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptMcsMutex<T> = lock_api::Mutex<RawInterruptMcsMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawInterruptMcsMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptMcsMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawInterruptMcsMutex, T>;
+
+/// An interrupt-safe [compact NUMA-aware lock].
+///
+/// This is an MCS lock like [`RawInterruptMcsMutex`] that prefers handing the lock over to waiters on the same NUMA node.
+/// Waiters of other NUMA nodes are moved to a secondary queue.
+/// After [`CNA_LOCAL_HANDOFFS`](crate::CNA_LOCAL_HANDOFFS) consecutive local handoffs, or once no local waiters are left, the secondary queue is served first.
+///
+/// The NUMA node of the current CPU core is provided by `N`.
+/// Wait nodes live in per-CPU statics, selected via [`core_id`](crate::core_id), like with [`RawInterruptMcsMutex`].
+/// Since the caller must not migrate to another CPU core while locking, this mutex always disables interrupts.
+///
+/// [compact NUMA-aware lock]: https://arxiv.org/abs/1810.05600
+///
+/// # Panics
+///
+/// Waiting for the lock panics if [`init_smp`](crate::init_smp) has not been called, since two CPU cores sharing a wait node would corrupt the queue.
+/// It also panics if [`core_id`](crate::core_id) is not less than the CPU count or if queue locks nest deeper than [`MAX_MCS_NESTING`](crate::MAX_MCS_NESTING) on one CPU core.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::thread;
+///
+/// use hermit_sync::{init_smp, InterruptCnaMutex, NumaNodeId, SmpConfig};
+///
+/// static NEXT_CORE_ID: AtomicUsize = AtomicUsize::new(0);
+///
+/// std::thread_local! {
+///     static CORE_ID: usize = NEXT_CORE_ID.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// fn core_id() -> usize {
+///     CORE_ID.with(|id| *id)
+/// }
+///
+/// struct TwoSockets;
+///
+/// impl NumaNodeId for TwoSockets {
+///     fn numa_node_id() -> usize {
+///         core_id() % 2
+///     }
+/// }
+///
+/// // The main thread and four workers.
+/// init_smp(SmpConfig::new(5, core_id)).unwrap();
+///
+/// static COUNTER: InterruptCnaMutex<usize, TwoSockets> = InterruptCnaMutex::new(0);
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             for _ in 0..1000 {
+///                 *COUNTER.lock() += 1;
+///             }
+///         });
+///     }
+/// });
+///
+/// assert_eq!(*COUNTER.lock(), 4000);
+/// ```
+#[cfg(target_has_atomic = "ptr")]
+pub type RawInterruptCnaMutex<N = SingleNode> = RawInterruptMutex<RawCnaMutex<N>>;
+
+/// A [`lock_api::Mutex`] based on [`RawInterruptCnaMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptCnaMutex<T, N = SingleNode> = lock_api::Mutex<RawInterruptCnaMutex<N>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawInterruptCnaMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptCnaMutexGuard<'a, T, N = SingleNode> =
+    lock_api::MutexGuard<'a, RawInterruptCnaMutex<N>, T>;
+
+/// An interrupt-safe [`RawCohortMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type RawInterruptCohortMutex<N = SingleNode> =
+    RawInterruptMutex<RawCohortMutex<RawTicketMutex, RawSpinMutex, N>>;
+
+/// A [`lock_api::Mutex`] based on [`RawInterruptCohortMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptCohortMutex<T, N = SingleNode> = lock_api::Mutex<RawInterruptCohortMutex<N>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawInterruptCohortMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptCohortMutexGuard<'a, T, N = SingleNode> =
+    lock_api::MutexGuard<'a, RawInterruptCohortMutex<N>, T>;
+
+/// An interrupt-safe [`RawAdaptiveMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type RawInterruptAdaptiveMutex = RawInterruptMutex<RawAdaptiveMutex>;
+
+/// A [`lock_api::Mutex`] based on [`RawInterruptAdaptiveMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptAdaptiveMutex<T> = lock_api::Mutex<RawInterruptAdaptiveMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawInterruptAdaptiveMutex`].
+#[cfg(target_has_atomic = "ptr")]
+pub type InterruptAdaptiveMutexGuard<'a, T> =
+    lock_api::MutexGuard<'a, RawInterruptAdaptiveMutex, T>;
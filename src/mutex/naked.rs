@@ -0,0 +1,125 @@
+use core::hint;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use lock_api::{GuardSend, RawMutex};
+
+use crate::atomic::cas;
+use crate::stats::RawMutexSample;
+
+/// A bare [test and test-and-set] [spinlock] without backoff.
+///
+/// In contrast to [`RawSpinMutex`](crate::RawSpinMutex), waiters only issue [`hint::spin_loop`] and do not back off or halt under hypervisors.
+/// This is intended for small, single-purpose kernels that care more about code size than about contention.
+///
+/// [test and test-and-set]: https://en.wikipedia.org/wiki/Test_and_test-and-set
+/// [spinlock]: https://en.wikipedia.org/wiki/Spinlock
+///
+/// # Examples
+///
+/// ```
+/// use core::fmt::Write;
+///
+/// use hermit_sync::NakedSpinMutex;
+///
+/// struct Console {
+///     written: usize,
+/// }
+///
+/// impl Write for Console {
+///     fn write_str(&mut self, s: &str) -> core::fmt::Result {
+///         // Write to the serial port here.
+///         self.written += s.len();
+///         Ok(())
+///     }
+/// }
+///
+/// static CONSOLE: NakedSpinMutex<Console> = NakedSpinMutex::new(Console { written: 0 });
+///
+/// writeln!(CONSOLE.lock(), "Hello, world!").unwrap();
+/// assert_eq!(CONSOLE.lock().written, 14);
+/// ```
+pub struct RawNakedSpinMutex {
+    locked: AtomicBool,
+}
+
+unsafe impl RawMutex for RawNakedSpinMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        locked: AtomicBool::new(false),
+    };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock(&self) {
+        while cas::compare_exchange_weak(
+            &self.locked,
+            false,
+            true,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_err()
+        {
+            while self.is_locked() {
+                hint::spin_loop();
+            }
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        cas::compare_exchange(
+            &self.locked,
+            false,
+            true,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+}
+
+impl RawMutexSample for RawNakedSpinMutex {}
+
+/// A [`lock_api::Mutex`] based on [`RawNakedSpinMutex`].
+pub type NakedSpinMutex<T> = lock_api::Mutex<RawNakedSpinMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawNakedSpinMutex`].
+pub type NakedSpinMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawNakedSpinMutex, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn lots_and_lots() {
+        const J: u32 = 1000;
+        const K: u32 = 3;
+
+        let mutex = NakedSpinMutex::new(0);
+        thread::scope(|s| {
+            for _ in 0..K {
+                s.spawn(|| {
+                    for _ in 0..J {
+                        *mutex.lock() += 1;
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*mutex.lock(), J * K);
+    }
+}
@@ -0,0 +1,228 @@
+use core::marker::PhantomData;
+use core::panic::Location;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use lock_api::RawMutex;
+
+#[cfg(feature = "held-locks")]
+use crate::panic;
+use crate::stats::RawMutexSample;
+use crate::{CoreIdProvider, RegisteredCoreId};
+
+const NO_OWNER: usize = usize::MAX;
+#[cfg(feature = "held-locks")]
+const NO_SLOT: usize = usize::MAX;
+
+/// A mutex that tracks which CPU core holds it.
+///
+/// This mutex wraps another [`RawMutex`] and records the ID of the CPU core that locked it, as returned by [`CoreIdProvider`] `C`.
+/// This allows verifying locking preconditions via [`is_locked_by_current_cpu`] and [`assert_lock_held!`].
+/// With the `held-locks` feature, this mutex is recorded for `dump_held_locks` while held (see `hermit_sync::panic`).
+///
+/// [`is_locked_by_current_cpu`]: RawMutexOwned::is_locked_by_current_cpu
+/// [`assert_lock_held!`]: crate::assert_lock_held
+pub struct RawOwnedMutex<I, C = RegisteredCoreId> {
+    inner: I,
+    owner: AtomicUsize,
+    /// The slot of this mutex in the held-lock registry, if recorded.
+    #[cfg(feature = "held-locks")]
+    held_slot: AtomicUsize,
+    _core_id: PhantomData<fn() -> C>,
+}
+
+/// A raw mutex that knows which CPU core holds it.
+///
+/// # Safety
+///
+/// [`owner`](Self::owner) must return the ID of the CPU core that holds the mutex, if any.
+/// [`is_locked_by_current_cpu`](Self::is_locked_by_current_cpu) must compare it against the ID of the current CPU core from the same source.
+pub unsafe trait RawMutexOwned: RawMutex {
+    /// Returns the ID of the CPU core holding this mutex, if locked.
+    fn owner(&self) -> Option<usize>;
+
+    /// Returns `true` if this mutex is held by the current CPU core.
+    ///
+    /// By default, this compares [`owner`](Self::owner) against [`RegisteredCoreId`].
+    /// If the current CPU core cannot be determined, this returns `false`.
+    #[inline]
+    fn is_locked_by_current_cpu(&self) -> bool {
+        RegisteredCoreId::try_core_id().is_some_and(|cpu| self.owner() == Some(cpu))
+    }
+}
+
+impl<I, C: CoreIdProvider> RawOwnedMutex<I, C> {
+    #[cfg_attr(not(feature = "held-locks"), allow(unused_variables))]
+    #[inline]
+    fn acquired(&self, location: &'static Location<'static>) {
+        let core_id = C::core_id();
+        self.owner.store(core_id, Ordering::Relaxed);
+        #[cfg(feature = "held-locks")]
+        {
+            let slot = panic::record_acquire(self as *const Self as usize, core_id, location);
+            self.held_slot
+                .store(slot.unwrap_or(NO_SLOT), Ordering::Relaxed);
+        }
+    }
+}
+
+unsafe impl<I: RawMutex, C: CoreIdProvider> RawMutex for RawOwnedMutex<I, C> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        inner: I::INIT,
+        owner: AtomicUsize::new(NO_OWNER),
+        #[cfg(feature = "held-locks")]
+        held_slot: AtomicUsize::new(NO_SLOT),
+        _core_id: PhantomData,
+    };
+
+    type GuardMarker = I::GuardMarker;
+
+    #[inline]
+    #[track_caller]
+    fn lock(&self) {
+        self.inner.lock();
+        self.acquired(Location::caller());
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_lock(&self) -> bool {
+        let ok = self.inner.try_lock();
+        if ok {
+            self.acquired(Location::caller());
+        }
+        ok
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        #[cfg(feature = "held-locks")]
+        {
+            let slot = self.held_slot.load(Ordering::Relaxed);
+            panic::record_release((slot != NO_SLOT).then_some(slot));
+        }
+        self.owner.store(NO_OWNER, Ordering::Relaxed);
+        unsafe {
+            self.inner.unlock();
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+impl<I: RawMutexSample, C: CoreIdProvider> RawMutexSample for RawOwnedMutex<I, C> {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        self.inner.queue_depth()
+    }
+}
+
+unsafe impl<I: RawMutex, C: CoreIdProvider> RawMutexOwned for RawOwnedMutex<I, C> {
+    #[inline]
+    fn owner(&self) -> Option<usize> {
+        let owner = self.owner.load(Ordering::Relaxed);
+        (owner != NO_OWNER).then_some(owner)
+    }
+
+    #[inline]
+    fn is_locked_by_current_cpu(&self) -> bool {
+        C::try_core_id().is_some_and(|cpu| self.owner() == Some(cpu))
+    }
+}
+
+/// Extension methods for [`lock_api::Mutex`]es based on [`RawMutexOwned`].
+pub trait MutexOwnedExt {
+    /// Returns `true` if this mutex is held by the current CPU core.
+    fn is_locked_by_current_cpu(&self) -> bool;
+}
+
+impl<R: RawMutexOwned, T: ?Sized> MutexOwnedExt for lock_api::Mutex<R, T> {
+    #[inline]
+    fn is_locked_by_current_cpu(&self) -> bool {
+        // SAFETY: We only query the raw mutex.
+        unsafe { self.raw() }.is_locked_by_current_cpu()
+    }
+}
+
+/// Asserts that a mutex is held by the current CPU core.
+///
+/// This is similar to Linux's `lockdep_assert_held` and requires the mutex to implement [`MutexOwnedExt`].
+/// If the current CPU core cannot be determined, for example, because no provider has been registered via [`set_core_id_provider`](crate::set_core_id_provider), the assertion fails.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{assert_lock_held, OwnedMutex, RawSpinMutex};
+///
+/// static COUNTER: OwnedMutex<RawSpinMutex, usize> = OwnedMutex::new(0);
+///
+/// fn core_id() -> usize {
+///     // Read the core ID from a CPU-local register here.
+///     0
+/// }
+///
+/// hermit_sync::set_core_id_provider(core_id);
+///
+/// fn increment(counter: &mut usize) {
+///     assert_lock_held!(COUNTER);
+///     *counter += 1;
+/// }
+///
+/// increment(&mut COUNTER.lock());
+/// ```
+#[macro_export]
+macro_rules! assert_lock_held {
+    ($mutex:expr $(,)?) => {
+        assert!(
+            $crate::MutexOwnedExt::is_locked_by_current_cpu(&$mutex),
+            "lock `{}` is not held by the current CPU core",
+            stringify!($mutex),
+        )
+    };
+}
+
+/// A [`lock_api::Mutex`] based on [`RawOwnedMutex`].
+pub type OwnedMutex<I, T, C = RegisteredCoreId> = lock_api::Mutex<RawOwnedMutex<I, C>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawOwnedMutex`].
+pub type OwnedMutexGuard<'a, I, T, C = RegisteredCoreId> =
+    lock_api::MutexGuard<'a, RawOwnedMutex<I, C>, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::percpu::per_cpu::ThreadCpu;
+    use crate::RawSpinMutex;
+
+    #[test]
+    fn is_locked_by_current_cpu() {
+        let mutex = OwnedMutex::<RawSpinMutex, _, ThreadCpu>::new(());
+        assert!(!mutex.is_locked_by_current_cpu());
+
+        let guard = mutex.lock();
+        assert!(mutex.is_locked_by_current_cpu());
+        assert_lock_held!(mutex);
+        thread::scope(|s| {
+            s.spawn(|| {
+                ThreadCpu::set(1);
+                assert!(!mutex.is_locked_by_current_cpu());
+            });
+        });
+
+        drop(guard);
+        assert!(!mutex.is_locked_by_current_cpu());
+    }
+
+    #[test]
+    fn unknown_core_id() {
+        // No core ID provider is registered in unit tests.
+        let mutex = OwnedMutex::<RawSpinMutex, _>::new(());
+        let _guard = mutex.lock();
+        assert!(!mutex.is_locked_by_current_cpu());
+    }
+}
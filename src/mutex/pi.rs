@@ -0,0 +1,280 @@
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use core::{mem, ptr};
+
+use lock_api::RawMutex;
+
+use crate::relax::{Backoff, Relax};
+use crate::stats::RawMutexSample;
+use crate::SpinMutex;
+
+const NO_OWNER: usize = usize::MAX;
+
+static PI_HOOKS: AtomicPtr<PiHooks> = AtomicPtr::new(ptr::null_mut());
+
+/// Scheduler hooks for [priority inheritance] in [`RawPiMutex`].
+///
+/// Tasks are identified by a `usize` chosen by the scheduler.
+/// Priorities are `usize`s where larger values denote higher priorities.
+///
+/// [priority inheritance]: https://en.wikipedia.org/wiki/Priority_inheritance
+#[derive(Clone, Copy, Debug)]
+pub struct PiHooks {
+    /// Returns the current task.
+    pub current_task: fn() -> usize,
+
+    /// Returns the priority of the current task.
+    pub current_priority: fn() -> usize,
+
+    /// Raises the priority of `owner` to at least `priority`.
+    ///
+    /// This is only called while `owner` holds the mutex.
+    pub boost: fn(owner: usize, priority: usize),
+
+    /// Restores the priority of `owner` after it has released a mutex it was boosted for.
+    pub unboost: fn(owner: usize),
+}
+
+/// Registers priority inheritance hooks.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::PiHooks;
+///
+/// fn current_task() -> usize {
+///     // Return the ID of the current task here.
+///     0
+/// }
+///
+/// fn current_priority() -> usize {
+///     // Return the priority of the current task here.
+///     0
+/// }
+///
+/// fn boost(_owner: usize, _priority: usize) {
+///     // Raise the priority of the owner here.
+/// }
+///
+/// fn unboost(_owner: usize) {
+///     // Restore the priority of the owner here.
+/// }
+///
+/// static PI_HOOKS: PiHooks = PiHooks {
+///     current_task,
+///     current_priority,
+///     boost,
+///     unboost,
+/// };
+///
+/// hermit_sync::set_pi_hooks(&PI_HOOKS);
+/// ```
+pub fn set_pi_hooks(hooks: &'static PiHooks) {
+    PI_HOOKS.store(ptr::from_ref(hooks).cast_mut(), Ordering::Release);
+}
+
+#[inline]
+fn hooks() -> Option<&'static PiHooks> {
+    let hooks = PI_HOOKS.load(Ordering::Acquire);
+    // SAFETY: Only `&'static PiHooks` are stored in `PI_HOOKS`.
+    unsafe { hooks.as_ref() }
+}
+
+/// A mutex with [priority inheritance].
+///
+/// This mutex wraps another [`RawMutex`] and records the task holding it via [`PiHooks`].
+/// While a waiter with a higher priority than the holder spins, the holder is boosted via [`PiHooks::boost`].
+/// Once a boosted holder unlocks the mutex, it is unboosted via [`PiHooks::unboost`].
+///
+/// While contended, waiters repeatedly call [`try_lock`](RawMutex::try_lock) on the wrapped mutex, so fairness of the wrapped mutex is lost.
+/// Until hooks are registered via [`set_pi_hooks`], this behaves like the wrapped mutex.
+///
+/// [priority inheritance]: https://en.wikipedia.org/wiki/Priority_inheritance
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::thread;
+///
+/// use hermit_sync::{PiHooks, PiMutex, RawSpinMutex};
+///
+/// std::thread_local! {
+///     static TASK: Cell<usize> = const { Cell::new(0) };
+/// }
+///
+/// static BOOSTED: AtomicUsize = AtomicUsize::new(usize::MAX);
+/// static UNBOOSTED: AtomicUsize = AtomicUsize::new(usize::MAX);
+///
+/// fn current_task() -> usize {
+///     TASK.get()
+/// }
+///
+/// fn current_priority() -> usize {
+///     // Task IDs double as priorities.
+///     TASK.get()
+/// }
+///
+/// fn boost(owner: usize, priority: usize) {
+///     assert_eq!(priority, 2);
+///     BOOSTED.store(owner, Ordering::Relaxed);
+/// }
+///
+/// fn unboost(owner: usize) {
+///     UNBOOSTED.store(owner, Ordering::Relaxed);
+/// }
+///
+/// static PI_HOOKS: PiHooks = PiHooks {
+///     current_task,
+///     current_priority,
+///     boost,
+///     unboost,
+/// };
+///
+/// hermit_sync::set_pi_hooks(&PI_HOOKS);
+///
+/// let mutex = PiMutex::<RawSpinMutex, _>::new(0);
+/// TASK.set(1);
+/// let guard = mutex.lock();
+/// thread::scope(|s| {
+///     s.spawn(|| {
+///         TASK.set(2);
+///         *mutex.lock() += 1;
+///     });
+///     while BOOSTED.load(Ordering::Relaxed) != 1 {
+///         core::hint::spin_loop();
+///     }
+///     drop(guard);
+/// });
+///
+/// assert_eq!(UNBOOSTED.load(Ordering::Relaxed), 1);
+/// assert_eq!(*mutex.lock(), 1);
+/// ```
+pub struct RawPiMutex<I> {
+    inner: I,
+    owner: AtomicUsize,
+    owner_priority: AtomicUsize,
+    /// The boosted owner, which is cleared before the owner changes.
+    boost: SpinMutex<Boost>,
+}
+
+/// A task that has been boosted for holding a [`RawPiMutex`].
+struct Boost {
+    owner: usize,
+    priority: usize,
+}
+
+impl Boost {
+    const NONE: Self = Self {
+        owner: NO_OWNER,
+        priority: 0,
+    };
+}
+
+impl<I> RawPiMutex<I> {
+    #[inline]
+    fn acquired(&self, hooks: &PiHooks, priority: usize) {
+        self.owner_priority.store(priority, Ordering::Relaxed);
+        self.owner.store((hooks.current_task)(), Ordering::Release);
+    }
+
+    #[inline]
+    fn is_boost_needed(&self, priority: usize) -> Option<usize> {
+        let owner = self.owner.load(Ordering::Acquire);
+        (owner != NO_OWNER && priority > self.owner_priority.load(Ordering::Relaxed))
+            .then_some(owner)
+    }
+
+    /// Boosts the current owner to `priority` if necessary.
+    #[inline]
+    fn boost_owner(&self, hooks: &PiHooks, priority: usize) {
+        if self.is_boost_needed(priority).is_none() {
+            return;
+        }
+
+        // The owner cannot unlock while we hold `boost`, so we only boost the current owner.
+        let mut boost = self.boost.lock();
+        let Some(owner) = self.is_boost_needed(priority) else {
+            return;
+        };
+        if priority > boost.priority {
+            (hooks.boost)(owner, priority);
+            *boost = Boost { owner, priority };
+        }
+    }
+}
+
+unsafe impl<I: RawMutex> RawMutex for RawPiMutex<I> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        inner: I::INIT,
+        owner: AtomicUsize::new(NO_OWNER),
+        owner_priority: AtomicUsize::new(0),
+        boost: SpinMutex::new(Boost::NONE),
+    };
+
+    type GuardMarker = I::GuardMarker;
+
+    #[inline]
+    fn lock(&self) {
+        let Some(hooks) = hooks() else {
+            self.inner.lock();
+            return;
+        };
+
+        let priority = (hooks.current_priority)();
+        let mut backoff = Backoff::default();
+        while !self.inner.try_lock() {
+            self.boost_owner(hooks, priority);
+            backoff.relax();
+        }
+        self.acquired(hooks, priority);
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        let ok = self.inner.try_lock();
+        if ok {
+            if let Some(hooks) = hooks() {
+                self.acquired(hooks, (hooks.current_priority)());
+            }
+        }
+        ok
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let boosted = {
+            let mut boost = self.boost.lock();
+            self.owner.store(NO_OWNER, Ordering::Relaxed);
+            mem::replace(&mut *boost, Boost::NONE)
+        };
+        unsafe {
+            self.inner.unlock();
+        }
+
+        if boosted.owner != NO_OWNER {
+            if let Some(hooks) = hooks() {
+                (hooks.unboost)(boosted.owner);
+            }
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+impl<I: RawMutexSample> RawMutexSample for RawPiMutex<I> {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        self.inner.queue_depth()
+    }
+}
+
+/// A [`lock_api::Mutex`] based on [`RawPiMutex`].
+pub type PiMutex<I, T> = lock_api::Mutex<RawPiMutex<I>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawPiMutex`].
+pub type PiMutexGuard<'a, I, T> = lock_api::MutexGuard<'a, RawPiMutex<I>, T>;
@@ -0,0 +1,158 @@
+use crate::init::preempt_hooks;
+use crate::{InterruptControl, RawInterruptMutex};
+
+/// Scheduler hooks for disabling preemption in [`RawPreemptMutex`].
+///
+/// This corresponds to Linux's `preempt_disable` and `preempt_enable`.
+/// Calls nest, so the scheduler should count them per CPU core and only preempt again once the count drops to zero.
+/// The hooks are registered via [`SmpConfig::preempt_hooks`](crate::SmpConfig::preempt_hooks).
+#[derive(Clone, Copy, Debug)]
+pub struct PreemptHooks {
+    /// Disables preemption of the current task.
+    pub disable: fn(),
+
+    /// Enables preemption of the current task again after [`disable`](Self::disable).
+    ///
+    /// If the task should have been preempted meanwhile, this is a good place to reschedule.
+    pub enable: fn(),
+}
+
+/// The [`InterruptControl`] for disabling preemption via [`PreemptHooks`].
+///
+/// The saved state records whether the hooks were called, so that restoring only enables preemption if saving disabled it.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PreemptControl;
+
+impl InterruptControl for PreemptControl {
+    type Flags = bool;
+
+    #[inline]
+    fn save_disable() -> Self::Flags {
+        let Some(hooks) = preempt_hooks() else {
+            return false;
+        };
+        (hooks.disable)();
+        true
+    }
+
+    #[inline]
+    fn restore(flags: Self::Flags) {
+        if !flags {
+            return;
+        }
+        if let Some(hooks) = preempt_hooks() {
+            (hooks.enable)();
+        }
+    }
+}
+
+/// A mutex that disables preemption while locked.
+///
+/// This is a [`RawInterruptMutex`] that calls the hooks registered via [`SmpConfig::preempt_hooks`](crate::SmpConfig::preempt_hooks) through [`PreemptControl`] instead of disabling interrupts.
+/// Thus, interrupts stay enabled, so the protected data must not be accessed from interrupt handlers.
+///
+/// Until hooks are registered, this mutex behaves like its inner mutex.
+/// Whether preemption was disabled is saved when locking, so unlocking only enables preemption if locking disabled it.
+/// This keeps the preemption count balanced for guards that were taken before [`init_smp`](crate::init_smp).
+///
+/// Locking corresponds to Linux's `spin_lock` on preemptible kernels.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use hermit_sync::{init_smp, PreemptHooks, PreemptMutex, RawSpinMutex, SmpConfig};
+///
+/// static PREEMPT_COUNT: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn disable() {
+///     PREEMPT_COUNT.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// fn enable() {
+///     // Reschedule here if necessary.
+///     PREEMPT_COUNT.fetch_sub(1, Ordering::Relaxed);
+/// }
+///
+/// static PREEMPT_HOOKS: PreemptHooks = PreemptHooks { disable, enable };
+///
+/// fn core_id() -> usize {
+///     0
+/// }
+///
+/// static RUN_QUEUE_LEN: PreemptMutex<RawSpinMutex, usize> = PreemptMutex::new(0);
+///
+/// let early = RUN_QUEUE_LEN.lock();
+/// init_smp(SmpConfig::new(1, core_id).preempt_hooks(&PREEMPT_HOOKS)).unwrap();
+/// // `early` was locked before the hooks were registered and does not enable preemption.
+/// drop(early);
+/// assert_eq!(PREEMPT_COUNT.load(Ordering::Relaxed), 0);
+///
+/// let mut guard = RUN_QUEUE_LEN.lock();
+/// *guard += 1;
+/// assert_eq!(PREEMPT_COUNT.load(Ordering::Relaxed), 1);
+/// assert!(RUN_QUEUE_LEN.try_lock().is_none());
+/// assert_eq!(PREEMPT_COUNT.load(Ordering::Relaxed), 1);
+/// drop(guard);
+/// assert_eq!(PREEMPT_COUNT.load(Ordering::Relaxed), 0);
+/// ```
+pub type RawPreemptMutex<I> = RawInterruptMutex<I, PreemptControl>;
+
+/// A [`lock_api::Mutex`] based on [`RawPreemptMutex`].
+pub type PreemptMutex<I, T> = lock_api::Mutex<RawPreemptMutex<I>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawPreemptMutex`].
+pub type PreemptMutexGuard<'a, I, T> = lock_api::MutexGuard<'a, RawPreemptMutex<I>, T>;
+
+/// A mutex that disables preemption and interrupts while locked.
+///
+/// Locking first disables preemption, then disables interrupts, and then locks `I`.
+/// Unlocking releases in reverse order, so the scheduler is never entered with interrupts disabled by this mutex.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicBool, Ordering};
+///
+/// use hermit_sync::{
+///     init_smp, irqs_disabled, PreemptHooks, PreemptInterruptMutex, RawSpinMutex, SmpConfig,
+/// };
+///
+/// /// Whether interrupts were disabled when a hook was last called.
+/// static HOOK_IRQS_DISABLED: AtomicBool = AtomicBool::new(false);
+///
+/// fn record_irq_state() {
+///     HOOK_IRQS_DISABLED.store(irqs_disabled(), Ordering::Relaxed);
+/// }
+///
+/// static PREEMPT_HOOKS: PreemptHooks = PreemptHooks {
+///     disable: record_irq_state,
+///     enable: record_irq_state,
+/// };
+///
+/// fn core_id() -> usize {
+///     0
+/// }
+///
+/// init_smp(SmpConfig::new(1, core_id).preempt_hooks(&PREEMPT_HOOKS)).unwrap();
+///
+/// static TIMER_QUEUE_LEN: PreemptInterruptMutex<RawSpinMutex, usize> =
+///     PreemptInterruptMutex::new(0);
+///
+/// let outside = irqs_disabled();
+/// let mut guard = TIMER_QUEUE_LEN.lock();
+/// *guard += 1;
+/// // The hooks are called outside of the interrupt-disabled section.
+/// assert_eq!(HOOK_IRQS_DISABLED.load(Ordering::Relaxed), outside);
+/// drop(guard);
+/// assert_eq!(HOOK_IRQS_DISABLED.load(Ordering::Relaxed), outside);
+/// ```
+pub type RawPreemptInterruptMutex<I> = RawPreemptMutex<RawInterruptMutex<I>>;
+
+/// A [`lock_api::Mutex`] based on [`RawPreemptInterruptMutex`].
+pub type PreemptInterruptMutex<I, T> = lock_api::Mutex<RawPreemptInterruptMutex<I>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawPreemptInterruptMutex`].
+pub type PreemptInterruptMutexGuard<'a, I, T> =
+    lock_api::MutexGuard<'a, RawPreemptInterruptMutex<I>, T>;
@@ -0,0 +1,88 @@
+use core::marker::PhantomData;
+
+use crate::{InterruptControl, RawInterruptMutex};
+
+/// A way of masking interrupts by priority.
+///
+/// Instead of disabling all interrupts, interrupt controllers can mask interrupts below a priority threshold, such as via the ARM GIC's PMR, x86's CR8 (TPR), or the RISC-V PLIC's threshold register.
+/// The meaning of thresholds is defined by the implementation.
+///
+/// This is used by [`PriorityMasked`] and [`RawInterruptPriorityMutex`].
+pub trait InterruptPriority {
+    /// Masks all interrupts that are not more important than `threshold` and returns the previous threshold.
+    ///
+    /// If the current threshold already masks more interrupts, this must not unmask any.
+    fn raise_threshold(threshold: usize) -> usize;
+
+    /// Restores the threshold returned by [`raise_threshold`](Self::raise_threshold).
+    fn restore_threshold(previous: usize);
+}
+
+/// An [`InterruptControl`] that raises the interrupt priority threshold to `THRESHOLD` instead of disabling all interrupts.
+///
+/// More important interrupts, such as timers, are still delivered.
+/// Thus, the protected data must not be accessed from interrupt handlers above `THRESHOLD`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PriorityMasked<P, const THRESHOLD: usize> {
+    _priority: PhantomData<P>,
+}
+
+impl<P: InterruptPriority, const THRESHOLD: usize> InterruptControl
+    for PriorityMasked<P, THRESHOLD>
+{
+    type Flags = usize;
+
+    #[inline]
+    fn save_disable() -> Self::Flags {
+        P::raise_threshold(THRESHOLD)
+    }
+
+    #[inline]
+    fn restore(flags: Self::Flags) {
+        P::restore_threshold(flags);
+    }
+}
+
+/// A [`RawInterruptMutex`] that masks interrupts up to `THRESHOLD` via [`InterruptPriority`] `P` while locked.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use hermit_sync::{InterruptPriority, InterruptPriorityMutex, RawSpinMutex};
+///
+/// /// The priority threshold register of our interrupt controller.
+/// static THRESHOLD: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct Plic;
+///
+/// impl InterruptPriority for Plic {
+///     fn raise_threshold(threshold: usize) -> usize {
+///         THRESHOLD.fetch_max(threshold, Ordering::Acquire)
+///     }
+///
+///     fn restore_threshold(previous: usize) {
+///         THRESHOLD.store(previous, Ordering::Release);
+///     }
+/// }
+///
+/// /// Shared with device interrupts of priority 3 and below, but not with the timer.
+/// static DEVICES: InterruptPriorityMutex<RawSpinMutex, Plic, 3, usize> =
+///     InterruptPriorityMutex::new(0);
+///
+/// let guard = DEVICES.lock();
+/// assert_eq!(THRESHOLD.load(Ordering::Relaxed), 3);
+/// drop(guard);
+/// assert_eq!(THRESHOLD.load(Ordering::Relaxed), 0);
+/// ```
+pub type RawInterruptPriorityMutex<I, P, const THRESHOLD: usize> =
+    RawInterruptMutex<I, PriorityMasked<P, THRESHOLD>>;
+
+/// A [`lock_api::Mutex`] based on [`RawInterruptPriorityMutex`].
+pub type InterruptPriorityMutex<I, P, const THRESHOLD: usize, T> =
+    lock_api::Mutex<RawInterruptPriorityMutex<I, P, THRESHOLD>, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawInterruptPriorityMutex`].
+pub type InterruptPriorityMutexGuard<'a, I, P, const THRESHOLD: usize, T> =
+    lock_api::MutexGuard<'a, RawInterruptPriorityMutex<I, P, THRESHOLD>, T>;
@@ -0,0 +1,329 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{fmt, mem};
+
+use lock_api::{MutexGuard, RawMutex};
+
+/// A type-erased action that is run when the last projected guard is dropped.
+#[derive(Clone, Copy)]
+struct Release {
+    data: *const (),
+    release: unsafe fn(*const ()),
+}
+
+impl Release {
+    fn unlock<R: RawMutex>(raw: &R) -> Self {
+        unsafe fn unlock<R: RawMutex>(data: *const ()) {
+            let raw = unsafe { &*data.cast::<R>() };
+            unsafe { raw.unlock() }
+        }
+
+        Self {
+            data: (raw as *const R).cast(),
+            release: unlock::<R>,
+        }
+    }
+
+    fn split(split: &GuardSplit) -> Self {
+        unsafe fn release(data: *const ()) {
+            let split = unsafe { &*data.cast::<GuardSplit>() };
+            if split.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+                // SAFETY: We are the last guard of this split.
+                let parent = unsafe { (*split.parent.get()).take() };
+                if let Some(parent) = parent {
+                    unsafe { parent.run() }
+                }
+            }
+        }
+
+        Self {
+            data: (split as *const GuardSplit).cast(),
+            release,
+        }
+    }
+
+    unsafe fn run(self) {
+        unsafe { (self.release)(self.data) }
+    }
+}
+
+/// The shared state of guards created via [`ProjectedMutexGuard::split`].
+///
+/// The mutex is unlocked once all guards of a split have been dropped.
+/// A `GuardSplit` is usually created on the stack right before splitting.
+pub struct GuardSplit {
+    remaining: AtomicUsize,
+    parent: UnsafeCell<Option<Release>>,
+}
+
+// SAFETY: `parent` is only accessed when splitting (through `&mut`) and by the last dropped guard.
+unsafe impl Send for GuardSplit {}
+unsafe impl Sync for GuardSplit {}
+
+impl GuardSplit {
+    /// Creates a new `GuardSplit`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            remaining: AtomicUsize::new(0),
+            parent: UnsafeCell::new(None),
+        }
+    }
+}
+
+impl Default for GuardSplit {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for GuardSplit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardSplit")
+            .field("remaining", &self.remaining)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A mutex guard that is projected onto a part of the locked data.
+///
+/// In contrast to [`lock_api::MappedMutexGuard`], projected guards can be split into multiple guards of disjoint parts of the data via [`split`].
+/// The mutex is unlocked once all projected guards that originate from the same [`MutexGuard`] have been dropped.
+///
+/// [`split`]: Self::split
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{GuardSplit, ProjectedMutexGuard, SpinMutex};
+///
+/// struct Device {
+///     rx: Vec<u8>,
+///     tx: Vec<u8>,
+/// }
+///
+/// static DEVICE: SpinMutex<Device> = SpinMutex::new(Device {
+///     rx: Vec::new(),
+///     tx: Vec::new(),
+/// });
+///
+/// let mut rx = ProjectedMutexGuard::project(DEVICE.lock(), |device| &mut device.rx);
+/// rx.push(1);
+/// drop(rx);
+///
+/// let mut split = GuardSplit::new();
+/// let (rx, mut tx) = ProjectedMutexGuard::split(DEVICE.lock(), &mut split, |device| {
+///     (&mut device.rx, &mut device.tx)
+/// });
+/// tx.extend_from_slice(&rx);
+/// drop(rx);
+/// assert!(DEVICE.is_locked());
+/// drop(tx);
+/// assert!(!DEVICE.is_locked());
+/// ```
+#[must_use = "if unused the Mutex will immediately unlock"]
+pub struct ProjectedMutexGuard<'a, R: RawMutex, T: ?Sized> {
+    data: *mut T,
+    release: Release,
+    marker: PhantomData<(&'a mut T, &'a R, R::GuardMarker)>,
+}
+
+unsafe impl<'a, R: RawMutex + Sync + 'a, T: ?Sized + Sync + 'a> Sync
+    for ProjectedMutexGuard<'a, R, T>
+{
+}
+
+unsafe impl<'a, R: RawMutex + 'a, T: ?Sized + Send + 'a> Send for ProjectedMutexGuard<'a, R, T> where
+    R::GuardMarker: Send
+{
+}
+
+impl<'a, R: RawMutex + 'a, T: ?Sized + 'a> ProjectedMutexGuard<'a, R, T> {
+    /// Creates a projected guard from a [`MutexGuard`] for the whole data.
+    #[inline]
+    pub fn from_guard(guard: MutexGuard<'a, R, T>) -> Self {
+        let mutex = MutexGuard::mutex(&guard);
+        // SAFETY: We hold the lock and take over its ownership.
+        let raw = unsafe { mutex.raw() };
+        let data = mutex.data_ptr();
+        mem::forget(guard);
+
+        Self {
+            data,
+            release: Release::unlock(raw),
+            marker: PhantomData,
+        }
+    }
+
+    /// Projects a [`MutexGuard`] onto a part of the locked data.
+    #[inline]
+    pub fn project<U: ?Sized, F>(guard: MutexGuard<'a, R, T>, f: F) -> ProjectedMutexGuard<'a, R, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        Self::map(Self::from_guard(guard), f)
+    }
+
+    /// Splits a [`MutexGuard`] into two guards of disjoint parts of the locked data.
+    ///
+    /// The mutex is unlocked once both guards have been dropped.
+    #[inline]
+    pub fn split<'s, A: ?Sized, B: ?Sized, F>(
+        guard: MutexGuard<'a, R, T>,
+        split: &'s mut GuardSplit,
+        f: F,
+    ) -> (ProjectedMutexGuard<'s, R, A>, ProjectedMutexGuard<'s, R, B>)
+    where
+        'a: 's,
+        F: FnOnce(&mut T) -> (&mut A, &mut B),
+    {
+        Self::split_projected(Self::from_guard(guard), split, f)
+    }
+
+    /// Narrows this guard onto a part of the locked data.
+    #[inline]
+    pub fn map<U: ?Sized, F>(s: Self, f: F) -> ProjectedMutexGuard<'a, R, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        // SAFETY: We have exclusive access to the data.
+        let data = f(unsafe { &mut *s.data }) as *mut U;
+        let release = s.release;
+        mem::forget(s);
+
+        ProjectedMutexGuard {
+            data,
+            release,
+            marker: PhantomData,
+        }
+    }
+
+    /// Splits this guard into two guards of disjoint parts of the locked data.
+    ///
+    /// The mutex is unlocked once both guards and all other guards originating from the same [`MutexGuard`] have been dropped.
+    #[inline]
+    pub fn split_projected<'s, A: ?Sized, B: ?Sized, F>(
+        s: Self,
+        split: &'s mut GuardSplit,
+        f: F,
+    ) -> (ProjectedMutexGuard<'s, R, A>, ProjectedMutexGuard<'s, R, B>)
+    where
+        'a: 's,
+        F: FnOnce(&mut T) -> (&mut A, &mut B),
+    {
+        // SAFETY: We have exclusive access to the data.
+        let (a, b) = f(unsafe { &mut *s.data });
+        let (a, b) = (a as *mut A, b as *mut B);
+
+        *split.parent.get_mut() = Some(s.release);
+        *split.remaining.get_mut() = 2;
+        mem::forget(s);
+
+        let release = Release::split(split);
+        (
+            ProjectedMutexGuard {
+                data: a,
+                release,
+                marker: PhantomData,
+            },
+            ProjectedMutexGuard {
+                data: b,
+                release,
+                marker: PhantomData,
+            },
+        )
+    }
+}
+
+impl<'a, R: RawMutex + 'a, T: ?Sized + 'a> From<MutexGuard<'a, R, T>>
+    for ProjectedMutexGuard<'a, R, T>
+{
+    #[inline]
+    fn from(guard: MutexGuard<'a, R, T>) -> Self {
+        Self::from_guard(guard)
+    }
+}
+
+impl<'a, R: RawMutex + 'a, T: ?Sized + 'a> Deref for ProjectedMutexGuard<'a, R, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, R: RawMutex + 'a, T: ?Sized + 'a> DerefMut for ProjectedMutexGuard<'a, R, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<'a, R: RawMutex + 'a, T: ?Sized + 'a> Drop for ProjectedMutexGuard<'a, R, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: We own our share of the lock.
+        unsafe { self.release.run() }
+    }
+}
+
+impl<'a, R: RawMutex + 'a, T: fmt::Debug + ?Sized + 'a> fmt::Debug
+    for ProjectedMutexGuard<'a, R, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpinMutex;
+
+    #[derive(Default)]
+    struct Pair {
+        a: u32,
+        b: (u32, u32),
+    }
+
+    #[test]
+    fn project() {
+        let mutex = SpinMutex::new(Pair::default());
+
+        let mut a = ProjectedMutexGuard::project(mutex.lock(), |pair| &mut pair.a);
+        *a = 1;
+        assert!(mutex.is_locked());
+        drop(a);
+        assert!(!mutex.is_locked());
+        assert_eq!(mutex.lock().a, 1);
+    }
+
+    #[test]
+    fn nested_split() {
+        let mutex = SpinMutex::new(Pair::default());
+
+        let mut outer = GuardSplit::new();
+        let (mut a, b) =
+            ProjectedMutexGuard::split(mutex.lock(), &mut outer, |pair| (&mut pair.a, &mut pair.b));
+        let mut inner = GuardSplit::new();
+        let (mut b0, mut b1) =
+            ProjectedMutexGuard::split_projected(b, &mut inner, |b| (&mut b.0, &mut b.1));
+
+        *a = 1;
+        *b0 = 2;
+        *b1 = 3;
+
+        drop(b0);
+        drop(a);
+        assert!(mutex.is_locked());
+        drop(b1);
+        assert!(!mutex.is_locked());
+
+        let pair = mutex.lock();
+        assert_eq!((pair.a, pair.b), (1, (2, 3)));
+    }
+}
@@ -0,0 +1,218 @@
+use core::ops::Deref;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use crate::relax::{Backoff, Relax};
+use crate::MAX_CPUS;
+
+/// The number of nested queue lock acquisitions per CPU core.
+///
+/// This is shared by [`RawInterruptMcsMutex`](crate::RawInterruptMcsMutex) and [`RawInterruptCnaMutex`](crate::RawInterruptCnaMutex).
+/// Like in Linux, one level each for thread context, soft interrupts, hard interrupts, and non-maskable interrupts.
+pub const MAX_MCS_NESTING: usize = 4;
+
+/// A wait node of an MCS-style queue.
+pub(crate) struct QueueNode {
+    pub(crate) next: AtomicPtr<QueueNode>,
+    /// Whether this node has become the head of the queue.
+    pub(crate) head: AtomicBool,
+    pub(crate) numa_node: AtomicUsize,
+    /// The secondary queue of CNA locks, handed over with the head of the queue.
+    pub(crate) secondary_head: AtomicPtr<QueueNode>,
+    pub(crate) secondary_tail: AtomicPtr<QueueNode>,
+    /// The number of consecutive local handoffs of CNA locks, handed over with the head of the queue.
+    pub(crate) local_handoffs: AtomicUsize,
+}
+
+impl QueueNode {
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub(crate) const INIT: Self = Self {
+        next: AtomicPtr::new(ptr::null_mut()),
+        head: AtomicBool::new(false),
+        numa_node: AtomicUsize::new(0),
+        secondary_head: AtomicPtr::new(ptr::null_mut()),
+        secondary_tail: AtomicPtr::new(ptr::null_mut()),
+        local_handoffs: AtomicUsize::new(0),
+    };
+
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *mut Self {
+        ptr::from_ref(self).cast_mut()
+    }
+
+    /// Makes this node the head of the queue.
+    #[inline]
+    pub(crate) fn make_head(&self, secondary: (*mut Self, *mut Self), local_handoffs: usize) {
+        self.secondary_head.store(secondary.0, Ordering::Relaxed);
+        self.secondary_tail.store(secondary.1, Ordering::Relaxed);
+        self.local_handoffs.store(local_handoffs, Ordering::Relaxed);
+        self.head.store(true, Ordering::Release);
+    }
+
+    /// Waits until our successor has linked itself to this node.
+    #[inline]
+    pub(crate) fn wait_next(&self) -> *mut Self {
+        let mut backoff = Backoff::default();
+        loop {
+            let next = self.next.load(Ordering::Acquire);
+            if !next.is_null() {
+                break next;
+            }
+            backoff.relax();
+        }
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NODES_INIT: [QueueNode; MAX_MCS_NESTING] = [QueueNode::INIT; MAX_MCS_NESTING];
+
+static NODES: [[QueueNode; MAX_MCS_NESTING]; MAX_CPUS] = [NODES_INIT; MAX_CPUS];
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of nodes in use per CPU core.
+static NESTING: [AtomicUsize; MAX_CPUS] = [ZERO; MAX_CPUS];
+
+/// A wait node in per-CPU statics, released on drop.
+///
+/// The caller must not migrate to another CPU core while holding this.
+pub(crate) struct PerCpuNode {
+    cpu: usize,
+    node: &'static QueueNode,
+}
+
+impl PerCpuNode {
+    /// Acquires the next free wait node of CPU core `cpu`.
+    #[inline]
+    pub(crate) fn acquire(cpu: usize, numa_node: usize) -> Self {
+        let level = NESTING[cpu].fetch_add(1, Ordering::Relaxed);
+        if level >= MAX_MCS_NESTING {
+            NESTING[cpu].fetch_sub(1, Ordering::Relaxed);
+            panic!("queue lock nesting too deep");
+        }
+
+        let node = &NODES[cpu][level];
+        node.next.store(ptr::null_mut(), Ordering::Relaxed);
+        node.head.store(false, Ordering::Relaxed);
+        node.numa_node.store(numa_node, Ordering::Relaxed);
+        node.secondary_head
+            .store(ptr::null_mut(), Ordering::Relaxed);
+        node.secondary_tail
+            .store(ptr::null_mut(), Ordering::Relaxed);
+        node.local_handoffs.store(0, Ordering::Relaxed);
+        Self { cpu, node }
+    }
+}
+
+impl Deref for PerCpuNode {
+    type Target = QueueNode;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.node
+    }
+}
+
+impl Drop for PerCpuNode {
+    #[inline]
+    fn drop(&mut self) {
+        NESTING[self.cpu].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The lock word and queue tail of an MCS-style queue lock.
+pub(crate) struct Queue {
+    pub(crate) tail: AtomicPtr<QueueNode>,
+    locked: AtomicBool,
+}
+
+impl Queue {
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub(crate) const INIT: Self = Self {
+        tail: AtomicPtr::new(ptr::null_mut()),
+        locked: AtomicBool::new(false),
+    };
+
+    /// Locks without queueing if nobody is queued, so waiters are not overtaken.
+    #[inline]
+    pub(crate) fn lock_fast(&self) -> bool {
+        self.tail.load(Ordering::Relaxed).is_null() && self.try_lock()
+    }
+
+    /// Queues up `node` and waits until we have locked.
+    ///
+    /// Afterwards, the caller must hand the head of the queue over before `node` may be reused.
+    ///
+    /// # Safety
+    ///
+    /// `node` must stay valid until the head of the queue has been handed over.
+    pub(crate) unsafe fn lock_queued(&self, node: &QueueNode) {
+        let prev = self.tail.swap(node.as_ptr(), Ordering::AcqRel);
+        if !prev.is_null() {
+            // SAFETY: Queued nodes stay valid until they have handed over the head of the queue.
+            unsafe { &*prev }
+                .next
+                .store(node.as_ptr(), Ordering::Release);
+
+            let mut backoff = Backoff::default();
+            while !node.head.load(Ordering::Acquire) {
+                backoff.relax();
+            }
+        }
+
+        // We are the head of the queue and the only waiter spinning on `locked`.
+        let mut backoff = Backoff::default();
+        while !self.try_lock_weak() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_weak(&self) -> bool {
+        !self.locked.load(Ordering::Relaxed)
+            && self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+    }
+
+    #[inline]
+    pub(crate) fn try_lock(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    #[inline]
+    pub(crate) fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nodes_are_released() {
+        let cpu = MAX_CPUS - 1;
+        for _ in 0..2 * MAX_MCS_NESTING {
+            let _nodes: [PerCpuNode; MAX_MCS_NESTING] =
+                core::array::from_fn(|_| PerCpuNode::acquire(cpu, 0));
+        }
+        assert_eq!(NESTING[cpu].load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    #[should_panic = "queue lock nesting too deep"]
+    fn nesting_too_deep() {
+        let _nodes: [PerCpuNode; MAX_MCS_NESTING + 1] =
+            core::array::from_fn(|_| PerCpuNode::acquire(MAX_CPUS - 2, 0));
+    }
+}
@@ -0,0 +1,496 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::num::NonZeroUsize;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use lock_api::{GetThreadId, RawMutex};
+
+use crate::{
+    local_irq_restore, local_irq_save, CoreIdProvider, Flags, InterruptGuard,
+    RawInterruptSpinMutex, RawSpinMutex, RegisteredCoreId,
+};
+
+const NO_OWNER: usize = usize::MAX;
+
+/// Provides the ID of the current thread for [`RawThreadReentrantSpinMutex`].
+///
+/// For reentering from the same CPU core instead, see [`RawReentrantSpinMutex`].
+///
+/// # Safety
+///
+/// [`thread_id`](Self::thread_id) must return IDs that are unique among all threads that are alive at the same time.
+/// CPU core IDs do not qualify, since they are shared by all threads on a CPU core.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+///
+/// use hermit_sync::ThreadIdProvider;
+///
+/// struct StdThreadId;
+///
+/// // SAFETY: Thread-local addresses are unique among live threads.
+/// unsafe impl ThreadIdProvider for StdThreadId {
+///     fn thread_id() -> NonZeroUsize {
+///         thread_local!(static ID: u8 = const { 0 });
+///         ID.with(|id| NonZeroUsize::new(id as *const u8 as usize).unwrap())
+///     }
+/// }
+/// ```
+pub unsafe trait ThreadIdProvider {
+    /// Returns the ID of the current thread.
+    fn thread_id() -> NonZeroUsize;
+}
+
+/// A [`GetThreadId`] based on a [`ThreadIdProvider`].
+pub struct ProvidedThreadId<P> {
+    _provider: PhantomData<fn() -> P>,
+}
+
+unsafe impl<P: ThreadIdProvider> GetThreadId for ProvidedThreadId<P> {
+    const INIT: Self = Self {
+        _provider: PhantomData,
+    };
+
+    #[inline]
+    fn nonzero_thread_id(&self) -> NonZeroUsize {
+        P::thread_id()
+    }
+}
+
+/// A reentrant [`RawSpinMutex`] owned by the threads of `P`.
+///
+/// This allows [`lock_api::ReentrantMutex`] to be reentered from the same thread, for example, through nested subsystem calls.
+pub type RawThreadReentrantSpinMutex<P> =
+    lock_api::RawReentrantMutex<RawSpinMutex, ProvidedThreadId<P>>;
+
+/// A [`lock_api::ReentrantMutex`] based on [`RawThreadReentrantSpinMutex`].
+///
+/// # Examples
+///
+/// ```
+/// # use core::num::NonZeroUsize;
+/// use hermit_sync::{ThreadReentrantSpinMutex, ThreadIdProvider};
+///
+/// # struct StdThreadId;
+/// # unsafe impl ThreadIdProvider for StdThreadId {
+/// #     fn thread_id() -> NonZeroUsize {
+/// #         thread_local!(static ID: u8 = const { 0 });
+/// #         ID.with(|id| NonZeroUsize::new(id as *const u8 as usize).unwrap())
+/// #     }
+/// # }
+/// static SUBSYSTEM: ThreadReentrantSpinMutex<usize, StdThreadId> = ThreadReentrantSpinMutex::new(0);
+///
+/// let outer = SUBSYSTEM.lock();
+/// let inner = SUBSYSTEM.lock();
+/// assert_eq!(*inner, 0);
+/// drop(inner);
+/// drop(outer);
+/// ```
+pub type ThreadReentrantSpinMutex<T, P> =
+    lock_api::ReentrantMutex<RawSpinMutex, ProvidedThreadId<P>, T>;
+
+/// A [`lock_api::ReentrantMutexGuard`] based on [`RawThreadReentrantSpinMutex`].
+pub type ThreadReentrantSpinMutexGuard<'a, T, P> =
+    lock_api::ReentrantMutexGuard<'a, RawSpinMutex, ProvidedThreadId<P>, T>;
+
+/// An interrupt-safe [`RawThreadReentrantSpinMutex`].
+pub type RawInterruptThreadReentrantSpinMutex<P> =
+    lock_api::RawReentrantMutex<RawInterruptSpinMutex, ProvidedThreadId<P>>;
+
+/// A [`lock_api::ReentrantMutex`] based on [`RawInterruptThreadReentrantSpinMutex`].
+pub type InterruptThreadReentrantSpinMutex<T, P> =
+    lock_api::ReentrantMutex<RawInterruptSpinMutex, ProvidedThreadId<P>, T>;
+
+/// A [`lock_api::ReentrantMutexGuard`] based on [`RawInterruptThreadReentrantSpinMutex`].
+pub type InterruptThreadReentrantSpinMutexGuard<'a, T, P> =
+    lock_api::ReentrantMutexGuard<'a, RawInterruptSpinMutex, ProvidedThreadId<P>, T>;
+
+/// A reentrant spin mutex owned by CPU cores, as identified by [`CoreIdProvider`] `C`.
+///
+/// In contrast to [`RawThreadReentrantSpinMutex`], this needs no [`ThreadIdProvider`].
+/// Interrupts are disabled before the current core ID is read and stay disabled until the outermost unlock.
+/// Thus, the owning CPU core cannot switch to another task while holding this mutex, and its core ID identifies the owner.
+/// Since only the outermost unlock restores the saved interrupt state, nested guards can be dropped in any order.
+///
+/// Like [`RawInterruptMutex`](crate::RawInterruptMutex), this disables interrupts on a best-effort basis (see [`set_interrupts_managed`](crate::set_interrupts_managed)).
+///
+/// # Relation to `lock_api`
+///
+/// This is neither a [`lock_api::RawReentrantMutex`] nor a [`RawMutex`]:
+/// * [`lock_api::RawReentrantMutex`] reads the owner ID before its inner mutex disables interrupts.
+///   A task that migrates to another CPU core in between could then mistake the lock of its previous CPU core for its own.
+/// * Implementing [`RawMutex`] would allow [`lock_api::Mutex`] to hand out aliasing mutable references when reentered.
+///
+/// Thus, this mutex provides its own [`ReentrantSpinMutex`] wrapper.
+/// For a [`lock_api::ReentrantMutex`] reentered from the same thread, see [`ThreadReentrantSpinMutex`].
+///
+/// # Panics
+///
+/// Locking panics if `C` cannot determine the current CPU core, for example, because no provider has been registered via [`set_core_id_provider`](crate::set_core_id_provider).
+pub struct RawReentrantSpinMutex<C = RegisteredCoreId> {
+    mutex: RawSpinMutex,
+    owner: AtomicUsize,
+    lock_count: UnsafeCell<usize>,
+    irq_state: UnsafeCell<Flags>,
+    _core_id: PhantomData<fn() -> C>,
+}
+
+// SAFETY: The `UnsafeCell`s are only accessed by the owning CPU core with interrupts disabled.
+unsafe impl<C> Sync for RawReentrantSpinMutex<C> {}
+// SAFETY: Mutexes cannot be send to other threads while locked.
+// Sending them while unlocked is fine.
+unsafe impl<C> Send for RawReentrantSpinMutex<C> {}
+
+impl<C> RawReentrantSpinMutex<C> {
+    /// Initial value for an unlocked mutex.
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub const INIT: Self = Self {
+        mutex: RawSpinMutex::INIT,
+        owner: AtomicUsize::new(NO_OWNER),
+        lock_count: UnsafeCell::new(0),
+        irq_state: UnsafeCell::new(Flags::EMPTY),
+        _core_id: PhantomData,
+    };
+}
+
+impl<C: CoreIdProvider> RawReentrantSpinMutex<C> {
+    #[inline]
+    #[track_caller]
+    fn lock_internal(&self, try_lock: impl FnOnce() -> bool) -> bool {
+        let flags = local_irq_save();
+        let Some(core_id) = C::try_core_id() else {
+            local_irq_restore(flags);
+            panic!("reentrant mutex locked before the current CPU core is known");
+        };
+
+        if self.owner.load(Ordering::Relaxed) == core_id {
+            // SAFETY: We own the mutex and interrupts are disabled.
+            unsafe {
+                *self.lock_count.get() = (*self.lock_count.get())
+                    .checked_add(1)
+                    .expect("reentrant mutex lock count overflowed");
+            }
+            // Interrupts were already disabled by the outermost lock.
+            local_irq_restore(flags);
+            return true;
+        }
+
+        if !try_lock() {
+            local_irq_restore(flags);
+            return false;
+        }
+
+        self.owner.store(core_id, Ordering::Relaxed);
+        // SAFETY: We have exclusive access through locking `mutex`.
+        unsafe {
+            *self.lock_count.get() = 1;
+            *self.irq_state.get() = flags;
+        }
+        true
+    }
+
+    /// Acquires this mutex, blocking the current CPU core until it is able to do so.
+    ///
+    /// If this mutex is already held by the current CPU core, this succeeds immediately.
+    #[inline]
+    #[track_caller]
+    pub fn lock(&self) {
+        self.lock_internal(|| {
+            self.mutex.lock();
+            true
+        });
+    }
+
+    /// Attempts to acquire this mutex without blocking.
+    ///
+    /// Returns `true` if the lock was successfully acquired and `false` otherwise.
+    #[inline]
+    #[track_caller]
+    pub fn try_lock(&self) -> bool {
+        self.lock_internal(|| self.mutex.try_lock())
+    }
+
+    /// Unlocks this mutex.
+    ///
+    /// The inner mutex is only unlocked and interrupts are only restored once the lock count drops to zero.
+    ///
+    /// # Safety
+    ///
+    /// This method may only be called if the mutex is held by the current CPU core.
+    #[inline]
+    pub unsafe fn unlock(&self) {
+        // SAFETY: We own the mutex and interrupts are disabled.
+        let lock_count = unsafe { &mut *self.lock_count.get() };
+        *lock_count -= 1;
+        if *lock_count == 0 {
+            // SAFETY: We own the mutex and interrupts are disabled.
+            let flags = unsafe { *self.irq_state.get() };
+            self.owner.store(NO_OWNER, Ordering::Relaxed);
+            unsafe {
+                self.mutex.unlock();
+            }
+            local_irq_restore(flags);
+        }
+    }
+
+    /// Checks whether this mutex is currently locked.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.mutex.is_locked()
+    }
+
+    /// Checks whether this mutex is currently held by the current CPU core.
+    #[inline]
+    pub fn is_owned_by_current_core(&self) -> bool {
+        let _guard = InterruptGuard::disable();
+        C::try_core_id().is_some_and(|core_id| self.owner.load(Ordering::Relaxed) == core_id)
+    }
+}
+
+impl<C> fmt::Debug for RawReentrantSpinMutex<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawReentrantSpinMutex")
+            .finish_non_exhaustive()
+    }
+}
+
+/// A reentrant mutex based on [`RawReentrantSpinMutex`].
+///
+/// Like [`lock_api::ReentrantMutex`], this only hands out shared references, since the data might be borrowed several times by the same CPU core.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+///
+/// use hermit_sync::{irqs_disabled, ReentrantSpinMutex};
+///
+/// fn core_id() -> usize {
+///     // Read the core ID from a CPU-local register here.
+///     0
+/// }
+///
+/// hermit_sync::set_core_id_provider(core_id);
+///
+/// static SUBSYSTEM: ReentrantSpinMutex<Cell<usize>> =
+///     ReentrantSpinMutex::new(Cell::new(0));
+///
+/// fn nested_call() {
+///     let inner = SUBSYSTEM.lock();
+///     inner.set(inner.get() + 1);
+/// }
+///
+/// let outer = SUBSYSTEM.lock();
+/// nested_call();
+/// assert_eq!(outer.get(), 1);
+/// drop(outer);
+/// assert!(!SUBSYSTEM.is_locked());
+/// ```
+pub struct ReentrantSpinMutex<T: ?Sized, C = RegisteredCoreId> {
+    raw: RawReentrantSpinMutex<C>,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: Only the owning CPU core accesses the data, which is handed over between CPU cores when unlocking.
+unsafe impl<T: ?Sized + Send, C> Sync for ReentrantSpinMutex<T, C> {}
+unsafe impl<T: ?Sized + Send, C> Send for ReentrantSpinMutex<T, C> {}
+
+impl<T, C> ReentrantSpinMutex<T, C> {
+    /// Creates a new reentrant mutex in an unlocked state ready for use.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            raw: RawReentrantSpinMutex::INIT,
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, C> ReentrantSpinMutex<T, C> {
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the mutex mutably, no actual locking needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Returns the underlying raw mutex.
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe because it allows unlocking a mutex while still holding a reference to a guard.
+    #[inline]
+    pub unsafe fn raw(&self) -> &RawReentrantSpinMutex<C> {
+        &self.raw
+    }
+}
+
+impl<T: ?Sized, C: CoreIdProvider> ReentrantSpinMutex<T, C> {
+    /// Acquires this mutex, blocking the current CPU core until it is able to do so.
+    ///
+    /// If this mutex is already held by the current CPU core, this succeeds immediately.
+    #[inline]
+    #[track_caller]
+    pub fn lock(&self) -> ReentrantSpinMutexGuard<'_, T, C> {
+        self.raw.lock();
+        ReentrantSpinMutexGuard {
+            mutex: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Attempts to acquire this mutex without blocking.
+    #[inline]
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<ReentrantSpinMutexGuard<'_, T, C>> {
+        self.raw.try_lock().then(|| ReentrantSpinMutexGuard {
+            mutex: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Checks whether this mutex is currently locked.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    /// Checks whether this mutex is currently held by the current CPU core.
+    #[inline]
+    pub fn is_owned_by_current_core(&self) -> bool {
+        self.raw.is_owned_by_current_core()
+    }
+}
+
+impl<T: ?Sized, C> fmt::Debug for ReentrantSpinMutex<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReentrantSpinMutex").finish_non_exhaustive()
+    }
+}
+
+/// A guard of an [`ReentrantSpinMutex`].
+///
+/// The mutex is unlocked once all guards of the current CPU core have been dropped.
+#[must_use = "if unused the ReentrantMutex will immediately unlock"]
+pub struct ReentrantSpinMutexGuard<'a, T: ?Sized, C: CoreIdProvider = RegisteredCoreId> {
+    mutex: &'a ReentrantSpinMutex<T, C>,
+    /// The guard must be dropped on the CPU core that locked the mutex.
+    _not_send: PhantomData<*const ()>,
+}
+
+// SAFETY: Only shared references to `T` are handed out.
+unsafe impl<T: ?Sized + Sync, C: CoreIdProvider> Sync for ReentrantSpinMutexGuard<'_, T, C> {}
+
+impl<T: ?Sized, C: CoreIdProvider> Deref for ReentrantSpinMutexGuard<'_, T, C> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: The current CPU core holds the mutex.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized, C: CoreIdProvider> Drop for ReentrantSpinMutexGuard<'_, T, C> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: The current CPU core holds the mutex.
+        unsafe {
+            self.mutex.raw.unlock();
+        }
+    }
+}
+
+impl<T: fmt::Debug + ?Sized, C: CoreIdProvider> fmt::Debug for ReentrantSpinMutexGuard<'_, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::percpu::per_cpu::ThreadCpu;
+
+    struct StdThreadId;
+
+    // SAFETY: Thread-local addresses are unique among live threads.
+    unsafe impl ThreadIdProvider for StdThreadId {
+        fn thread_id() -> NonZeroUsize {
+            thread_local!(static ID: u8 = const { 0 });
+            ID.with(|id| NonZeroUsize::new(id as *const u8 as usize).unwrap())
+        }
+    }
+
+    #[test]
+    fn reenter() {
+        let mutex = InterruptThreadReentrantSpinMutex::<_, StdThreadId>::new(());
+        let outer = mutex.lock();
+        let inner = mutex.try_lock().unwrap();
+        assert!(mutex.is_owned_by_current_thread());
+        drop(inner);
+        drop(outer);
+        assert!(!mutex.is_locked());
+    }
+
+    #[test]
+    fn other_thread_blocks() {
+        let mutex = ThreadReentrantSpinMutex::<_, StdThreadId>::new(());
+        let _guard = mutex.lock();
+        thread::scope(|s| {
+            s.spawn(|| assert!(mutex.try_lock().is_none()));
+        });
+    }
+
+    #[test]
+    fn core_reenter_out_of_order() {
+        let mutex = ReentrantSpinMutex::<_, ThreadCpu>::new(());
+        let outer = mutex.lock();
+        let inner = mutex.try_lock().unwrap();
+        assert!(mutex.is_owned_by_current_core());
+
+        drop(outer);
+        assert!(mutex.is_locked());
+        #[cfg(all(unix, not(miri), not(feature = "interrupt-hooks")))]
+        assert!(crate::irqs_disabled());
+
+        drop(inner);
+        assert!(!mutex.is_locked());
+        #[cfg(all(unix, not(miri), not(feature = "interrupt-hooks")))]
+        assert!(!crate::irqs_disabled());
+    }
+
+    #[test]
+    fn other_core_blocks() {
+        let mutex = ReentrantSpinMutex::<_, ThreadCpu>::new(());
+        let _guard = mutex.lock();
+        thread::scope(|s| {
+            s.spawn(|| {
+                ThreadCpu::set(1);
+                assert!(!mutex.is_owned_by_current_core());
+                assert!(mutex.try_lock().is_none());
+            });
+        });
+    }
+
+    #[test]
+    #[should_panic = "reentrant mutex locked before the current CPU core is known"]
+    fn unknown_core_id() {
+        // No core ID provider is registered in unit tests.
+        let mutex = ReentrantSpinMutex::<_>::new(());
+        let _guard = mutex.lock();
+    }
+}
@@ -0,0 +1,177 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use lock_api::{GuardSend, RawMutex};
+
+use crate::atomic::cas;
+use crate::pv::{self, PvBackoff};
+use crate::stats::RawMutexSample;
+
+/// A simple [test and test-and-set] [spinlock] with [exponential backoff].
+///
+/// [test and test-and-set]: https://en.wikipedia.org/wiki/Test_and_test-and-set
+/// [spinlock]: https://en.wikipedia.org/wiki/Spinlock
+/// [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
+// Based on `spinning_top::RawSpinlock`.
+pub struct RawSpinMutex {
+    locked: AtomicBool,
+}
+
+impl RawSpinMutex {
+    #[inline]
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Attempts to acquire this mutex without blocking, possibly failing spuriously.
+    ///
+    /// In contrast to [`RawMutex::try_lock`], this may fail even if the mutex is not locked.
+    /// On LL/SC architectures, this is cheaper than `try_lock` and intended for callers that retry in their own loops.
+    ///
+    /// Returns `true` if the mutex was successfully acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::RawSpinMutex;
+    /// use lock_api::RawMutex;
+    ///
+    /// let mutex = RawSpinMutex::INIT;
+    /// while !mutex.try_lock_weak() {
+    ///     core::hint::spin_loop();
+    /// }
+    /// assert!(mutex.is_locked());
+    /// unsafe { mutex.unlock() };
+    /// ```
+    #[inline]
+    pub fn try_lock_weak(&self) -> bool {
+        cas::compare_exchange_weak(
+            &self.locked,
+            false,
+            true,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_ok()
+    }
+}
+
+unsafe impl RawMutex for RawSpinMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        locked: AtomicBool::new(false),
+    };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock(&self) {
+        let mut backoff = PvBackoff::default();
+        while !self.try_lock_weak() {
+            while self.is_locked() {
+                backoff.relax(self.key(), &|| self.is_locked());
+            }
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        cas::compare_exchange(
+            &self.locked,
+            false,
+            true,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+        pv::kick(self.key());
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+}
+
+impl RawMutexSample for RawSpinMutex {}
+
+/// A [`lock_api::Mutex`] based on [`RawSpinMutex`].
+pub type SpinMutex<T> = lock_api::Mutex<RawSpinMutex, T>;
+
+/// A [`lock_api::MutexGuard`] based on [`RawSpinMutex`].
+pub type SpinMutexGuard<'a, T> = lock_api::MutexGuard<'a, RawSpinMutex, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn smoke() {
+        let m = SpinMutex::<_>::new(());
+        drop(m.lock());
+        drop(m.lock());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn lots_and_lots() {
+        static M: SpinMutex<u32> = SpinMutex::<_>::new(0);
+        const J: u32 = 1000;
+        const K: u32 = 3;
+
+        fn inc() {
+            for _ in 0..J {
+                *M.lock() += 1;
+            }
+        }
+
+        let (tx, rx) = channel();
+        for _ in 0..2 * K {
+            let tx2 = tx.clone();
+            thread::spawn(move || {
+                inc();
+                tx2.send(()).unwrap();
+            });
+        }
+
+        drop(tx);
+        for _ in 0..2 * K {
+            rx.recv().unwrap();
+        }
+        assert_eq!(*M.lock(), J * K * 2);
+    }
+
+    #[test]
+    fn try_lock() {
+        let mutex = SpinMutex::<_>::new(42);
+
+        let a = mutex.try_lock();
+        assert_eq!(a.as_ref().map(|r| **r), Some(42));
+
+        let b = mutex.try_lock();
+        assert!(b.is_none());
+
+        drop(a);
+        let c = mutex.try_lock();
+        assert_eq!(c.as_ref().map(|r| **r), Some(42));
+    }
+
+    #[test]
+    fn try_lock_weak() {
+        let mutex = RawSpinMutex::INIT;
+
+        while !mutex.try_lock_weak() {}
+        assert!(mutex.is_locked());
+        assert!(!mutex.try_lock_weak());
+
+        unsafe { mutex.unlock() };
+        assert!(!mutex.is_locked());
+    }
+}
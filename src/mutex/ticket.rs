@@ -1,7 +1,45 @@
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+use cfg_if::cfg_if;
 use lock_api::{GuardSend, RawMutex, RawMutexFair};
-use spinning_top::relax::{Backoff, Relax};
+
+use crate::pv::{self, PvBackoff};
+use crate::relax::{Backoff, Relax};
+use crate::stats::RawMutexSample;
+
+/// The number of tickets that can be tracked as skipped at once.
+const SKIP_WINDOW: usize = usize::BITS as usize;
+
+cfg_if! {
+    if #[cfg(target_has_atomic = "64")] {
+        use core::sync::atomic::AtomicU64 as AtomicState;
+
+        type State = u64;
+        type Ticket = u32;
+    } else {
+        use core::sync::atomic::AtomicU32 as AtomicState;
+
+        type State = u32;
+        type Ticket = u16;
+    }
+}
+
+/// The lower half of the state is the ticket being served, the upper half is the next ticket.
+const TICKET_SHIFT: u32 = Ticket::BITS;
+
+/// Taking a ticket adds this to the state.
+const TICKET: State = 1 << TICKET_SHIFT;
+
+#[inline]
+fn next_ticket(state: State) -> Ticket {
+    (state >> TICKET_SHIFT) as Ticket
+}
+
+#[inline]
+fn serving(state: State) -> Ticket {
+    state as Ticket
+}
 
 /// A [fair] [ticket lock] with [exponential backoff].
 ///
@@ -10,53 +48,218 @@ use spinning_top::relax::{Backoff, Relax};
 /// [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
 // Based on `spin::mutex::TicketMutex`, but with backoff.
 pub struct RawTicketMutex {
-    next_ticket: AtomicUsize,
-    next_serving: AtomicUsize,
+    /// The next ticket and the ticket being served, packed for single-instruction `try_lock` and `is_locked`.
+    state: AtomicState,
+    /// Whether the current holder has to hand the lock back to a bumping holder on unlock.
+    bumped: AtomicBool,
+    /// A bitmap of canceled tickets, indexed by ticket modulo [`SKIP_WINDOW`].
+    skipped: AtomicUsize,
+}
+
+impl RawTicketMutex {
+    #[inline]
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    #[inline]
+    fn skip_bit(ticket: Ticket) -> usize {
+        1 << (ticket as usize % SKIP_WINDOW)
+    }
+
+    #[inline]
+    fn serving(&self, order: Ordering) -> Ticket {
+        serving(self.state.load(order))
+    }
+
+    /// Advances the ticket being served and returns it.
+    ///
+    /// Only the holder may call this, so the ticket being served does not change concurrently.
+    #[inline]
+    fn advance_serving(&self) -> Ticket {
+        let prev = if self.serving(Ordering::Relaxed) == Ticket::MAX {
+            // Wrap around without carrying into the next ticket.
+            self.state
+                .fetch_sub(State::from(Ticket::MAX), Ordering::SeqCst)
+        } else {
+            self.state.fetch_add(1, Ordering::SeqCst)
+        };
+        serving(prev).wrapping_add(1)
+    }
+
+    /// Serves the next ticket, skipping over canceled tickets.
+    #[inline]
+    fn serve_next(&self) {
+        loop {
+            let serving = self.advance_serving();
+
+            let bit = Self::skip_bit(serving);
+            if self.skipped.load(Ordering::SeqCst) & bit == 0 {
+                pv::kick(self.key());
+                return;
+            }
+
+            // Whoever clears the bit is served the canceled ticket.
+            if self.skipped.fetch_and(!bit, Ordering::SeqCst) & bit == 0 {
+                pv::kick(self.key());
+                return;
+            }
+        }
+    }
+
+    /// Takes a ticket that can be canceled before the mutex has been acquired.
+    ///
+    /// The returned [`RawTicket`] acquires the mutex via [`RawTicket::try_acquire`] or [`RawTicket::acquire`].
+    /// Dropping the ticket before it has been acquired cancels it without blocking the queue.
+    /// This allows composing timed or bounded waiting with the fair ticket lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::RawTicketMutex;
+    /// use lock_api::RawMutex;
+    ///
+    /// let mutex = RawTicketMutex::INIT;
+    /// mutex.lock();
+    ///
+    /// let ticket = mutex.lock_cancelable();
+    /// let ticket = ticket.try_acquire().unwrap_err();
+    /// // Give up waiting.
+    /// drop(ticket);
+    ///
+    /// unsafe { mutex.unlock() };
+    /// assert!(!mutex.is_locked());
+    /// ```
+    #[inline]
+    pub fn lock_cancelable(&self) -> RawTicket<'_> {
+        let ticket = next_ticket(self.state.fetch_add(TICKET, Ordering::Relaxed));
+        RawTicket {
+            mutex: self,
+            ticket,
+        }
+    }
+
+    #[inline]
+    fn cancel(&self, ticket: Ticket) {
+        let mut backoff = Backoff::default();
+        // The skip bitmap can only track tickets within `SKIP_WINDOW` of the ticket being served.
+        while ticket.wrapping_sub(self.serving(Ordering::Relaxed)) as usize >= SKIP_WINDOW {
+            backoff.relax();
+        }
+
+        let bit = Self::skip_bit(ticket);
+        self.skipped.fetch_or(bit, Ordering::SeqCst);
+
+        // If our ticket is already being served, whoever clears the bit is served the ticket.
+        if self.serving(Ordering::SeqCst) == ticket
+            && self.skipped.fetch_and(!bit, Ordering::SeqCst) & bit == bit
+        {
+            unsafe {
+                self.unlock();
+            }
+        }
+    }
+}
+
+/// A ticket of a [`RawTicketMutex`] taken via [`RawTicketMutex::lock_cancelable`].
+///
+/// Dropping this ticket cancels it.
+#[must_use = "dropping the ticket cancels it immediately"]
+pub struct RawTicket<'a> {
+    mutex: &'a RawTicketMutex,
+    ticket: Ticket,
+}
+
+impl<'a> RawTicket<'a> {
+    /// Returns `true` if this ticket is being served.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.mutex.serving(Ordering::Relaxed) == self.ticket
+    }
+
+    /// Acquires the mutex if this ticket is being served.
+    ///
+    /// If the ticket is not being served yet, it is returned.
+    #[inline]
+    pub fn try_acquire(self) -> Result<(), Self> {
+        if self.mutex.serving(Ordering::Acquire) == self.ticket {
+            core::mem::forget(self);
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Acquires the mutex, waiting until this ticket is being served.
+    #[inline]
+    pub fn acquire(self) {
+        let mut backoff = PvBackoff::default();
+        while self.mutex.serving(Ordering::Acquire) != self.ticket {
+            backoff.relax(self.mutex.key(), &|| !self.is_ready());
+        }
+        core::mem::forget(self);
+    }
+}
+
+impl fmt::Debug for RawTicket<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawTicket")
+            .field("ticket", &self.ticket)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for RawTicket<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.mutex.cancel(self.ticket);
+    }
 }
 
 unsafe impl RawMutex for RawTicketMutex {
     #[allow(clippy::declare_interior_mutable_const)]
     const INIT: Self = Self {
-        next_ticket: AtomicUsize::new(0),
-        next_serving: AtomicUsize::new(0),
+        state: AtomicState::new(0),
+        bumped: AtomicBool::new(false),
+        skipped: AtomicUsize::new(0),
     };
 
     type GuardMarker = GuardSend;
 
     #[inline]
     fn lock(&self) {
-        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
-
-        let mut backoff = Backoff::default();
-        while self.next_serving.load(Ordering::Acquire) != ticket {
-            backoff.relax();
-        }
+        self.lock_cancelable().acquire();
     }
 
     #[inline]
     fn try_lock(&self) -> bool {
-        let ticket = self
-            .next_ticket
-            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |ticket| {
-                if self.next_serving.load(Ordering::Acquire) == ticket {
-                    Some(ticket + 1)
-                } else {
-                    None
-                }
-            });
-
-        ticket.is_ok()
+        let state = self.state.load(Ordering::Relaxed);
+        next_ticket(state) == serving(state)
+            && self
+                .state
+                .compare_exchange(
+                    state,
+                    state.wrapping_add(TICKET),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
     }
 
     #[inline]
     unsafe fn unlock(&self) {
-        self.next_serving.fetch_add(1, Ordering::Release);
+        if self.bumped.swap(false, Ordering::AcqRel) {
+            // Hand the lock back to the bumping holder without serving the next ticket.
+            return;
+        }
+
+        self.serve_next();
     }
 
     #[inline]
     fn is_locked(&self) -> bool {
-        let ticket = self.next_ticket.load(Ordering::Relaxed);
-        self.next_serving.load(Ordering::Relaxed) != ticket
+        let state = self.state.load(Ordering::Relaxed);
+        next_ticket(state) != serving(state)
     }
 }
 
@@ -66,19 +269,51 @@ unsafe impl RawMutexFair for RawTicketMutex {
         unsafe { self.unlock() }
     }
 
+    /// Temporarily yields the mutex to exactly the next waiting thread.
+    ///
+    /// In contrast to unlocking and locking again, the caller does not lose its position in the queue.
+    /// Once the next waiter unlocks, the mutex is handed back to the caller before any further tickets are served.
     #[inline]
     unsafe fn bump(&self) {
-        let ticket = self.next_ticket.load(Ordering::Relaxed);
-        let serving = self.next_serving.load(Ordering::Relaxed);
-        if serving + 1 != ticket {
+        if self.bumped.load(Ordering::Relaxed) {
+            // We were handed the mutex by a bumping holder.
+            // Hand it back and queue up again.
             unsafe {
-                self.unlock_fair();
+                self.unlock();
+            }
+            self.lock();
+            return;
+        }
+
+        let state = self.state.load(Ordering::Relaxed);
+        if serving(state).wrapping_add(1) == next_ticket(state) {
+            return;
+        }
+
+        self.bumped.store(true, Ordering::Relaxed);
+        self.serve_next();
+
+        let mut backoff = Backoff::default();
+        while self.bumped.load(Ordering::Acquire) {
+            // All waiters might have canceled their tickets.
+            // Whoever clears `bumped` first decides whether we are handed the lock back.
+            if !self.is_locked() && self.bumped.swap(false, Ordering::Acquire) {
                 self.lock();
+                return;
             }
+            backoff.relax();
         }
     }
 }
 
+impl RawMutexSample for RawTicketMutex {
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        let state = self.state.load(Ordering::Relaxed);
+        next_ticket(state).wrapping_sub(serving(state)) as usize
+    }
+}
+
 /// A [`lock_api::Mutex`] based on [`RawTicketMutex`].
 pub type TicketMutex<T> = lock_api::Mutex<RawTicketMutex, T>;
 
@@ -240,4 +475,113 @@ mod tests {
         drop(lock);
         assert!(!mutex.is_locked());
     }
+
+    #[test]
+    fn cancel() {
+        let mutex = TicketMutex::<_>::new(());
+        let raw = unsafe { mutex.raw() };
+
+        let guard = mutex.lock();
+        let a = raw.lock_cancelable();
+        let b = raw.lock_cancelable();
+        let c = raw.lock_cancelable();
+        assert!(!b.is_ready());
+        drop(b);
+        let a = a.try_acquire().unwrap_err();
+        drop(a);
+        drop(guard);
+
+        // Canceled tickets `a` and `b` are skipped.
+        assert!(c.is_ready());
+        c.try_acquire().unwrap();
+        unsafe { raw.unlock() };
+        assert!(!mutex.is_locked());
+        drop(mutex.lock());
+    }
+
+    #[test]
+    fn cancel_while_served() {
+        let mutex = TicketMutex::<_>::new(());
+        let raw = unsafe { mutex.raw() };
+
+        let guard = mutex.lock();
+        let ticket = raw.lock_cancelable();
+        drop(guard);
+        assert!(ticket.is_ready());
+        drop(ticket);
+
+        assert!(!mutex.is_locked());
+        drop(mutex.lock());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn cancel_concurrent() {
+        const N: usize = 1000;
+
+        let mutex = TicketMutex::<_>::new(0);
+        let raw = unsafe { mutex.raw() };
+
+        thread::scope(|s| {
+            for i in 0..4 {
+                let mutex = &mutex;
+                s.spawn(move || {
+                    for j in 0..N {
+                        let ticket = raw.lock_cancelable();
+                        if (i + j) % 2 == 0 {
+                            drop(ticket);
+                        } else {
+                            ticket.acquire();
+                            let mut guard = unsafe { mutex.make_guard_unchecked() };
+                            *guard += 1;
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*mutex.lock(), 2 * N);
+    }
+
+    #[test]
+    fn bump_keeps_queue_position() {
+        let mutex = TicketMutex::<Vec<u32>>::new(Vec::new());
+        let raw = unsafe { mutex.raw() };
+        let wait_for_tickets = |n| {
+            while next_ticket(raw.state.load(Ordering::Relaxed)) != n {
+                thread::yield_now();
+            }
+        };
+
+        let mut guard = mutex.lock();
+        thread::scope(|s| {
+            s.spawn(|| mutex.lock().push(1));
+            wait_for_tickets(2);
+            s.spawn(|| mutex.lock().push(2));
+            wait_for_tickets(3);
+
+            TicketMutexGuard::bump(&mut guard);
+            guard.push(0);
+            drop(guard);
+        });
+
+        assert_eq!(*mutex.lock(), [1, 0, 2]);
+    }
+
+    #[test]
+    fn serving_wraps_around() {
+        let mutex = RawTicketMutex {
+            state: AtomicState::new(
+                State::from(Ticket::MAX) << TICKET_SHIFT | State::from(Ticket::MAX),
+            ),
+            ..RawTicketMutex::INIT
+        };
+
+        mutex.lock();
+        assert_eq!(next_ticket(mutex.state.load(Ordering::Relaxed)), 0);
+        unsafe { mutex.unlock() };
+        assert_eq!(mutex.state.load(Ordering::Relaxed), 0);
+        assert!(mutex.try_lock());
+        assert_eq!(mutex.queue_depth(), 1);
+    }
 }
@@ -0,0 +1,132 @@
+use core::cell::UnsafeCell;
+use core::hint;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use lock_api::{GuardNoSend, RawMutex, RawMutexFair};
+
+use crate::stats::RawMutexSample;
+use crate::{local_irq_restore, local_irq_save, Flags};
+
+/// A mutex for single-core systems that only disables interrupts.
+///
+/// With the `uniprocessor` feature, this replaces [`RawSpinMutex`](crate::RawSpinMutex) and [`RawTicketMutex`](crate::RawTicketMutex).
+/// Since interrupts stay disabled while the mutex is locked, nothing else can run on the only CPU core.
+/// The locked state is thus only tracked with plain atomic loads and stores, without read-modify-write operations.
+///
+/// Contention can only come from reentrancy, which would deadlock.
+/// In debug builds, this panics instead.
+///
+/// This is only sound on single-core systems.
+pub struct RawUniprocessorMutex {
+    locked: AtomicBool,
+    irq_state: UnsafeCell<MaybeUninit<Flags>>,
+}
+
+// SAFETY: The `UnsafeCell` is guarded by `locked`, initialized on `lock` and read on `unlock`.
+unsafe impl Sync for RawUniprocessorMutex {}
+// SAFETY: Mutexes cannot be send to other threads while locked.
+// Sending them while unlocked is fine.
+unsafe impl Send for RawUniprocessorMutex {}
+
+impl RawUniprocessorMutex {
+    /// Marks this mutex as locked and saves `flags`.
+    #[inline]
+    fn acquire(&self, flags: Flags) {
+        // Disabling and restoring interrupts already orders the critical section on the only CPU core.
+        self.locked.store(true, Ordering::Relaxed);
+        // SAFETY: We have exclusive access, since interrupts are disabled and we just locked the mutex.
+        unsafe {
+            self.irq_state.get().write(MaybeUninit::new(flags));
+        }
+    }
+}
+
+unsafe impl RawMutex for RawUniprocessorMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        locked: AtomicBool::new(false),
+        irq_state: UnsafeCell::new(MaybeUninit::uninit()),
+    };
+
+    type GuardMarker = GuardNoSend;
+
+    #[inline]
+    #[track_caller]
+    fn lock(&self) {
+        let flags = local_irq_save();
+        debug_assert!(!self.is_locked(), "reentrant lock on a uniprocessor");
+        while self.is_locked() {
+            hint::spin_loop();
+        }
+        self.acquire(flags);
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        let flags = local_irq_save();
+        if self.is_locked() {
+            local_irq_restore(flags);
+            return false;
+        }
+        self.acquire(flags);
+        true
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        // SAFETY: We have exclusive access through holding the mutex, which initialized the state.
+        let flags = unsafe { self.irq_state.get().read().assume_init() };
+        self.locked.store(false, Ordering::Relaxed);
+        local_irq_restore(flags);
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+}
+
+unsafe impl RawMutexFair for RawUniprocessorMutex {
+    #[inline]
+    unsafe fn unlock_fair(&self) {
+        // SAFETY: The caller holds the mutex.
+        unsafe { self.unlock() }
+    }
+
+    #[inline]
+    unsafe fn bump(&self) {
+        // Restore interrupts so that pending interrupt handlers may run.
+        unsafe {
+            self.unlock();
+        }
+        self.lock();
+    }
+}
+
+impl RawMutexSample for RawUniprocessorMutex {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_lock_while_locked() {
+        let mutex = RawUniprocessorMutex::INIT;
+        mutex.lock();
+        assert!(!mutex.try_lock());
+        unsafe { mutex.unlock() };
+        assert!(mutex.try_lock());
+        unsafe { mutex.unlock() };
+        assert!(!mutex.is_locked());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic = "reentrant lock on a uniprocessor"]
+    fn reentrant_lock_panics() {
+        let mutex = RawUniprocessorMutex::INIT;
+        mutex.lock();
+        mutex.lock();
+    }
+}
@@ -0,0 +1,211 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::relax::{Backoff, Relax};
+use crate::{local_irq_restore, local_irq_save};
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+/// A cell that is initialized at most once by racing initializers.
+///
+/// In contrast to [`OnceCell`](crate::OnceCell), initializers are not serialized by a mutex.
+/// Multiple initializers may run concurrently and the first to finish publishes its value.
+/// The other initializers' values are dropped.
+/// This is useful if initialization cannot be done under a lock, for example, because it performs MMIO.
+///
+/// Publishing a value disables interrupts for a few instructions, so this cell can be used from interrupt handlers.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::DoubleCheckedCell;
+///
+/// static FREQUENCY: DoubleCheckedCell<u64> = DoubleCheckedCell::new();
+///
+/// fn measure_frequency() -> u64 {
+///     // Measure the frequency via MMIO here.
+///     1_000_000
+/// }
+///
+/// assert_eq!(*FREQUENCY.get_or_init(measure_frequency), 1_000_000);
+/// ```
+pub struct DoubleCheckedCell<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: The value is only written once by whoever moved the state to `WRITING`.
+unsafe impl<T: Send + Sync> Sync for DoubleCheckedCell<T> {}
+unsafe impl<T: Send> Send for DoubleCheckedCell<T> {}
+
+impl<T> DoubleCheckedCell<T> {
+    /// Creates a new, empty cell.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns a reference to the value if initialized.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == READY {
+            // SAFETY: The value has been published.
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the value if initialized.
+    #[inline]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state.get_mut() == READY {
+            // SAFETY: The value has been published.
+            Some(unsafe { self.value.get_mut().assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Publishes `value` if the cell is empty.
+    ///
+    /// If the cell has already been initialized or is being initialized, `value` is returned.
+    #[inline]
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let flags = local_irq_save();
+        let result =
+            self.state
+                .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Acquire);
+        if result.is_err() {
+            local_irq_restore(flags);
+            return Err(value);
+        }
+
+        // SAFETY: We have exclusive access through moving the state to `WRITING`.
+        unsafe {
+            (*self.value.get()).write(value);
+        }
+        self.state.store(READY, Ordering::Release);
+        local_irq_restore(flags);
+        Ok(())
+    }
+
+    /// Returns a reference to the value, initializing it with `f` if empty.
+    ///
+    /// If another initializer finishes first, the value returned by `f` is dropped.
+    #[inline]
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        if let Some(value) = self.get() {
+            return value;
+        }
+
+        let _ = self.set(f());
+
+        let mut backoff = Backoff::default();
+        loop {
+            if let Some(value) = self.get() {
+                return value;
+            }
+            // Another initializer is publishing its value.
+            backoff.relax();
+        }
+    }
+
+    /// Consumes the cell, returning the value if initialized.
+    #[inline]
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+
+    /// Takes the value out, leaving the cell empty.
+    #[inline]
+    pub fn take(&mut self) -> Option<T> {
+        if *self.state.get_mut() == READY {
+            *self.state.get_mut() = EMPTY;
+            // SAFETY: The value had been published and the state has been reset.
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for DoubleCheckedCell<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for DoubleCheckedCell<T> {
+    #[inline]
+    fn drop(&mut self) {
+        drop(self.take());
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for DoubleCheckedCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("DoubleCheckedCell");
+        match self.get() {
+            Some(value) => d.field(value),
+            None => d.field(&format_args!("<uninit>")),
+        };
+        d.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn first_completed_wins() {
+        const N: usize = 4;
+
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Value(usize);
+
+        impl Drop for Value {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let cell = DoubleCheckedCell::new();
+        let barrier = Barrier::new(N);
+
+        thread::scope(|s| {
+            for i in 0..N {
+                let cell = &cell;
+                let barrier = &barrier;
+                s.spawn(move || {
+                    let value = cell.get_or_init(|| {
+                        barrier.wait();
+                        Value(i)
+                    });
+                    assert!(value.0 < N);
+                });
+            }
+        });
+
+        assert_eq!(DROPPED.load(Ordering::Relaxed), N - 1);
+        drop(cell);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), N);
+    }
+}
@@ -0,0 +1,117 @@
+use core::mem;
+
+use generic_once_cell::{Lazy, OnceCell};
+use lock_api::RawMutex;
+
+use crate::relax::{Backoff, Relax};
+
+/// Extension methods for [`generic_once_cell::OnceCell`]s.
+pub trait OnceCellExt<T> {
+    /// Waits until another CPU core has initialized this cell and returns its value.
+    ///
+    /// This spins with [exponential backoff] and never runs an initializer.
+    /// This is intended for secondary CPU cores waiting for the boot CPU core to publish boot data.
+    ///
+    /// [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    ///
+    /// use hermit_sync::{OnceCell, OnceCellExt};
+    ///
+    /// static BOOT_INFO: OnceCell<usize> = OnceCell::new();
+    ///
+    /// thread::scope(|s| {
+    ///     let ap = s.spawn(|| *BOOT_INFO.wait());
+    ///     BOOT_INFO.set(42).unwrap();
+    ///     assert_eq!(ap.join().unwrap(), 42);
+    /// });
+    /// ```
+    fn wait(&self) -> &T;
+
+    /// Returns this cell to the uninitialized state, dropping its value.
+    ///
+    /// Use [`OnceCell::take`] to get the value instead.
+    /// This is intended for tearing down state before a soft reboot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::{OnceCell, OnceCellExt};
+    ///
+    /// let mut cell = OnceCell::with_value(42);
+    /// cell.reset();
+    /// assert_eq!(cell.get(), None);
+    /// cell.set(43).unwrap();
+    /// ```
+    fn reset(&mut self);
+}
+
+impl<R: RawMutex, T> OnceCellExt<T> for OnceCell<R, T> {
+    #[inline]
+    fn wait(&self) -> &T {
+        let mut backoff = Backoff::default();
+        loop {
+            if let Some(value) = self.get() {
+                return value;
+            }
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.take();
+    }
+}
+
+/// Extension methods for [`generic_once_cell::Lazy`]s.
+///
+/// Since the initializer is consumed on the first access, returning a lazy value to the uninitialized state requires a new initializer.
+pub trait LazyExt<T, F> {
+    /// Returns this lazy value to the uninitialized state with the initializer `f` and returns the previous value if it had been initialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::{Lazy, LazyExt};
+    ///
+    /// let mut lazy = Lazy::<usize>::new(|| 42);
+    /// assert_eq!(lazy.take(|| 43), None);
+    /// assert_eq!(*lazy, 43);
+    /// assert_eq!(lazy.take(|| 44), Some(43));
+    /// ```
+    fn take(&mut self, f: F) -> Option<T>;
+
+    /// Returns this lazy value to the uninitialized state with the initializer `f`, dropping the previous value.
+    ///
+    /// This is intended for tearing down state before a soft reboot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::{Lazy, LazyExt};
+    ///
+    /// let mut lazy = Lazy::<usize>::new(|| 42);
+    /// assert_eq!(*lazy, 42);
+    /// lazy.reset(|| 43);
+    /// assert_eq!(Lazy::get(&lazy), None);
+    /// assert_eq!(*lazy, 43);
+    /// ```
+    fn reset(&mut self, f: F);
+}
+
+impl<R: RawMutex, T, F> LazyExt<T, F> for Lazy<R, T, F> {
+    #[inline]
+    fn take(&mut self, f: F) -> Option<T> {
+        let lazy = mem::replace(self, Lazy::new(f));
+        Lazy::into_value(lazy).ok()
+    }
+
+    #[inline]
+    fn reset(&mut self, f: F) {
+        *self = Lazy::new(f);
+    }
+}
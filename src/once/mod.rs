@@ -0,0 +1,8 @@
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod double_checked;
+pub(crate) mod ext;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod race;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod racy_lazy;
+pub(crate) mod try_lazy;
@@ -0,0 +1,343 @@
+//! Lock-free cells that are initialized by racing initializers.
+//!
+//! These cells are initialized with a single compare-and-swap: the first store wins.
+//! They are modeled after [`once_cell::race`](https://docs.rs/once_cell/latest/once_cell/race/index.html).
+
+use core::marker::PhantomData;
+use core::num::NonZeroUsize;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use core::{fmt, ptr};
+
+/// A [`NonZeroUsize`] that is initialized at most once by racing initializers.
+///
+/// Multiple initializers may run concurrently and the first to store its value wins.
+///
+/// # Examples
+///
+/// ```
+/// use std::num::NonZeroUsize;
+///
+/// use hermit_sync::OnceNonZeroUsize;
+///
+/// static PAGE_SIZE: OnceNonZeroUsize = OnceNonZeroUsize::new();
+///
+/// let page_size = PAGE_SIZE.get_or_init(|| NonZeroUsize::new(4096).unwrap());
+/// assert_eq!(page_size.get(), 4096);
+/// ```
+#[derive(Default, Debug)]
+pub struct OnceNonZeroUsize {
+    inner: AtomicUsize,
+}
+
+impl OnceNonZeroUsize {
+    /// Creates a new, empty cell.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            inner: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the value if initialized.
+    #[inline]
+    pub fn get(&self) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(self.inner.load(Ordering::Acquire))
+    }
+
+    /// Stores `value` if the cell is empty.
+    ///
+    /// If the cell has already been initialized, `value` is returned.
+    #[inline]
+    pub fn set(&self, value: NonZeroUsize) -> Result<(), NonZeroUsize> {
+        self.inner
+            .compare_exchange(0, value.get(), Ordering::AcqRel, Ordering::Acquire)
+            .map(drop)
+            .map_err(|_| value)
+    }
+
+    /// Returns the value, initializing it with `f` if empty.
+    ///
+    /// If another initializer stores its value first, the value returned by `f` is discarded.
+    #[inline]
+    pub fn get_or_init<F>(&self, f: F) -> NonZeroUsize
+    where
+        F: FnOnce() -> NonZeroUsize,
+    {
+        match self.get_or_try_init(|| Ok::<_, core::convert::Infallible>(f())) {
+            Ok(value) => value,
+            Err(err) => match err {},
+        }
+    }
+
+    /// Returns the value, initializing it with `f` if empty.
+    ///
+    /// If `f` fails, the cell stays empty and the error is returned.
+    #[inline]
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<NonZeroUsize, E>
+    where
+        F: FnOnce() -> Result<NonZeroUsize, E>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+
+        let value = f()?;
+        match self
+            .inner
+            .compare_exchange(0, value.get(), Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => Ok(value),
+            // SAFETY: Only non-zero values are stored.
+            Err(winner) => Ok(unsafe { NonZeroUsize::new_unchecked(winner) }),
+        }
+    }
+}
+
+/// A [`bool`] that is initialized at most once by racing initializers.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::OnceBool;
+///
+/// static HAS_X2APIC: OnceBool = OnceBool::new();
+///
+/// assert!(!HAS_X2APIC.get_or_init(|| false));
+/// assert_eq!(HAS_X2APIC.set(true), Err(true));
+/// ```
+#[derive(Default, Debug)]
+pub struct OnceBool {
+    inner: OnceNonZeroUsize,
+}
+
+impl OnceBool {
+    /// Creates a new, empty cell.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            inner: OnceNonZeroUsize::new(),
+        }
+    }
+
+    /// Returns the value if initialized.
+    #[inline]
+    pub fn get(&self) -> Option<bool> {
+        self.inner.get().map(Self::from_usize)
+    }
+
+    /// Stores `value` if the cell is empty.
+    ///
+    /// If the cell has already been initialized, `value` is returned.
+    #[inline]
+    pub fn set(&self, value: bool) -> Result<(), bool> {
+        self.inner.set(Self::to_usize(value)).map_err(|_| value)
+    }
+
+    /// Returns the value, initializing it with `f` if empty.
+    ///
+    /// If another initializer stores its value first, the value returned by `f` is discarded.
+    #[inline]
+    pub fn get_or_init<F>(&self, f: F) -> bool
+    where
+        F: FnOnce() -> bool,
+    {
+        Self::from_usize(self.inner.get_or_init(|| Self::to_usize(f())))
+    }
+
+    /// Returns the value, initializing it with `f` if empty.
+    ///
+    /// If `f` fails, the cell stays empty and the error is returned.
+    #[inline]
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<bool, E>
+    where
+        F: FnOnce() -> Result<bool, E>,
+    {
+        self.inner
+            .get_or_try_init(|| f().map(Self::to_usize))
+            .map(Self::from_usize)
+    }
+
+    #[inline]
+    fn from_usize(value: NonZeroUsize) -> bool {
+        value.get() == 1
+    }
+
+    #[inline]
+    fn to_usize(value: bool) -> NonZeroUsize {
+        if value {
+            NonZeroUsize::MIN
+        } else {
+            NonZeroUsize::MAX
+        }
+    }
+}
+
+/// A reference that is initialized at most once by racing initializers.
+///
+/// This is intended for `&'static` references discovered during boot, such as firmware tables.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::OnceRef;
+///
+/// struct Ops {
+///     send_ipi: fn(usize),
+/// }
+///
+/// static APIC_OPS: Ops = Ops { send_ipi: |_cpu| {} };
+/// static OPS: OnceRef<'static, Ops> = OnceRef::new();
+///
+/// assert!(OPS.set(&APIC_OPS).is_ok());
+/// (OPS.get().unwrap().send_ipi)(1);
+/// ```
+pub struct OnceRef<'a, T> {
+    inner: AtomicPtr<T>,
+    _marker: PhantomData<&'a T>,
+}
+
+// SAFETY: This only hands out `&'a T`, like `&'a T` itself.
+unsafe impl<T: Sync> Sync for OnceRef<'_, T> {}
+unsafe impl<T: Sync> Send for OnceRef<'_, T> {}
+
+impl<'a, T> OnceRef<'a, T> {
+    /// Creates a new, empty cell.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            inner: AtomicPtr::new(ptr::null_mut()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the reference if initialized.
+    #[inline]
+    pub fn get(&self) -> Option<&'a T> {
+        let ptr = self.inner.load(Ordering::Acquire);
+        // SAFETY: Only `&'a T` are stored in `inner`.
+        unsafe { ptr.as_ref() }
+    }
+
+    /// Stores `value` if the cell is empty.
+    ///
+    /// If the cell has already been initialized, `value` is returned.
+    #[inline]
+    pub fn set(&self, value: &'a T) -> Result<(), &'a T> {
+        self.inner
+            .compare_exchange(
+                ptr::null_mut(),
+                ptr::from_ref(value).cast_mut(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .map(drop)
+            .map_err(|_| value)
+    }
+
+    /// Returns the reference, initializing it with `f` if empty.
+    ///
+    /// If another initializer stores its reference first, the reference returned by `f` is discarded.
+    #[inline]
+    pub fn get_or_init<F>(&self, f: F) -> &'a T
+    where
+        F: FnOnce() -> &'a T,
+    {
+        match self.get_or_try_init(|| Ok::<_, core::convert::Infallible>(f())) {
+            Ok(value) => value,
+            Err(err) => match err {},
+        }
+    }
+
+    /// Returns the reference, initializing it with `f` if empty.
+    ///
+    /// If `f` fails, the cell stays empty and the error is returned.
+    #[inline]
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<&'a T, E>
+    where
+        F: FnOnce() -> Result<&'a T, E>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+
+        let value = f()?;
+        match self.inner.compare_exchange(
+            ptr::null_mut(),
+            ptr::from_ref(value).cast_mut(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Ok(value),
+            // SAFETY: Only `&'a T` are stored in `inner`.
+            Err(winner) => Ok(unsafe { &*winner }),
+        }
+    }
+}
+
+impl<T> Default for OnceRef<'_, T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("OnceRef");
+        match self.get() {
+            Some(value) => d.field(value),
+            None => d.field(&format_args!("<uninit>")),
+        };
+        d.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn first_store_wins() {
+        const N: usize = 4;
+
+        static VALUES: [usize; N] = [0, 1, 2, 3];
+
+        let cell = OnceRef::new();
+        let barrier = Barrier::new(N);
+
+        let winners = thread::scope(|s| {
+            let handles = VALUES
+                .iter()
+                .map(|value| {
+                    let cell = &cell;
+                    let barrier = &barrier;
+                    s.spawn(move || {
+                        cell.get_or_init(|| {
+                            barrier.wait();
+                            value
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| *handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let winner = *cell.get().unwrap();
+        assert!(winners.iter().all(|&value| value == winner));
+    }
+
+    #[test]
+    fn failed_init_stays_empty() {
+        let cell = OnceBool::new();
+        assert_eq!(cell.get_or_try_init(|| Err(())), Err(()));
+        assert_eq!(cell.get(), None);
+        assert_eq!(cell.get_or_try_init(|| Ok::<_, ()>(false)), Ok(false));
+        assert_eq!(cell.get(), Some(false));
+    }
+}
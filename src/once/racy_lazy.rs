@@ -0,0 +1,114 @@
+use core::fmt;
+use core::ops::Deref;
+
+use crate::DoubleCheckedCell;
+
+/// A value that is initialized on the first access by racing initializers.
+///
+/// In contrast to [`Lazy`](crate::Lazy), concurrent first accesses do not wait for each other.
+/// Each of them may run the initializer and the first to finish publishes its value.
+/// The other values are dropped.
+/// Thus, the initializer has to be [`Fn`], and should be cheap and idempotent, such as feature detection or calibration constants.
+///
+/// This is built on [`DoubleCheckedCell`].
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::RacyLazy;
+///
+/// static CACHE_LINE_SIZE: RacyLazy<usize> = RacyLazy::new(|| {
+///     // Query CPUID here.
+///     64
+/// });
+///
+/// assert_eq!(*CACHE_LINE_SIZE, 64);
+/// ```
+pub struct RacyLazy<T, F = fn() -> T> {
+    cell: DoubleCheckedCell<T>,
+    init: F,
+}
+
+impl<T, F> RacyLazy<T, F> {
+    /// Creates a new lazy value with the given initializer.
+    #[inline]
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: DoubleCheckedCell::new(),
+            init,
+        }
+    }
+
+    /// Returns a reference to the value if initialized.
+    ///
+    /// This never runs the initializer.
+    #[inline]
+    pub fn get(this: &Self) -> Option<&T> {
+        this.cell.get()
+    }
+
+    /// Returns a mutable reference to the value if initialized.
+    #[inline]
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        this.cell.get_mut()
+    }
+}
+
+impl<T, F: Fn() -> T> RacyLazy<T, F> {
+    /// Forces the evaluation of this lazy value and returns a reference to the result.
+    ///
+    /// This is equivalent to the `Deref` impl, but is explicit.
+    #[inline]
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(&this.init)
+    }
+}
+
+impl<T, F: Fn() -> T> Deref for RacyLazy<T, F> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        Self::force(self)
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for RacyLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RacyLazy")
+            .field("cell", &self.cell)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn racing_initializers() {
+        const N: usize = 4;
+
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        let barrier = Barrier::new(N);
+        let lazy = RacyLazy::new(|| {
+            barrier.wait();
+            RUNS.fetch_add(1, Ordering::Relaxed)
+        });
+
+        thread::scope(|s| {
+            for _ in 0..N {
+                s.spawn(|| assert!(*lazy < N));
+            }
+        });
+
+        let value = *RacyLazy::get(&lazy).unwrap();
+        assert_eq!(RUNS.load(Ordering::Relaxed), N);
+        assert_eq!(*lazy, value);
+    }
+}
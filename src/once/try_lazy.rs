@@ -0,0 +1,124 @@
+use core::cell::Cell;
+use core::fmt;
+
+use generic_once_cell::OnceCell;
+use lock_api::RawMutex;
+
+use crate::{RawInterruptMutex, RawSpinMutex};
+
+/// A value with a fallible initializer that is run on the first access.
+///
+/// In contrast to [`Lazy`](crate::Lazy), the initializer may fail.
+/// Both the value and the error are cached, so the initializer is run at most once.
+/// This is intended for hardware resources that may legitimately be absent, such as optional devices.
+///
+/// Initialization is serialized via the [`RawMutex`] `R`.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::TryLazy;
+///
+/// static HPET: TryLazy<usize, &str> = TryLazy::new(|| Err("no HPET table"));
+///
+/// assert_eq!(HPET.force(), Err(&"no HPET table"));
+/// assert_eq!(HPET.get(), Some(Err(&"no HPET table")));
+/// ```
+pub struct TryLazy<T, E, F = fn() -> Result<T, E>, R = RawSpinMutex> {
+    cell: OnceCell<R, Result<T, E>>,
+    init: Cell<Option<F>>,
+}
+
+// SAFETY: `init` is only accessed while initializing `cell`, which is serialized by `R`.
+unsafe impl<T, E, F: Send, R> Sync for TryLazy<T, E, F, R> where OnceCell<R, Result<T, E>>: Sync {}
+
+impl<T, E, F, R: RawMutex> TryLazy<T, E, F, R> {
+    /// Creates a new lazy value with the given fallible initializer.
+    #[inline]
+    pub const fn new(f: F) -> Self {
+        Self {
+            cell: OnceCell::new(),
+            init: Cell::new(Some(f)),
+        }
+    }
+
+    /// Returns the result of the initializer if it has already been run.
+    ///
+    /// This never runs the initializer.
+    #[inline]
+    pub fn get(&self) -> Option<Result<&T, &E>> {
+        self.cell.get().map(Result::as_ref)
+    }
+
+    /// Returns a mutable reference to the result of the initializer if it has already been run.
+    #[inline]
+    pub fn get_mut(&mut self) -> Option<Result<&mut T, &mut E>> {
+        self.cell.get_mut().map(Result::as_mut)
+    }
+
+    /// Consumes this lazy value, returning the result of the initializer if it has already been run.
+    #[inline]
+    pub fn into_inner(self) -> Option<Result<T, E>> {
+        self.cell.into_inner()
+    }
+}
+
+impl<T, E, F: FnOnce() -> Result<T, E>, R: RawMutex> TryLazy<T, E, F, R> {
+    /// Runs the initializer if it has not been run yet and returns its result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initializer panicked on a previous access.
+    #[inline]
+    pub fn force(&self) -> Result<&T, &E> {
+        self.cell
+            .get_or_init(|| match self.init.take() {
+                Some(f) => f(),
+                None => panic!("TryLazy instance has previously been poisoned"),
+            })
+            .as_ref()
+    }
+}
+
+impl<T: fmt::Debug, E: fmt::Debug, F, R: RawMutex> fmt::Debug for TryLazy<T, E, F, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TryLazy")
+            .field("cell", &self.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`TryLazy`], initialized using [`RawInterruptMutex`]`<R>`.
+///
+/// By default, this is initialized using [`RawInterruptSpinMutex`](crate::RawInterruptSpinMutex).
+pub type InterruptTryLazy<T, E, F = fn() -> Result<T, E>, R = RawSpinMutex> =
+    TryLazy<T, E, F, RawInterruptMutex<R>>;
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn caches_error() {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        let lazy = InterruptTryLazy::<usize, &str>::new(|| {
+            RUNS.fetch_add(1, Ordering::Relaxed);
+            Err("absent")
+        });
+        assert_eq!(lazy.get(), None);
+        assert_eq!(lazy.force(), Err(&"absent"));
+        assert_eq!(lazy.force(), Err(&"absent"));
+        assert_eq!(RUNS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn caches_value() {
+        let mut lazy = TryLazy::<_, (), _>::new(|| Ok(1));
+        assert_eq!(lazy.force(), Ok(&1));
+        *lazy.get_mut().unwrap().unwrap() += 1;
+        assert_eq!(lazy.into_inner(), Some(Ok(2)));
+    }
+}
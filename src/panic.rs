@@ -0,0 +1,252 @@
+//! Debugging aids for panic handlers.
+//!
+//! This module requires the `held-locks` feature.
+//! With it, [`RawOwnedMutex`](crate::RawOwnedMutex)es record themselves in a fixed-size registry while held.
+//! [`dump_held_locks`] lists the held locks per CPU core with their names and acquisition sites.
+//! Since recording touches shared state on every lock and unlock, this is intended for debugging.
+//!
+//! # Examples
+//!
+//! ```
+//! use hermit_sync::{panic, OwnedMutex, RawSpinMutex};
+//!
+//! static RUN_QUEUE: OwnedMutex<RawSpinMutex, ()> = OwnedMutex::new(());
+//!
+//! panic::set_lock_name(&RUN_QUEUE, "RUN_QUEUE");
+//!
+//! let guard = RUN_QUEUE.lock();
+//! let mut output = String::new();
+//! panic::dump_held_locks(&mut output).unwrap();
+//! assert!(output.contains("RUN_QUEUE"));
+//! drop(guard);
+//! ```
+
+use core::panic::Location;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use core::{fmt, ptr, slice, str};
+
+use lock_api::Mutex;
+
+use crate::RawMutexOwned;
+
+/// The maximum number of locks that can be recorded as held at the same time.
+///
+/// Further locks are counted and reported by [`dump_held_locks`] without details.
+pub const MAX_HELD_LOCKS: usize = 64;
+
+/// The maximum number of locks that can be named.
+pub const MAX_LOCK_NAMES: usize = 32;
+
+const EMPTY: usize = 0;
+
+struct HeldLock {
+    lock: AtomicUsize,
+    core_id: AtomicUsize,
+    location: AtomicPtr<Location<'static>>,
+}
+
+impl HeldLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Self = Self {
+        lock: AtomicUsize::new(EMPTY),
+        core_id: AtomicUsize::new(0),
+        location: AtomicPtr::new(ptr::null_mut()),
+    };
+}
+
+struct LockName {
+    lock: AtomicUsize,
+    name: AtomicPtr<u8>,
+    len: AtomicUsize,
+}
+
+impl LockName {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Self = Self {
+        lock: AtomicUsize::new(EMPTY),
+        name: AtomicPtr::new(ptr::null_mut()),
+        len: AtomicUsize::new(0),
+    };
+}
+
+static HELD_LOCKS: [HeldLock; MAX_HELD_LOCKS] = [HeldLock::EMPTY; MAX_HELD_LOCKS];
+static LOCK_NAMES: [LockName; MAX_LOCK_NAMES] = [LockName::EMPTY; MAX_LOCK_NAMES];
+
+/// The number of held locks that did not fit into [`HELD_LOCKS`].
+static UNRECORDED_LOCKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Records `lock` as held by `core_id` and returns its slot.
+///
+/// If the registry is full, the lock is only counted and `None` is returned.
+pub(crate) fn record_acquire(
+    lock: usize,
+    core_id: usize,
+    location: &'static Location<'static>,
+) -> Option<usize> {
+    for (slot, held) in HELD_LOCKS.iter().enumerate() {
+        if held
+            .lock
+            .compare_exchange(EMPTY, lock, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            held.core_id.store(core_id, Ordering::Relaxed);
+            held.location
+                .store(ptr::from_ref(location).cast_mut(), Ordering::Release);
+            return Some(slot);
+        }
+    }
+
+    UNRECORDED_LOCKS.fetch_add(1, Ordering::Relaxed);
+    None
+}
+
+/// Removes the record returned by [`record_acquire`].
+pub(crate) fn record_release(slot: Option<usize>) {
+    match slot {
+        Some(slot) => {
+            let held = &HELD_LOCKS[slot];
+            held.location.store(ptr::null_mut(), Ordering::Relaxed);
+            held.lock.store(EMPTY, Ordering::Release);
+        }
+        None => {
+            UNRECORDED_LOCKS.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Names a lock for [`dump_held_locks`].
+///
+/// `name` is usually the name of the static holding the lock.
+/// Since locks are identified by their address, only `'static` locks can be named.
+/// Naming a lock again has no effect and keeps its first name.
+/// If [`MAX_LOCK_NAMES`] locks have already been named, the lock stays unnamed.
+pub fn set_lock_name<R: RawMutexOwned, T: ?Sized>(mutex: &'static Mutex<R, T>, name: &'static str) {
+    // SAFETY: We only take the address of the raw mutex.
+    let lock = ptr::from_ref(unsafe { mutex.raw() }) as usize;
+    for entry in &LOCK_NAMES {
+        let claimed = entry
+            .lock
+            .compare_exchange(EMPTY, lock, Ordering::AcqRel, Ordering::Acquire);
+        if claimed == Err(lock) {
+            // The name and length must not change once published.
+            return;
+        }
+        if claimed.is_ok() {
+            entry.len.store(name.len(), Ordering::Relaxed);
+            entry
+                .name
+                .store(name.as_ptr().cast_mut(), Ordering::Release);
+            return;
+        }
+    }
+}
+
+fn lock_name(lock: usize) -> Option<&'static str> {
+    LOCK_NAMES.iter().find_map(|entry| {
+        if entry.lock.load(Ordering::Acquire) != lock {
+            return None;
+        }
+
+        let name = entry.name.load(Ordering::Acquire);
+        if name.is_null() {
+            return None;
+        }
+        let len = entry.len.load(Ordering::Relaxed);
+        // SAFETY: `name` and `len` were stored once from a `&'static str` before publishing `name`.
+        Some(unsafe { str::from_utf8_unchecked(slice::from_raw_parts(name, len)) })
+    })
+}
+
+/// Returns the smallest ID of a CPU core holding locks that is greater than `after`.
+fn next_core_id(after: Option<usize>) -> Option<usize> {
+    HELD_LOCKS
+        .iter()
+        .filter(|held| held.lock.load(Ordering::Acquire) != EMPTY)
+        .map(|held| held.core_id.load(Ordering::Relaxed))
+        .filter(|&core_id| after.is_none_or(|after| core_id > after))
+        .min()
+}
+
+/// Writes the locks currently held per CPU core to `writer`.
+///
+/// Only [`RawOwnedMutex`](crate::RawOwnedMutex)es are recorded.
+/// If more than [`MAX_HELD_LOCKS`] locks are held, the number of unrecorded locks is written last.
+/// This is intended for panic handlers and does not lock anything.
+/// Since locks might be acquired and released concurrently, the output is best-effort.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::panic::{self, MAX_HELD_LOCKS};
+/// use hermit_sync::{OwnedMutex, RawSpinMutex};
+///
+/// let mutexes = [const { OwnedMutex::<RawSpinMutex, ()>::new(()) }; MAX_HELD_LOCKS + 2];
+/// let guards = mutexes.iter().map(|mutex| mutex.lock()).collect::<Vec<_>>();
+///
+/// let mut output = String::new();
+/// panic::dump_held_locks(&mut output).unwrap();
+/// assert!(output.ends_with("2 more locks held but not recorded\n"));
+///
+/// drop(guards);
+/// let mut output = String::new();
+/// panic::dump_held_locks(&mut output).unwrap();
+/// assert_eq!(output, "no locks held\n");
+/// ```
+pub fn dump_held_locks(writer: &mut dyn fmt::Write) -> fmt::Result {
+    let unrecorded = UNRECORDED_LOCKS.load(Ordering::Relaxed);
+    let mut next = next_core_id(None);
+    if next.is_none() && unrecorded == 0 {
+        return writeln!(writer, "no locks held");
+    }
+
+    while let Some(core_id) = next {
+        writeln!(writer, "locks held by CPU core {core_id}:")?;
+        for held in &HELD_LOCKS {
+            let lock = held.lock.load(Ordering::Acquire);
+            if lock == EMPTY || held.core_id.load(Ordering::Relaxed) != core_id {
+                continue;
+            }
+
+            write!(writer, "  {lock:#x}")?;
+            if let Some(name) = lock_name(lock) {
+                write!(writer, " ({name})")?;
+            }
+            let location = held.location.load(Ordering::Acquire);
+            // SAFETY: Only `&'static Location<'static>` are stored in `location`.
+            if let Some(location) = unsafe { location.as_ref() } {
+                write!(writer, " acquired at {location}")?;
+            }
+            writeln!(writer)?;
+        }
+
+        next = next_core_id(Some(core_id));
+    }
+
+    if unrecorded > 0 {
+        writeln!(writer, "{unrecorded} more locks held but not recorded")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OwnedMutex, RawSpinMutex};
+
+    #[test]
+    fn acquisition_site() {
+        static MUTEX: OwnedMutex<RawSpinMutex, ()> = OwnedMutex::new(());
+        set_lock_name(&MUTEX, "MUTEX");
+
+        let guard = MUTEX.lock();
+        let line = line!() - 1;
+        let mut output = String::new();
+        dump_held_locks(&mut output).unwrap();
+        assert!(output.contains(&format!("(MUTEX) acquired at {}:{line}:", file!())));
+
+        drop(guard);
+        let mut output = String::new();
+        dump_held_locks(&mut output).unwrap();
+        assert!(!output.contains("(MUTEX)"));
+    }
+}
@@ -0,0 +1,114 @@
+use core::fmt;
+
+use crate::{CoreIdProvider, InterruptGuard, OnceCell, PerCpu, RegisteredCoreId, MAX_CPUS};
+
+/// A value per CPU core that each CPU core initializes on its first access.
+///
+/// This combines [`PerCpu`] with [`Lazy`](crate::Lazy).
+/// The initializer is run once per CPU core, on that CPU core, with disabled interrupts.
+/// Thus, it has to be [`Fn`] and may read CPU-local state, for example, for per-core allocators or RNG state.
+///
+/// # Panics
+///
+/// Accessing the current value panics if `C` cannot determine the current CPU core, like with [`PerCpu`].
+/// It also panics if the current core ID is not less than `N`.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::CoreLocal;
+///
+/// hermit_sync::set_core_id_provider(|| 0);
+///
+/// static CORE_NAME: CoreLocal<String> = CoreLocal::new(|| format!("cpu{}", hermit_sync::core_id()));
+///
+/// assert_eq!(CORE_NAME.get(), "cpu0");
+/// ```
+pub struct CoreLocal<T, F = fn() -> T, const N: usize = MAX_CPUS, C = RegisteredCoreId> {
+    cells: PerCpu<OnceCell<T>, N, C>,
+    init: F,
+}
+
+impl<T, F, const N: usize, C> CoreLocal<T, F, N, C> {
+    /// Creates a new per-CPU lazy value with the given initializer.
+    #[inline]
+    pub const fn new(init: F) -> Self {
+        Self {
+            cells: PerCpu::new([const { OnceCell::new() }; N]),
+            init,
+        }
+    }
+
+    /// Returns the value of `cpu` if it has already been initialized.
+    ///
+    /// This never runs the initializer.
+    #[inline]
+    pub fn get_cpu(&self, cpu: usize) -> Option<&T> {
+        self.cells.get_cpu(cpu)?.get()
+    }
+
+    /// Returns an iterator over the initialized values of all CPU cores.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter().filter_map(OnceCell::get)
+    }
+}
+
+impl<T, F: Fn() -> T, const N: usize, C: CoreIdProvider> CoreLocal<T, F, N, C> {
+    /// Returns the value of the current CPU core, initializing it if necessary.
+    ///
+    /// The current task may be migrated to another CPU core while holding the returned reference.
+    #[inline]
+    pub fn get(&self) -> &T {
+        let guard = InterruptGuard::disable();
+        let value = self.cells.get().get_or_init(&self.init);
+        drop(guard);
+        value
+    }
+
+    /// Runs `f` with the value of the current CPU core and disabled interrupts, initializing it if necessary.
+    #[inline]
+    pub fn with<G, R>(&self, f: G) -> R
+    where
+        G: FnOnce(&T) -> R,
+    {
+        let guard = InterruptGuard::disable();
+        let ret = f(self.cells.get().get_or_init(&self.init));
+        drop(guard);
+        ret
+    }
+}
+
+impl<T: fmt::Debug, F, const N: usize, C> fmt::Debug for CoreLocal<T, F, N, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoreLocal")
+            .field("cells", &self.cells)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::percpu::per_cpu::ThreadCpu;
+
+    #[test]
+    fn initializes_per_core() {
+        let local = CoreLocal::<_, _, 4, ThreadCpu>::new(|| ThreadCpu::core_id() * 10);
+        thread::scope(|s| {
+            for cpu in [1, 3] {
+                let local = &local;
+                s.spawn(move || {
+                    ThreadCpu::set(cpu);
+                    assert_eq!(*local.get(), cpu * 10);
+                    assert_eq!(local.with(|value| *value), cpu * 10);
+                });
+            }
+        });
+        assert_eq!(local.get_cpu(0), None);
+        assert_eq!(local.get_cpu(3), Some(&30));
+        assert_eq!(local.iter().copied().collect::<Vec<_>>(), [10, 30]);
+    }
+}
@@ -0,0 +1,146 @@
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{CoreIdProvider, PerCpu, RegisteredCoreId, MAX_CPUS};
+
+/// A counter with a slot per CPU core.
+///
+/// Updates only touch the slot of the current CPU core, so hot counters, such as interrupt counts or allocator statistics, do not bounce cache lines between CPU cores.
+/// Reading [`sum`](Self::sum) is expensive, since it visits all `N` slots.
+///
+/// Counts wrap around on overflow.
+/// Since the sum is computed with wrapping arithmetic, decrementing on another CPU core than incrementing is fine.
+///
+/// # Panics
+///
+/// Updating the counter panics if `C` cannot determine the current CPU core, like with [`PerCpu`].
+/// It also panics if the current core ID is not less than `N`.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::PerCpuCounter;
+///
+/// hermit_sync::set_core_id_provider(|| 0);
+///
+/// static IRQ_COUNT: PerCpuCounter<4> = PerCpuCounter::new();
+///
+/// IRQ_COUNT.inc();
+/// IRQ_COUNT.add(2);
+/// assert_eq!(IRQ_COUNT.sum(), 3);
+/// ```
+pub struct PerCpuCounter<const N: usize = MAX_CPUS, C = RegisteredCoreId> {
+    counts: PerCpu<AtomicUsize, N, C>,
+}
+
+impl<const N: usize, C> PerCpuCounter<N, C> {
+    /// Creates a new counter with all slots set to zero.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            counts: PerCpu::new([const { AtomicUsize::new(0) }; N]),
+        }
+    }
+
+    /// Returns a snapshot of the sum of all slots.
+    ///
+    /// Updates that happen concurrently may or may not be included.
+    #[inline]
+    pub fn sum(&self) -> usize {
+        self.counts.iter().fold(0, |sum, count| {
+            sum.wrapping_add(count.load(Ordering::Relaxed))
+        })
+    }
+
+    /// Returns the count of `cpu`.
+    ///
+    /// Returns `None` if `cpu` is not less than `N`.
+    #[inline]
+    pub fn get_cpu(&self, cpu: usize) -> Option<usize> {
+        self.counts
+            .get_cpu(cpu)
+            .map(|count| count.load(Ordering::Relaxed))
+    }
+
+    /// Sets all slots to zero.
+    #[inline]
+    pub fn reset(&mut self) {
+        for cpu in 0..N {
+            if let Some(count) = self.counts.get_cpu_mut(cpu) {
+                *count.get_mut() = 0;
+            }
+        }
+    }
+}
+
+impl<const N: usize, C: CoreIdProvider> PerCpuCounter<N, C> {
+    /// Adds one to the slot of the current CPU core.
+    #[inline]
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Adds `n` to the slot of the current CPU core.
+    #[inline]
+    pub fn add(&self, n: usize) {
+        self.counts.get().fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Subtracts `n` from the slot of the current CPU core.
+    #[inline]
+    pub fn sub(&self, n: usize) {
+        self.counts.get().fetch_sub(n, Ordering::Relaxed);
+    }
+}
+
+impl<const N: usize, C> Default for PerCpuCounter<N, C> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, C> fmt::Debug for PerCpuCounter<N, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PerCpuCounter")
+            .field("sum", &self.sum())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::percpu::per_cpu::ThreadCpu;
+
+    #[test]
+    fn sums_slots() {
+        let counter = PerCpuCounter::<4, ThreadCpu>::new();
+        thread::scope(|s| {
+            for cpu in 0..4 {
+                let counter = &counter;
+                s.spawn(move || {
+                    ThreadCpu::set(cpu);
+                    for _ in 0..100 {
+                        counter.inc();
+                    }
+                });
+            }
+        });
+        assert_eq!(counter.sum(), 400);
+        assert_eq!(counter.get_cpu(3), Some(100));
+
+        counter.sub(401);
+        assert_eq!(counter.sum(), usize::MAX);
+    }
+
+    #[test]
+    #[should_panic = "PerCpu accessed before the current CPU core is known"]
+    fn unknown_core_id() {
+        // No core ID provider is registered in unit tests.
+        let counter = PerCpuCounter::<4>::new();
+        counter.inc();
+    }
+}
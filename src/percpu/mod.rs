@@ -0,0 +1,6 @@
+pub(crate) mod core_local;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod counter;
+pub(crate) mod per_cpu;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) mod refcount;
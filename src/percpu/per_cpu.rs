@@ -0,0 +1,273 @@
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::{array, fmt, slice};
+
+use crate::cpu::registered_core_id;
+use crate::{core_id, InterruptGuard, MAX_CPUS};
+
+/// A way of determining the current CPU core for [`PerCpu`], [`BrLock`](crate::BrLock), and [`RawOwnedMutex`](crate::RawOwnedMutex).
+pub trait CoreIdProvider {
+    /// Returns the ID of the current CPU core.
+    ///
+    /// IDs must be unique per CPU core and stable while interrupts are disabled.
+    fn core_id() -> usize;
+
+    /// Returns the ID of the current CPU core, or `None` if it cannot be determined yet.
+    ///
+    /// By default, this returns [`core_id`](Self::core_id).
+    #[inline]
+    fn try_core_id() -> Option<usize> {
+        Some(Self::core_id())
+    }
+}
+
+/// The [`CoreIdProvider`] registered via [`set_core_id_provider`](crate::set_core_id_provider).
+///
+/// Until a provider is registered, [`try_core_id`](CoreIdProvider::try_core_id) returns `None`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RegisteredCoreId;
+
+impl CoreIdProvider for RegisteredCoreId {
+    #[inline]
+    fn core_id() -> usize {
+        core_id()
+    }
+
+    #[inline]
+    fn try_core_id() -> Option<usize> {
+        registered_core_id()
+    }
+}
+
+#[cfg(test)]
+std::thread_local! {
+    static THREAD_CPU: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/// A [`CoreIdProvider`] for tests that treats each thread as a CPU core with a settable ID.
+#[cfg(test)]
+pub(crate) struct ThreadCpu;
+
+#[cfg(test)]
+impl ThreadCpu {
+    /// Sets the core ID of the current thread.
+    pub(crate) fn set(cpu: usize) {
+        THREAD_CPU.set(cpu);
+    }
+}
+
+#[cfg(test)]
+impl CoreIdProvider for ThreadCpu {
+    fn core_id() -> usize {
+        THREAD_CPU.get()
+    }
+}
+
+/// A per-CPU slot on its own cache line.
+#[derive(Debug)]
+#[repr(align(64))]
+struct Slot<T> {
+    value: T,
+}
+
+/// A value per CPU core.
+///
+/// Each of the `N` CPU cores gets its own slot on its own cache line, selected via [`CoreIdProvider`] `C`.
+/// Slots are accessed by shared reference, so mutable per-CPU data needs interior mutability, such as atomics.
+///
+/// [`with`](Self::with) disables interrupts while accessing the slot, so that the current task is not migrated to another CPU core meanwhile.
+/// [`get`](Self::get) does not, so the returned slot may belong to another CPU core by the time it is used.
+///
+/// # Panics
+///
+/// Accessing the current slot panics if `C` cannot determine the current CPU core, for example, because no provider has been registered via [`set_core_id_provider`](crate::set_core_id_provider) or [`init_smp`](crate::init_smp).
+/// Otherwise, every CPU core would share the slot of CPU core 0.
+/// It also panics if the current core ID is not less than `N`.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use hermit_sync::PerCpu;
+///
+/// fn core_id() -> usize {
+///     // Read the core ID from a CPU-local register here.
+///     0
+/// }
+///
+/// hermit_sync::set_core_id_provider(core_id);
+///
+/// static TICKS: PerCpu<AtomicUsize, 4> = PerCpu::new([const { AtomicUsize::new(0) }; 4]);
+///
+/// TICKS.with(|ticks| ticks.fetch_add(1, Ordering::Relaxed));
+/// let total = TICKS.iter().map(|ticks| ticks.load(Ordering::Relaxed)).sum::<usize>();
+/// assert_eq!(total, 1);
+/// ```
+pub struct PerCpu<T, const N: usize = MAX_CPUS, C = RegisteredCoreId> {
+    slots: [Slot<T>; N],
+    _core_id: PhantomData<fn() -> C>,
+}
+
+impl<T, const N: usize, C> PerCpu<T, N, C> {
+    /// Creates a new per-CPU value with `values[cpu]` in the slot of each CPU core.
+    #[inline]
+    pub const fn new(values: [T; N]) -> Self {
+        let values = MaybeUninit::new(values);
+        let values = values.as_ptr().cast::<T>();
+        let mut slots = [const { MaybeUninit::<Slot<T>>::uninit() }; N];
+        let mut cpu = 0;
+        while cpu < N {
+            // SAFETY: Each value is moved out exactly once and `values` is never dropped.
+            let value = unsafe { values.add(cpu).read() };
+            slots[cpu] = MaybeUninit::new(Slot { value });
+            cpu += 1;
+        }
+        // SAFETY: All slots have been initialized.
+        let slots = unsafe { slots.as_ptr().cast::<[Slot<T>; N]>().read() };
+        Self {
+            slots,
+            _core_id: PhantomData,
+        }
+    }
+
+    /// Creates a new per-CPU value, initializing each CPU core's slot with `f(cpu)`.
+    #[inline]
+    pub fn from_fn<F>(mut f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        Self {
+            slots: array::from_fn(|cpu| Slot { value: f(cpu) }),
+            _core_id: PhantomData,
+        }
+    }
+
+    /// Returns the slot of `cpu`.
+    ///
+    /// Returns `None` if `cpu` is not less than `N`.
+    #[inline]
+    pub fn get_cpu(&self, cpu: usize) -> Option<&T> {
+        self.slots.get(cpu).map(|slot| &slot.value)
+    }
+
+    /// Returns a mutable reference to the slot of `cpu`.
+    ///
+    /// Returns `None` if `cpu` is not less than `N`.
+    #[inline]
+    pub fn get_cpu_mut(&mut self, cpu: usize) -> Option<&mut T> {
+        self.slots.get_mut(cpu).map(|slot| &mut slot.value)
+    }
+
+    /// Returns an iterator over the slots of all CPU cores.
+    #[inline]
+    pub fn iter(&self) -> PerCpuIter<'_, T> {
+        PerCpuIter {
+            slots: self.slots.iter(),
+        }
+    }
+}
+
+impl<T, const N: usize, C: CoreIdProvider> PerCpu<T, N, C> {
+    /// Returns the slot of the current CPU core without disabling interrupts.
+    ///
+    /// The current task may be migrated to another CPU core while holding the returned reference.
+    #[inline]
+    pub fn get(&self) -> &T {
+        let Some(cpu) = C::try_core_id() else {
+            panic!("PerCpu accessed before the current CPU core is known");
+        };
+        match self.get_cpu(cpu) {
+            Some(value) => value,
+            None => panic!("core ID {cpu} exceeds PerCpu capacity {N}"),
+        }
+    }
+
+    /// Runs `f` with the slot of the current CPU core and disabled interrupts.
+    #[inline]
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let guard = InterruptGuard::disable();
+        let ret = f(self.get());
+        drop(guard);
+        ret
+    }
+}
+
+impl<T: Default, const N: usize, C> Default for PerCpu<T, N, C> {
+    #[inline]
+    fn default() -> Self {
+        Self::from_fn(|_| T::default())
+    }
+}
+
+impl<T: fmt::Debug, const N: usize, C> fmt::Debug for PerCpu<T, N, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, const N: usize, C> IntoIterator for &'a PerCpu<T, N, C> {
+    type Item = &'a T;
+    type IntoIter = PerCpuIter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the slots of a [`PerCpu`].
+///
+/// This is returned by [`PerCpu::iter`].
+#[derive(Clone, Debug)]
+pub struct PerCpuIter<'a, T> {
+    slots: slice::Iter<'a, Slot<T>>,
+}
+
+impl<'a, T> Iterator for PerCpuIter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.slots.next().map(|slot| &slot.value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for PerCpuIter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use core::mem;
+
+    use super::*;
+
+    #[test]
+    fn selects_current_slot() {
+        ThreadCpu::set(2);
+        let per_cpu = PerCpu::<usize, 4, ThreadCpu>::from_fn(|cpu| cpu * 10);
+        assert_eq!(*per_cpu.get(), 20);
+        assert_eq!(per_cpu.with(|value| *value), 20);
+        assert_eq!(per_cpu.get_cpu(4), None);
+        assert_eq!(per_cpu.iter().copied().collect::<Vec<_>>(), [0, 10, 20, 30]);
+        assert!(mem::align_of::<PerCpu<u8, 4>>() >= 64);
+
+        let per_cpu = PerCpu::<_, 4, ThreadCpu>::new([0, 1, 2, 3].map(Box::new));
+        assert_eq!(**per_cpu.get(), 2);
+    }
+
+    #[test]
+    #[should_panic = "PerCpu accessed before the current CPU core is known"]
+    fn unknown_core_id() {
+        // No core ID provider is registered in unit tests.
+        let per_cpu = PerCpu::<usize, 4>::default();
+        per_cpu.get();
+    }
+}
@@ -0,0 +1,220 @@
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::relax::{Backoff, Relax};
+use crate::{CoreIdProvider, PerCpu, RegisteredCoreId, MAX_CPUS};
+
+/// The reference count of a CPU core.
+#[derive(Debug)]
+struct RefSlot {
+    /// The number of operations on `count` that may not have observed `killed`.
+    active: AtomicUsize,
+    /// The number of references taken minus the number of references put on this CPU core.
+    count: AtomicUsize,
+}
+
+impl RefSlot {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        active: AtomicUsize::new(0),
+        count: AtomicUsize::new(0),
+    };
+}
+
+/// A reference counter that counts per CPU core until it is killed.
+///
+/// This is like Linux's `percpu_ref`.
+/// While live, [`try_get`](Self::try_get) and [`put`](Self::put) only touch the slot of the current CPU core.
+/// [`kill_and_wait`](Self::kill_and_wait) switches the counter to a shared atomic count and waits until all references have been put.
+/// Afterwards, [`try_get`](Self::try_get) fails, so that the protected object can be torn down.
+///
+/// References may be put on another CPU core than they were taken on.
+///
+/// # Panics
+///
+/// Taking and putting references panics if `C` cannot determine the current CPU core, like with [`PerCpu`].
+/// Otherwise, all CPU cores would count in one slot, which the live fast path does not expect.
+/// It also panics if the current core ID is not less than `N`.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::PerCpuRef;
+///
+/// hermit_sync::set_core_id_provider(|| 0);
+///
+/// static DEVICE_REFS: PerCpuRef<4> = PerCpuRef::new();
+///
+/// assert!(DEVICE_REFS.try_get());
+/// // Use the device here.
+/// DEVICE_REFS.put();
+///
+/// DEVICE_REFS.kill_and_wait();
+/// assert!(!DEVICE_REFS.try_get());
+/// ```
+pub struct PerCpuRef<const N: usize = MAX_CPUS, C = RegisteredCoreId> {
+    slots: PerCpu<RefSlot, N, C>,
+    killed: AtomicBool,
+    /// The number of references that have been put after being killed, negated.
+    shared: AtomicUsize,
+}
+
+impl<const N: usize, C> PerCpuRef<N, C> {
+    /// Creates a new live reference counter without references.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            slots: PerCpu::new([RefSlot::INIT; N]),
+            killed: AtomicBool::new(false),
+            shared: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns `true` if this counter has been killed.
+    #[inline]
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Relaxed)
+    }
+}
+
+impl<const N: usize, C: CoreIdProvider> PerCpuRef<N, C> {
+    /// Runs `f` on the count of the current CPU core if this counter is live.
+    ///
+    /// Returns `false` if this counter has been killed.
+    #[inline]
+    fn with_live_count<F>(&self, f: F) -> bool
+    where
+        F: FnOnce(&AtomicUsize),
+    {
+        let slot = self.slots.get();
+        // Pairs with the `SeqCst` in `kill_and_wait`: either we see the kill or the killer waits for us.
+        slot.active.fetch_add(1, Ordering::SeqCst);
+        let live = !self.killed.load(Ordering::SeqCst);
+        if live {
+            f(&slot.count);
+        }
+        slot.active.fetch_sub(1, Ordering::Release);
+        live
+    }
+
+    /// Takes a reference if this counter is live.
+    ///
+    /// Returns `false` if this counter has been killed.
+    #[inline]
+    #[must_use]
+    pub fn try_get(&self) -> bool {
+        self.with_live_count(|count| {
+            count.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    /// Puts a reference taken via [`try_get`](Self::try_get).
+    #[inline]
+    pub fn put(&self) {
+        let live = self.with_live_count(|count| {
+            count.fetch_sub(1, Ordering::Release);
+        });
+        if !live {
+            self.shared.fetch_sub(1, Ordering::Release);
+        }
+    }
+
+    /// Kills this counter and waits until all references have been put.
+    ///
+    /// This must not be called from interrupt handlers, since it waits for other holders of references.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this counter has already been killed.
+    pub fn kill_and_wait(&self) {
+        let killed = self.killed.swap(true, Ordering::SeqCst);
+        assert!(!killed, "PerCpuRef instance has already been killed");
+
+        let mut count = 0usize;
+        for slot in &self.slots {
+            let mut backoff = Backoff::default();
+            while slot.active.load(Ordering::SeqCst) != 0 {
+                backoff.relax();
+            }
+            count = count.wrapping_add(slot.count.load(Ordering::Acquire));
+        }
+        self.shared.fetch_add(count, Ordering::AcqRel);
+
+        let mut backoff = Backoff::default();
+        while self.shared.load(Ordering::Acquire) != 0 {
+            backoff.relax();
+        }
+    }
+}
+
+impl<const N: usize, C> Default for PerCpuRef<N, C> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, C> fmt::Debug for PerCpuRef<N, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PerCpuRef")
+            .field("killed", &self.is_killed())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+    use crate::percpu::per_cpu::ThreadCpu;
+
+    #[test]
+    fn kill_waits_for_puts() {
+        const N: usize = 4;
+
+        let refs = PerCpuRef::<N, ThreadCpu>::new();
+        let users = AtomicUsize::new(0);
+        let barrier = Barrier::new(N + 1);
+
+        thread::scope(|s| {
+            for cpu in 0..N {
+                let refs = &refs;
+                let users = &users;
+                let barrier = &barrier;
+                s.spawn(move || {
+                    ThreadCpu::set(cpu);
+                    assert!(refs.try_get());
+                    users.fetch_add(1, Ordering::Relaxed);
+                    barrier.wait();
+
+                    // Put the reference on another CPU core.
+                    ThreadCpu::set((cpu + 1) % N);
+                    users.fetch_sub(1, Ordering::Relaxed);
+                    refs.put();
+
+                    while refs.try_get() {
+                        users.fetch_add(1, Ordering::Relaxed);
+                        users.fetch_sub(1, Ordering::Relaxed);
+                        refs.put();
+                    }
+                });
+            }
+
+            barrier.wait();
+            refs.kill_and_wait();
+            assert_eq!(users.load(Ordering::Relaxed), 0);
+        });
+
+        assert!(!refs.try_get());
+    }
+
+    #[test]
+    #[should_panic = "PerCpu accessed before the current CPU core is known"]
+    fn unknown_core_id() {
+        // No core ID provider is registered in unit tests.
+        let refs = PerCpuRef::<4>::new();
+        let _ = refs.try_get();
+    }
+}
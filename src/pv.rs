@@ -0,0 +1,140 @@
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::relax::{Backoff, Relax};
+
+/// The number of relaxations before waiting via [`PvHooks::wait`].
+const SPIN_THRESHOLD: u32 = 16;
+
+static PV_HOOKS: AtomicPtr<PvHooks> = AtomicPtr::new(ptr::null_mut());
+
+/// Paravirtual wait and kick hooks for lock slow paths.
+///
+/// Under hypervisors such as KVM or Xen, the vCPU holding a lock might be preempted.
+/// Instead of spinning until the holder runs again, waiters can halt their vCPU until they are kicked.
+///
+/// Locks are identified by a `key`, which is the address of the lock.
+/// [`RawSpinMutex`](crate::RawSpinMutex) and [`RawTicketMutex`](crate::RawTicketMutex) spin for a while before calling [`wait`](Self::wait) and call [`kick`](Self::kick) on every unlock.
+///
+/// This corresponds to Linux's `pv_wait` and `pv_kick`.
+#[derive(Clone, Copy, Debug)]
+pub struct PvHooks {
+    /// Halts the current vCPU until `key` is kicked.
+    ///
+    /// The implementation has to record the waiter before checking `should_wait`.
+    /// If `should_wait` returns `false`, the implementation must return without halting.
+    /// Otherwise, it must return once `key` has been kicked after recording the waiter.
+    /// Spurious returns are allowed.
+    pub wait: fn(key: usize, should_wait: &dyn Fn() -> bool),
+
+    /// Wakes all vCPUs waiting on `key`.
+    ///
+    /// This is called on every unlock and should be cheap if nobody waits on `key`.
+    pub kick: fn(key: usize),
+}
+
+/// Registers paravirtual wait and kick hooks.
+///
+/// # Examples
+///
+/// With the `uniprocessor` and `all-one-shot` features, [`TicketMutex`](crate::TicketMutex) does not support contention from other threads.
+///
+#[cfg_attr(
+    not(any(feature = "all-one-shot", feature = "uniprocessor")),
+    doc = "```"
+)]
+#[cfg_attr(
+    any(feature = "all-one-shot", feature = "uniprocessor"),
+    doc = "```ignore"
+)]
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::thread;
+///
+/// use hermit_sync::{PvHooks, TicketMutex};
+///
+/// static WAITS: AtomicUsize = AtomicUsize::new(0);
+/// static KICKS: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn wait(_key: usize, should_wait: &dyn Fn() -> bool) {
+///     // Record the waiter and halt via hypercall here.
+///     WAITS.fetch_add(1, Ordering::Relaxed);
+///     while should_wait() {
+///         core::hint::spin_loop();
+///     }
+/// }
+///
+/// fn kick(_key: usize) {
+///     // Kick recorded waiters via hypercall here.
+///     KICKS.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// static PV_HOOKS: PvHooks = PvHooks { wait, kick };
+///
+/// hermit_sync::set_pv_hooks(&PV_HOOKS);
+///
+/// let mutex = TicketMutex::new(0);
+/// let guard = mutex.lock();
+/// thread::scope(|s| {
+///     s.spawn(|| *mutex.lock() += 1);
+///     while WAITS.load(Ordering::Relaxed) == 0 {
+///         core::hint::spin_loop();
+///     }
+///     drop(guard);
+/// });
+///
+/// assert_eq!(*mutex.lock(), 1);
+/// assert!(KICKS.load(Ordering::Relaxed) > 0);
+/// ```
+pub fn set_pv_hooks(hooks: &'static PvHooks) {
+    PV_HOOKS.store(ptr::from_ref(hooks).cast_mut(), Ordering::Release);
+}
+
+#[inline]
+fn hooks() -> Option<&'static PvHooks> {
+    let hooks = PV_HOOKS.load(Ordering::Acquire);
+    // SAFETY: Only `&'static PvHooks` are stored in `PV_HOOKS`.
+    unsafe { hooks.as_ref() }
+}
+
+/// Kicks the waiters of `key` if paravirtual hooks are registered.
+#[cfg_attr(
+    any(feature = "all-one-shot", feature = "uniprocessor"),
+    allow(dead_code)
+)]
+#[inline]
+pub(crate) fn kick(key: usize) {
+    if let Some(hooks) = hooks() {
+        (hooks.kick)(key);
+    }
+}
+
+/// [`Backoff`] that falls back to [`PvHooks::wait`] after spinning for a while.
+#[cfg_attr(
+    any(feature = "all-one-shot", feature = "uniprocessor"),
+    allow(dead_code)
+)]
+#[derive(Default, Debug)]
+pub(crate) struct PvBackoff {
+    backoff: Backoff,
+    spins: u32,
+}
+
+#[cfg_attr(
+    any(feature = "all-one-shot", feature = "uniprocessor"),
+    allow(dead_code)
+)]
+impl PvBackoff {
+    #[inline]
+    pub(crate) fn relax(&mut self, key: usize, should_wait: &dyn Fn() -> bool) {
+        if self.spins < SPIN_THRESHOLD {
+            self.spins += 1;
+            self.backoff.relax();
+            return;
+        }
+
+        match hooks() {
+            Some(hooks) => (hooks.wait)(key, should_wait),
+            None => self.backoff.relax(),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use lock_api::{RawMutex, RawRwLock};
+
+use crate::stats::RawMutexSample;
+
+/// A readers-writer lock that maps both shared and exclusive locking onto a [`RawMutex`].
+///
+/// This allows code that is generic over [`RawRwLock`] to use cheaper mutexes for instances that never have concurrent readers.
+/// Readers exclude each other, so taking a shared lock twice on the same CPU core deadlocks.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{ExclusiveOnly, RawSpinMutex};
+///
+/// static ROUTES: lock_api::RwLock<ExclusiveOnly<RawSpinMutex>, usize> = lock_api::RwLock::new(0);
+///
+/// *ROUTES.write() += 1;
+/// assert_eq!(*ROUTES.read(), 1);
+/// ```
+pub struct ExclusiveOnly<R> {
+    inner: R,
+}
+
+unsafe impl<R: RawMutex> RawRwLock for ExclusiveOnly<R> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self { inner: R::INIT };
+
+    type GuardMarker = R::GuardMarker;
+
+    #[inline]
+    fn lock_shared(&self) {
+        self.inner.lock();
+    }
+
+    #[inline]
+    fn try_lock_shared(&self) -> bool {
+        self.inner.try_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock_shared(&self) {
+        unsafe {
+            self.inner.unlock();
+        }
+    }
+
+    #[inline]
+    fn lock_exclusive(&self) {
+        self.inner.lock();
+    }
+
+    #[inline]
+    fn try_lock_exclusive(&self) -> bool {
+        self.inner.try_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock_exclusive(&self) {
+        unsafe {
+            self.inner.unlock();
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    #[inline]
+    fn is_locked_exclusive(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+/// A mutex that locks a [`RawRwLock`] exclusively.
+///
+/// This allows passing a readers-writer lock to APIs that only accept [`RawMutex`]es, such as [`generic_once_cell`].
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{RawRwSpinLock, WriteOnly};
+///
+/// static CELL: generic_once_cell::OnceCell<WriteOnly<RawRwSpinLock>, usize> =
+///     generic_once_cell::OnceCell::new();
+///
+/// assert_eq!(*CELL.get_or_init(|| 42), 42);
+/// ```
+pub struct WriteOnly<R> {
+    inner: R,
+}
+
+unsafe impl<R: RawRwLock> RawMutex for WriteOnly<R> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self { inner: R::INIT };
+
+    type GuardMarker = R::GuardMarker;
+
+    #[inline]
+    fn lock(&self) {
+        self.inner.lock_exclusive();
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.inner.try_lock_exclusive()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        unsafe {
+            self.inner.unlock_exclusive();
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+}
+
+impl<R: RawRwLock> RawMutexSample for WriteOnly<R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RawRwSpinLock, RawSpinMutex};
+
+    #[test]
+    fn readers_exclude_each_other() {
+        let l = lock_api::RwLock::<ExclusiveOnly<RawSpinMutex>, _>::new(0);
+        let r = l.read();
+        assert!(l.try_read().is_none());
+        assert!(l.try_write().is_none());
+        drop(r);
+
+        *l.write() += 1;
+        assert_eq!(*l.read(), 1);
+        assert!(!l.is_locked());
+    }
+
+    #[test]
+    fn locks_exclusively() {
+        let m = WriteOnly::<RawRwSpinLock>::INIT;
+        m.lock();
+        assert!(!m.inner.try_lock_shared());
+        assert!(!m.try_lock());
+        unsafe { m.unlock() };
+
+        assert!(m.inner.try_lock_shared());
+        assert!(!m.try_lock());
+        unsafe { m.inner.unlock_shared() };
+        assert!(!m.is_locked());
+    }
+}
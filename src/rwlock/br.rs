@@ -0,0 +1,284 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::{fmt, hint};
+
+use crate::relax::{Backoff, Relax};
+use crate::{CoreIdProvider, RegisteredCoreId, MAX_CPUS};
+
+/// A per-CPU reader counter on its own cache line.
+#[repr(align(64))]
+struct ReaderSlot {
+    readers: AtomicUsize,
+}
+
+impl ReaderSlot {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        readers: AtomicUsize::new(0),
+    };
+}
+
+/// A big-reader lock with per-CPU reader counters.
+///
+/// Readers only touch the counter of their CPU core, so concurrent readers on different CPU cores do not share a cache line.
+/// Writers are expensive: they block new readers and then sweep all `CPUS` counters until all readers have left.
+/// This is intended for data that is read on every system call but written rarely.
+///
+/// The counters are selected via [`CoreIdProvider`] `C` modulo `CPUS`.
+/// Read guards remember their counter, so readers may migrate between CPU cores.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::BrLock;
+///
+/// static SYSCALL_TABLE: BrLock<[usize; 4]> = BrLock::new([0; 4]);
+///
+/// SYSCALL_TABLE.write()[1] = 42;
+/// assert_eq!(SYSCALL_TABLE.read()[1], 42);
+/// ```
+pub struct BrLock<T: ?Sized, const CPUS: usize = MAX_CPUS, C = RegisteredCoreId> {
+    slots: [ReaderSlot; CPUS],
+    writer: AtomicBool,
+    _core_id: PhantomData<fn() -> C>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, const CPUS: usize, C> Send for BrLock<T, CPUS, C> {}
+unsafe impl<T: ?Sized + Send + Sync, const CPUS: usize, C> Sync for BrLock<T, CPUS, C> {}
+
+impl<T, const CPUS: usize, C> BrLock<T, CPUS, C> {
+    /// Creates a new unlocked big-reader lock.
+    #[inline]
+    pub const fn new(data: T) -> Self {
+        Self {
+            slots: [ReaderSlot::INIT; CPUS],
+            writer: AtomicBool::new(false),
+            _core_id: PhantomData,
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this lock, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, const CPUS: usize, C: CoreIdProvider> BrLock<T, CPUS, C> {
+    /// Locks this lock with shared read access.
+    ///
+    /// While a writer holds or waits for the lock, this spins.
+    #[inline]
+    pub fn read(&self) -> BrLockReadGuard<'_, T, CPUS, C> {
+        let slot = &self.slots[C::core_id() % CPUS];
+        loop {
+            // Pairs with the `SeqCst` in `write`: either we see the writer or the writer sees us.
+            slot.readers.fetch_add(1, Ordering::SeqCst);
+            if !self.writer.load(Ordering::SeqCst) {
+                return BrLockReadGuard { lock: self, slot };
+            }
+
+            slot.readers.fetch_sub(1, Ordering::Release);
+            let mut backoff = Backoff::default();
+            while self.writer.load(Ordering::Relaxed) {
+                backoff.relax();
+            }
+        }
+    }
+
+    /// Locks this lock with exclusive write access.
+    ///
+    /// This blocks new readers and waits until all readers have left.
+    #[inline]
+    pub fn write(&self) -> BrLockWriteGuard<'_, T, CPUS, C> {
+        let mut backoff = Backoff::default();
+        while self
+            .writer
+            .compare_exchange_weak(false, true, Ordering::SeqCst, Ordering::Relaxed)
+            .is_err()
+        {
+            backoff.relax();
+        }
+
+        // Pairs with the `SeqCst` in `read`: either we see the reader or the reader sees us.
+        for slot in &self.slots {
+            while slot.readers.load(Ordering::SeqCst) != 0 {
+                hint::spin_loop();
+            }
+        }
+
+        BrLockWriteGuard { lock: self }
+    }
+
+    /// Attempts to lock this lock with exclusive write access.
+    ///
+    /// This fails if another writer holds the lock or if any reader holds the lock.
+    #[inline]
+    pub fn try_write(&self) -> Option<BrLockWriteGuard<'_, T, CPUS, C>> {
+        self.writer
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed)
+            .ok()?;
+
+        if self
+            .slots
+            .iter()
+            .any(|slot| slot.readers.load(Ordering::SeqCst) != 0)
+        {
+            self.writer.store(false, Ordering::Release);
+            return None;
+        }
+
+        Some(BrLockWriteGuard { lock: self })
+    }
+}
+
+impl<T: ?Sized, const CPUS: usize, C> BrLock<T, CPUS, C> {
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the lock mutably, no actual locking needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default, const CPUS: usize, C> Default for BrLock<T, CPUS, C> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized, const CPUS: usize, C> fmt::Debug for BrLock<T, CPUS, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrLock")
+            .field("writer", &self.writer.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+/// A read guard of a [`BrLock`].
+#[must_use = "if unused the BrLock will immediately unlock"]
+pub struct BrLockReadGuard<'a, T: ?Sized, const CPUS: usize = MAX_CPUS, C = RegisteredCoreId> {
+    lock: &'a BrLock<T, CPUS, C>,
+    slot: &'a ReaderSlot,
+}
+
+impl<T: ?Sized, const CPUS: usize, C> Deref for BrLockReadGuard<'_, T, CPUS, C> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: We hold the lock for reading.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, const CPUS: usize, C> Drop for BrLockReadGuard<'_, T, CPUS, C> {
+    #[inline]
+    fn drop(&mut self) {
+        self.slot.readers.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, const CPUS: usize, C> fmt::Debug for BrLockReadGuard<'_, T, CPUS, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// A write guard of a [`BrLock`].
+#[must_use = "if unused the BrLock will immediately unlock"]
+pub struct BrLockWriteGuard<'a, T: ?Sized, const CPUS: usize = MAX_CPUS, C = RegisteredCoreId> {
+    lock: &'a BrLock<T, CPUS, C>,
+}
+
+impl<T: ?Sized, const CPUS: usize, C> Deref for BrLockWriteGuard<'_, T, CPUS, C> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: We hold the lock for writing.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, const CPUS: usize, C> DerefMut for BrLockWriteGuard<'_, T, CPUS, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: We hold the lock for writing.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, const CPUS: usize, C> Drop for BrLockWriteGuard<'_, T, CPUS, C> {
+    #[inline]
+    fn drop(&mut self) {
+        self.lock.writer.store(false, Ordering::Release);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, const CPUS: usize, C> fmt::Debug for BrLockWriteGuard<'_, T, CPUS, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::percpu::per_cpu::ThreadCpu;
+
+    #[test]
+    fn readers_block_writers() {
+        let lock = BrLock::<_, 4>::new(0);
+        let guard = lock.read();
+        let other = lock.read();
+        assert!(lock.try_write().is_none());
+        drop((guard, other));
+
+        *lock.try_write().unwrap() += 1;
+        assert_eq!(*lock.read(), 1);
+    }
+
+    #[test]
+    fn reads_slot_of_provider() {
+        ThreadCpu::set(2);
+        let lock = BrLock::<_, 4, ThreadCpu>::new(0);
+        let guard = lock.read();
+        assert_eq!(lock.slots[2].readers.load(Ordering::Relaxed), 1);
+        drop(guard);
+        assert_eq!(lock.slots[2].readers.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn concurrent() {
+        const N: usize = 4;
+        const M: usize = 1000;
+
+        let lock = BrLock::<_>::new(0);
+        thread::scope(|s| {
+            for i in 0..N {
+                let lock = &lock;
+                s.spawn(move || {
+                    for j in 0..M {
+                        if (i + j) % 8 == 0 {
+                            *lock.write() += 1;
+                        } else {
+                            drop(lock.read());
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(lock.into_inner(), N * M / 8);
+    }
+}
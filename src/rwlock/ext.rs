@@ -0,0 +1,48 @@
+use core::mem;
+
+use lock_api::{RawRwLock, RwLock};
+
+/// Convenience methods for [`lock_api::RwLock`]s.
+///
+/// These methods keep the lock held only for the duration of the call, which discourages holding guards across complex logic.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::{RwLockExt, RwSpinLock};
+///
+/// static NAME: RwSpinLock<&str> = RwSpinLock::new("Ferris");
+///
+/// assert_eq!(NAME.get_cloned(), "Ferris");
+/// NAME.set("Corro");
+/// assert_eq!(NAME.get_cloned(), "Corro");
+/// ```
+pub trait RwLockExt<T> {
+    /// Returns a clone of the data.
+    ///
+    /// This acquires a read lock for the duration of the clone.
+    fn get_cloned(&self) -> T
+    where
+        T: Clone;
+
+    /// Sets the data, dropping the previous value after unlocking.
+    ///
+    /// This acquires a write lock for the duration of the assignment.
+    fn set(&self, value: T);
+}
+
+impl<R: RawRwLock, T> RwLockExt<T> for RwLock<R, T> {
+    #[inline]
+    fn get_cloned(&self) -> T
+    where
+        T: Clone,
+    {
+        self.read().clone()
+    }
+
+    #[inline]
+    fn set(&self, value: T) {
+        let old = mem::replace(&mut *self.write(), value);
+        drop(old);
+    }
+}
@@ -0,0 +1,47 @@
+pub(crate) mod adapter;
+pub(crate) mod br;
+pub(crate) mod ext;
+#[cfg(not(feature = "all-one-shot"))]
+pub(crate) mod phase_fair;
+#[cfg(feature = "all-one-shot")]
+pub(crate) mod phase_fair {
+    pub use one_shot_mutex::{
+        OneShotRwLock as PhaseFairRwLock, OneShotRwLockReadGuard as PhaseFairRwLockReadGuard,
+        OneShotRwLockWriteGuard as PhaseFairRwLockWriteGuard,
+        RawOneShotRwLock as RawPhaseFairRwLock,
+    };
+}
+#[cfg(not(any(feature = "all-one-shot", feature = "spinning_top")))]
+pub(crate) mod spin;
+#[cfg(feature = "all-one-shot")]
+pub(crate) mod spin {
+    pub use one_shot_mutex::{
+        OneShotRwLock as RwSpinLock, OneShotRwLockReadGuard as RwSpinLockReadGuard,
+        OneShotRwLockUpgradableReadGuard as RwSpinLockUpgradableReadGuard,
+        OneShotRwLockWriteGuard as RwSpinLockWriteGuard, RawOneShotRwLock as RawRwSpinLock,
+    };
+}
+#[cfg(all(feature = "spinning_top", not(feature = "all-one-shot")))]
+pub(crate) mod spin {
+    pub use spinning_top::guard::{
+        BackoffRwSpinlockReadGuard as RwSpinLockReadGuard,
+        BackoffRwSpinlockUpgradableReadGuard as RwSpinLockUpgradableReadGuard,
+        BackoffRwSpinlockWriteGuard as RwSpinLockWriteGuard,
+    };
+    pub use spinning_top::BackoffRwSpinlock as RwSpinLock;
+
+    /// A simple spinning readers-writer lock with exponential backoff from [`spinning_top`].
+    pub type RawRwSpinLock = spinning_top::RawRwSpinlock<spinning_top::relax::Backoff>;
+}
+#[cfg(not(feature = "all-one-shot"))]
+pub(crate) mod write_pref;
+#[cfg(feature = "all-one-shot")]
+pub(crate) mod write_pref {
+    pub use one_shot_mutex::{
+        OneShotRwLock as RwSpinLockWritePref,
+        OneShotRwLockReadGuard as RwSpinLockWritePrefReadGuard,
+        OneShotRwLockUpgradableReadGuard as RwSpinLockWritePrefUpgradableReadGuard,
+        OneShotRwLockWriteGuard as RwSpinLockWritePrefWriteGuard,
+        RawOneShotRwLock as RawRwSpinLockWritePref,
+    };
+}
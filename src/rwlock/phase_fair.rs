@@ -0,0 +1,246 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use lock_api::{GuardSend, RawRwLock};
+
+use crate::relax::{Backoff, Relax};
+
+/// Reader counter increment
+const RINC: usize = 1 << 8;
+/// Writer bits in the reader entry counter
+const WBITS: usize = 0b11;
+/// A writer is present
+const PRES: usize = 0b10;
+/// The phase ID of the present writer
+const PHID: usize = 0b01;
+
+/// A phase-fair spinning readers-writer lock.
+///
+/// Reader phases and writer phases alternate:
+/// Once a writer is waiting, readers arriving later wait for the writer, and once a writer is done, all waiting readers enter before the next writer.
+/// Writers are served in FIFO order.
+/// Thus, neither readers nor writers are starved and readers wait for at most one writer.
+///
+/// [`is_locked_exclusive`](RawRwLock::is_locked_exclusive) also returns `true` while a writer waits for current readers to leave.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::PhaseFairRwLock;
+///
+/// static CONFIG: PhaseFairRwLock<usize> = PhaseFairRwLock::new(0);
+///
+/// *CONFIG.write() += 1;
+/// assert_eq!(*CONFIG.read(), 1);
+/// ```
+// Based on the ticket-based PF-T lock from "Spin-Based Reader-Writer Synchronization for Multiprocessor Real-Time Systems" by Brandenburg and Anderson.
+pub struct RawPhaseFairRwLock {
+    /// Reader entries in multiples of [`RINC`] and the writer bits
+    rin: AtomicUsize,
+    /// Reader exits in multiples of [`RINC`]
+    rout: AtomicUsize,
+    /// The next writer ticket
+    win: AtomicUsize,
+    /// The writer ticket being served
+    wout: AtomicUsize,
+}
+
+unsafe impl RawRwLock for RawPhaseFairRwLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        rin: AtomicUsize::new(0),
+        rout: AtomicUsize::new(0),
+        win: AtomicUsize::new(0),
+        wout: AtomicUsize::new(0),
+    };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock_shared(&self) {
+        let writer = self.rin.fetch_add(RINC, Ordering::Acquire) & WBITS;
+        if writer == 0 {
+            return;
+        }
+
+        // Wait for the writer phase to end.
+        let mut backoff = Backoff::default();
+        while self.rin.load(Ordering::Acquire) & WBITS == writer {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared(&self) -> bool {
+        self.rin
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |rin| {
+                (rin & WBITS == 0).then(|| rin.wrapping_add(RINC))
+            })
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock_shared(&self) {
+        self.rout.fetch_add(RINC, Ordering::Release);
+    }
+
+    #[inline]
+    fn lock_exclusive(&self) {
+        let ticket = self.win.fetch_add(1, Ordering::Relaxed);
+
+        let mut backoff = Backoff::default();
+        while self.wout.load(Ordering::Acquire) != ticket {
+            backoff.relax();
+        }
+
+        // Block new readers and wait for current readers to leave.
+        let readers = self
+            .rin
+            .fetch_add(PRES | (ticket & PHID), Ordering::Relaxed);
+        let mut backoff = Backoff::default();
+        while self.rout.load(Ordering::Acquire) != readers {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_exclusive(&self) -> bool {
+        let ticket = self.wout.load(Ordering::Relaxed);
+        if self
+            .win
+            .compare_exchange(
+                ticket,
+                ticket.wrapping_add(1),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            return false;
+        }
+
+        let readers = self.rout.load(Ordering::Acquire);
+        if self
+            .rin
+            .compare_exchange(
+                readers,
+                readers | PRES | (ticket & PHID),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            return true;
+        }
+
+        // Readers are present, so pass our turn on to the next writer.
+        self.wout.fetch_add(1, Ordering::Release);
+        false
+    }
+
+    #[inline]
+    unsafe fn unlock_exclusive(&self) {
+        self.rin.fetch_and(!WBITS, Ordering::Release);
+        self.wout.fetch_add(1, Ordering::Release);
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        let rin = self.rin.load(Ordering::Relaxed);
+        rin & WBITS != 0 || rin != self.rout.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn is_locked_exclusive(&self) -> bool {
+        self.rin.load(Ordering::Relaxed) & PRES != 0
+    }
+}
+
+/// A [`lock_api::RwLock`] based on [`RawPhaseFairRwLock`].
+pub type PhaseFairRwLock<T> = lock_api::RwLock<RawPhaseFairRwLock, T>;
+
+/// A [`lock_api::RwLockReadGuard`] based on [`RawPhaseFairRwLock`].
+pub type PhaseFairRwLockReadGuard<'a, T> = lock_api::RwLockReadGuard<'a, RawPhaseFairRwLock, T>;
+
+/// A [`lock_api::RwLockWriteGuard`] based on [`RawPhaseFairRwLock`].
+pub type PhaseFairRwLockWriteGuard<'a, T> = lock_api::RwLockWriteGuard<'a, RawPhaseFairRwLock, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn smoke() {
+        let l = PhaseFairRwLock::new(());
+        drop(l.read());
+        drop(l.write());
+        drop((l.read(), l.read()));
+        drop(l.write());
+        assert!(!l.is_locked());
+    }
+
+    #[test]
+    fn try_lock() {
+        let l = PhaseFairRwLock::new(());
+        let r = l.read();
+        assert!(l.try_write().is_none());
+        assert!(l.try_read().is_some());
+        drop(r);
+
+        let w = l.try_write().unwrap();
+        assert!(l.is_locked_exclusive());
+        assert!(l.try_read().is_none());
+        drop(w);
+        assert!(!l.is_locked());
+    }
+
+    #[test]
+    fn waiting_writer_blocks_new_readers() {
+        let m = RawPhaseFairRwLock::INIT;
+        m.lock_shared();
+
+        thread::scope(|s| {
+            s.spawn(|| unsafe {
+                m.lock_exclusive();
+                m.unlock_exclusive();
+            });
+
+            while !m.is_locked_exclusive() {
+                thread::yield_now();
+            }
+
+            assert!(!m.try_lock_shared());
+            unsafe { m.unlock_shared() };
+        });
+
+        assert!(!m.is_locked());
+        assert!(m.try_lock_shared());
+        unsafe { m.unlock_shared() };
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn concurrent() {
+        const N: usize = 4;
+        const M: usize = 1000;
+
+        let l = PhaseFairRwLock::new(0);
+        thread::scope(|s| {
+            for i in 0..N {
+                let l = &l;
+                s.spawn(move || {
+                    for j in 0..M {
+                        if (i + j) % 4 == 0 {
+                            *l.write() += 1;
+                        } else {
+                            drop(l.read());
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*l.read(), N * M / 4);
+    }
+}
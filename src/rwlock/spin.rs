@@ -0,0 +1,554 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use lock_api::{
+    GuardSend, RawRwLock, RawRwLockDowngrade, RawRwLockFair, RawRwLockRecursive, RawRwLockUpgrade,
+    RawRwLockUpgradeDowngrade,
+};
+
+use crate::relax::{Backoff, Relax};
+
+/// A simple spinning, read-preferring readers-writer lock with exponential backoff.
+///
+/// While an upgradable reader is waiting in [`upgrade`], new readers are held back.
+/// This makes sure that upgrading, such as via [`RwLockUpgradableReadGuard::with_upgraded`], is not starved by readers.
+/// Recursive readers are not held back to avoid deadlocks.
+///
+/// [`upgrade`]: RawRwLockUpgrade::upgrade
+/// [`RwLockUpgradableReadGuard::with_upgraded`]: lock_api::RwLockUpgradableReadGuard::with_upgraded
+// Based on `spinning_top::RawRwSpinlock`, but
+// - with an `UPGRADING` flag,
+// - with `try_lock_upgradable` not touching `UPGRADABLE` if the lock is not acquired.
+pub struct RawRwSpinLock {
+    lock: AtomicUsize,
+}
+
+/// Normal shared lock counter
+const SHARED: usize = 1 << 3;
+/// An upgradable reader is waiting to upgrade
+const UPGRADING: usize = 1 << 2;
+/// Special upgradable shared lock flag
+const UPGRADABLE: usize = 1 << 1;
+/// Exclusive lock flag
+const EXCLUSIVE: usize = 1;
+
+impl RawRwSpinLock {
+    /// Creates a lock from a raw state previously obtained via [`into_raw_state`].
+    ///
+    /// This allows kernel snapshot and migration code to reconstruct a lock.
+    ///
+    /// # Safety
+    ///
+    /// `state` must have been obtained via [`into_raw_state`] of a `RawRwSpinLock`.
+    /// The raw state encodes the number of readers and whether the lock is held upgradably or exclusively.
+    /// For each reader, upgradable reader, and writer encoded in `state`, there must be exactly one owner of the respective lock that eventually unlocks it.
+    /// Usually, this means that the respective guards have been [forgotten] and the lock is force-unlocked later.
+    ///
+    /// A raw state of `0` corresponds to an unlocked lock and is always safe.
+    ///
+    /// [`into_raw_state`]: Self::into_raw_state
+    /// [forgotten]: core::mem::forget
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hermit_sync::RawRwSpinLock;
+    /// use lock_api::RawRwLock;
+    ///
+    /// let lock = RawRwSpinLock::INIT;
+    /// lock.lock_shared();
+    ///
+    /// let state = lock.into_raw_state();
+    /// let lock = unsafe { RawRwSpinLock::from_raw_state(state) };
+    ///
+    /// assert!(lock.is_locked());
+    /// unsafe { lock.unlock_shared() };
+    /// assert!(!lock.is_locked());
+    /// ```
+    #[inline]
+    pub const unsafe fn from_raw_state(state: usize) -> Self {
+        Self {
+            lock: AtomicUsize::new(state),
+        }
+    }
+
+    /// Consumes this lock, returning its raw state.
+    ///
+    /// The raw state can be used to reconstruct the lock via [`from_raw_state`](Self::from_raw_state).
+    #[inline]
+    pub fn into_raw_state(self) -> usize {
+        self.lock.into_inner()
+    }
+
+    #[inline]
+    fn is_locked_shared(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & !(EXCLUSIVE | UPGRADABLE | UPGRADING) != 0
+    }
+
+    /// Returns the number of shared holders of this lock, excluding the upgradable reader.
+    ///
+    /// This is only a snapshot for debugging and might be outdated immediately.
+    /// It may include readers that are about to fail acquiring the lock.
+    #[inline]
+    pub fn reader_count(&self) -> usize {
+        (self.lock.load(Ordering::Relaxed) & !(EXCLUSIVE | UPGRADABLE | UPGRADING)) / SHARED
+    }
+
+    /// Returns `true` if this lock is held by an upgradable reader.
+    ///
+    /// This is only a snapshot for debugging and might be outdated immediately.
+    #[inline]
+    pub fn is_locked_upgradable(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & UPGRADABLE == UPGRADABLE
+    }
+
+    /// Returns `true` if the upgradable reader is waiting in [`upgrade`](RawRwLockUpgrade::upgrade), holding back new readers.
+    ///
+    /// Writers waiting in [`lock_exclusive`](RawRwLock::lock_exclusive) are not tracked.
+    /// This is only a snapshot for debugging and might be outdated immediately.
+    #[inline]
+    pub fn is_upgrade_pending(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & UPGRADING == UPGRADING
+    }
+
+    /// Acquire a shared lock, returning the new lock value.
+    #[inline]
+    fn acquire_shared(&self) -> usize {
+        let value = self.lock.fetch_add(SHARED, Ordering::Acquire);
+
+        // An arbitrary cap that allows us to catch overflows long before they happen
+        if value > usize::MAX / 2 {
+            self.lock.fetch_sub(SHARED, Ordering::Relaxed);
+            panic!("Too many shared locks, cannot safely proceed");
+        }
+
+        value
+    }
+
+    /// Try to acquire a shared lock if none of the `blocking` flags are set.
+    #[inline]
+    fn try_lock_shared_unless(&self, blocking: usize) -> bool {
+        let value = self.acquire_shared();
+
+        let acquired = value & blocking == 0;
+
+        if !acquired {
+            unsafe {
+                self.unlock_shared();
+            }
+        }
+
+        acquired
+    }
+}
+
+unsafe impl RawRwLock for RawRwSpinLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        lock: AtomicUsize::new(0),
+    };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock_shared(&self) {
+        let mut backoff = Backoff::default();
+
+        while !self.try_lock_shared() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared(&self) -> bool {
+        self.try_lock_shared_unless(EXCLUSIVE | UPGRADING)
+    }
+
+    #[inline]
+    unsafe fn unlock_shared(&self) {
+        debug_assert!(self.is_locked_shared());
+
+        self.lock.fetch_sub(SHARED, Ordering::Release);
+    }
+
+    #[inline]
+    fn lock_exclusive(&self) {
+        let mut backoff = Backoff::default();
+
+        while !self.try_lock_exclusive() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_exclusive(&self) -> bool {
+        self.lock
+            .compare_exchange(0, EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock_exclusive(&self) {
+        debug_assert!(self.is_locked_exclusive());
+
+        self.lock.fetch_and(!EXCLUSIVE, Ordering::Release);
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) != 0
+    }
+
+    #[inline]
+    fn is_locked_exclusive(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & EXCLUSIVE == EXCLUSIVE
+    }
+}
+
+unsafe impl RawRwLockFair for RawRwSpinLock {
+    #[inline]
+    unsafe fn unlock_shared_fair(&self) {
+        unsafe { self.unlock_shared() }
+    }
+
+    #[inline]
+    unsafe fn unlock_exclusive_fair(&self) {
+        unsafe { self.unlock_exclusive() }
+    }
+
+    /// Temporarily yields the lock to a waiting writer.
+    ///
+    /// Since this lock is read-preferring, this only lets writers in if no other readers hold the lock.
+    #[inline]
+    unsafe fn bump_shared(&self) {
+        unsafe {
+            self.unlock_shared();
+        }
+        // Give a spinning writer the chance to observe the lock as unlocked.
+        Backoff::default().relax();
+        self.lock_shared();
+    }
+
+    /// Temporarily yields the lock to waiting readers and writers.
+    #[inline]
+    unsafe fn bump_exclusive(&self) {
+        unsafe {
+            self.unlock_exclusive();
+        }
+        // Give spinning readers and writers the chance to observe the lock as unlocked.
+        Backoff::default().relax();
+        self.lock_exclusive();
+    }
+}
+
+unsafe impl RawRwLockRecursive for RawRwSpinLock {
+    #[inline]
+    fn lock_shared_recursive(&self) {
+        let mut backoff = Backoff::default();
+
+        while !self.try_lock_shared_recursive() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared_recursive(&self) -> bool {
+        self.try_lock_shared_unless(EXCLUSIVE)
+    }
+}
+
+unsafe impl RawRwLockDowngrade for RawRwSpinLock {
+    #[inline]
+    unsafe fn downgrade(&self) {
+        // Reserve the shared guard for ourselves
+        self.acquire_shared();
+
+        unsafe {
+            self.unlock_exclusive();
+        }
+    }
+}
+
+unsafe impl RawRwLockUpgrade for RawRwSpinLock {
+    #[inline]
+    fn lock_upgradable(&self) {
+        let mut backoff = Backoff::default();
+
+        while !self.try_lock_upgradable() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_upgradable(&self) -> bool {
+        self.lock
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |value| {
+                (value & (UPGRADABLE | EXCLUSIVE) == 0).then_some(value | UPGRADABLE)
+            })
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock_upgradable(&self) {
+        debug_assert!(self.is_locked_upgradable());
+
+        self.lock.fetch_and(!UPGRADABLE, Ordering::Release);
+    }
+
+    #[inline]
+    unsafe fn upgrade(&self) {
+        if unsafe { self.try_upgrade() } {
+            return;
+        }
+
+        // Hold back new readers until we are done upgrading.
+        self.lock.fetch_or(UPGRADING, Ordering::Relaxed);
+
+        let mut backoff = Backoff::default();
+        while self
+            .lock
+            .compare_exchange_weak(
+                UPGRADABLE | UPGRADING,
+                EXCLUSIVE,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    unsafe fn try_upgrade(&self) -> bool {
+        self.lock
+            .compare_exchange(UPGRADABLE, EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+unsafe impl RawRwLockUpgradeDowngrade for RawRwSpinLock {
+    #[inline]
+    unsafe fn downgrade_upgradable(&self) {
+        self.acquire_shared();
+
+        unsafe {
+            self.unlock_upgradable();
+        }
+    }
+
+    #[inline]
+    unsafe fn downgrade_to_upgradable(&self) {
+        debug_assert!(self.is_locked_exclusive());
+
+        self.lock
+            .fetch_xor(UPGRADABLE | EXCLUSIVE, Ordering::Release);
+    }
+}
+
+/// A [`lock_api::RwLock`] based on [`RawRwSpinLock`].
+pub type RwSpinLock<T> = lock_api::RwLock<RawRwSpinLock, T>;
+
+/// A [`lock_api::RwLockReadGuard`] based on [`RawRwSpinLock`].
+pub type RwSpinLockReadGuard<'a, T> = lock_api::RwLockReadGuard<'a, RawRwSpinLock, T>;
+
+/// A [`lock_api::RwLockUpgradableReadGuard`] based on [`RawRwSpinLock`].
+pub type RwSpinLockUpgradableReadGuard<'a, T> =
+    lock_api::RwLockUpgradableReadGuard<'a, RawRwSpinLock, T>;
+
+/// A [`lock_api::RwLockWriteGuard`] based on [`RawRwSpinLock`].
+pub type RwSpinLockWriteGuard<'a, T> = lock_api::RwLockWriteGuard<'a, RawRwSpinLock, T>;
+
+// Adapted from `spinning_top::rw_spinlock`.
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    use lock_api::{RwLockUpgradableReadGuard, RwLockWriteGuard};
+
+    use super::*;
+
+    #[test]
+    fn test_unlock_shared() {
+        let m = RawRwSpinLock::INIT;
+        m.lock_shared();
+        m.lock_shared();
+        m.lock_shared();
+        assert!(!m.try_lock_exclusive());
+        unsafe {
+            m.unlock_shared();
+            m.unlock_shared();
+        }
+        assert!(!m.try_lock_exclusive());
+        unsafe {
+            m.unlock_shared();
+        }
+        assert!(m.try_lock_exclusive());
+    }
+
+    #[test]
+    fn test_unlock_exclusive() {
+        let m = RawRwSpinLock::INIT;
+        m.lock_exclusive();
+        assert!(!m.try_lock_shared());
+        unsafe {
+            m.unlock_exclusive();
+        }
+        assert!(m.try_lock_shared());
+    }
+
+    #[test]
+    fn smoke() {
+        let l = RwSpinLock::new(());
+        drop(l.read());
+        drop(l.write());
+        drop((l.read(), l.read()));
+        drop(l.write());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn frob() {
+        use rand::Rng;
+
+        static R: RwSpinLock<usize> = RwSpinLock::new(0);
+        const N: usize = 10;
+        const M: usize = 1000;
+
+        let (tx, rx) = channel::<()>();
+        for _ in 0..N {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut rng = rand::thread_rng();
+                for _ in 0..M {
+                    if rng.gen_bool(1.0 / N as f64) {
+                        drop(R.write());
+                    } else if rng.gen_bool(0.5) {
+                        let mut guard = R.upgradable_read();
+                        guard.with_upgraded(|value| *value += 1);
+                    } else {
+                        drop(R.read());
+                    }
+                }
+                drop(tx);
+            });
+        }
+        drop(tx);
+        let _ = rx.recv();
+    }
+
+    #[test]
+    fn test_upgrade_downgrade() {
+        let m = RwSpinLock::new(());
+        {
+            let _r = m.read();
+            let upg = m.try_upgradable_read().unwrap();
+            assert!(m.try_read().is_some());
+            assert!(m.try_write().is_none());
+            assert!(RwLockUpgradableReadGuard::try_upgrade(upg).is_err());
+        }
+        {
+            let w = m.write();
+            assert!(m.try_upgradable_read().is_none());
+            let _r = RwLockWriteGuard::downgrade(w);
+            assert!(m.try_upgradable_read().is_some());
+            assert!(m.try_read().is_some());
+            assert!(m.try_write().is_none());
+        }
+        {
+            let _u = m.upgradable_read();
+            assert!(m.try_upgradable_read().is_none());
+        }
+
+        assert!(RwLockUpgradableReadGuard::try_upgrade(m.try_upgradable_read().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn bump_lets_readers_in() {
+        let m = RwSpinLock::new(0);
+        let mut guard = m.write();
+        thread::scope(|s| {
+            let reader = s.spawn(|| *m.read());
+            while !reader.is_finished() {
+                RwLockWriteGuard::bump(&mut guard);
+            }
+            *guard += 1;
+            assert_eq!(reader.join().unwrap(), 0);
+        });
+        drop(guard);
+
+        let guard = m.write();
+        RwLockWriteGuard::unlock_fair(guard);
+        assert_eq!(*m.read(), 1);
+    }
+
+    #[test]
+    fn raw_state() {
+        let m = RawRwSpinLock::INIT;
+        m.lock_upgradable();
+        m.lock_shared();
+        m.lock_shared();
+
+        let m = unsafe { RawRwSpinLock::from_raw_state(m.into_raw_state()) };
+        assert!(!m.try_lock_upgradable());
+        assert!(m.try_lock_shared());
+        unsafe {
+            m.unlock_shared();
+            m.unlock_shared();
+            m.unlock_shared();
+            m.upgrade();
+        }
+        assert!(m.is_locked_exclusive());
+        unsafe { m.unlock_exclusive() };
+        assert_eq!(m.into_raw_state(), 0);
+    }
+
+    #[test]
+    fn upgrade_holds_back_readers() {
+        let m = RawRwSpinLock::INIT;
+        m.lock_upgradable();
+        m.lock_shared();
+
+        thread::scope(|s| {
+            s.spawn(|| unsafe {
+                m.upgrade();
+                m.unlock_exclusive();
+            });
+
+            while !m.is_upgrade_pending() {
+                thread::yield_now();
+            }
+
+            assert!(!m.try_lock_shared());
+            assert!(m.try_lock_shared_recursive());
+            unsafe {
+                m.unlock_shared();
+                m.unlock_shared();
+            }
+        });
+
+        assert!(!m.is_locked());
+    }
+
+    #[test]
+    fn introspection() {
+        let m = RawRwSpinLock::INIT;
+        assert_eq!(m.reader_count(), 0);
+        assert!(!m.is_locked_upgradable());
+
+        m.lock_upgradable();
+        m.lock_shared();
+        m.lock_shared();
+        assert_eq!(m.reader_count(), 2);
+        assert!(m.is_locked_upgradable());
+        assert!(!m.is_upgrade_pending());
+
+        unsafe {
+            m.unlock_shared();
+            m.unlock_shared();
+            m.unlock_upgradable();
+        }
+        assert_eq!(m.reader_count(), 0);
+        assert!(!m.is_locked_upgradable());
+    }
+}
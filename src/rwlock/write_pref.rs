@@ -0,0 +1,336 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use lock_api::{
+    GuardSend, RawRwLock, RawRwLockDowngrade, RawRwLockRecursive, RawRwLockUpgrade,
+    RawRwLockUpgradeDowngrade,
+};
+
+use crate::relax::{Backoff, Relax};
+
+/// A simple spinning, write-preferring readers-writer lock with exponential backoff.
+///
+/// In contrast to [`RawRwSpinLock`](crate::RawRwSpinLock), waiting writers hold back new readers.
+/// This makes sure that writers are not starved on read-heavy paths.
+/// Recursive readers are not held back to avoid deadlocks.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::RwSpinLockWritePref;
+///
+/// static PAGE_TABLE: RwSpinLockWritePref<usize> = RwSpinLockWritePref::new(0);
+///
+/// *PAGE_TABLE.write() += 1;
+/// assert_eq!(*PAGE_TABLE.read(), 1);
+/// ```
+pub struct RawRwSpinLockWritePref {
+    lock: AtomicUsize,
+}
+
+/// Normal shared lock counter
+const SHARED: usize = 1 << 3;
+/// A writer or an upgradable reader is waiting
+const WRITER_WAITING: usize = 1 << 2;
+/// Special upgradable shared lock flag
+const UPGRADABLE: usize = 1 << 1;
+/// Exclusive lock flag
+const EXCLUSIVE: usize = 1;
+
+impl RawRwSpinLockWritePref {
+    #[inline]
+    fn is_locked_shared(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & !(EXCLUSIVE | UPGRADABLE | WRITER_WAITING) != 0
+    }
+
+    #[inline]
+    fn is_locked_upgradable(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & UPGRADABLE == UPGRADABLE
+    }
+
+    /// Acquire a shared lock, returning the new lock value.
+    #[inline]
+    fn acquire_shared(&self) -> usize {
+        let value = self.lock.fetch_add(SHARED, Ordering::Acquire);
+
+        // An arbitrary cap that allows us to catch overflows long before they happen
+        if value > usize::MAX / 2 {
+            self.lock.fetch_sub(SHARED, Ordering::Relaxed);
+            panic!("Too many shared locks, cannot safely proceed");
+        }
+
+        value
+    }
+
+    /// Try to acquire a shared lock if none of the `blocking` flags are set.
+    #[inline]
+    fn try_lock_shared_unless(&self, blocking: usize) -> bool {
+        let value = self.acquire_shared();
+
+        let acquired = value & blocking == 0;
+
+        if !acquired {
+            unsafe {
+                self.unlock_shared();
+            }
+        }
+
+        acquired
+    }
+
+    /// Waits until the lock only has the `held` flags and replaces them with [`EXCLUSIVE`].
+    ///
+    /// [`WRITER_WAITING`] is set while waiting.
+    #[inline]
+    fn wait_exclusive(&self, held: usize) {
+        let mut backoff = Backoff::default();
+        loop {
+            let value = self.lock.load(Ordering::Relaxed);
+            if value & !WRITER_WAITING == held
+                && self
+                    .lock
+                    .compare_exchange_weak(value, EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return;
+            }
+
+            if value & WRITER_WAITING == 0 {
+                self.lock.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+            }
+            backoff.relax();
+        }
+    }
+}
+
+unsafe impl RawRwLock for RawRwSpinLockWritePref {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        lock: AtomicUsize::new(0),
+    };
+
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock_shared(&self) {
+        let mut backoff = Backoff::default();
+
+        while !self.try_lock_shared() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared(&self) -> bool {
+        self.try_lock_shared_unless(EXCLUSIVE | WRITER_WAITING)
+    }
+
+    #[inline]
+    unsafe fn unlock_shared(&self) {
+        debug_assert!(self.is_locked_shared());
+
+        self.lock.fetch_sub(SHARED, Ordering::Release);
+    }
+
+    #[inline]
+    fn lock_exclusive(&self) {
+        if !self.try_lock_exclusive() {
+            self.wait_exclusive(0);
+        }
+    }
+
+    #[inline]
+    fn try_lock_exclusive(&self) -> bool {
+        self.lock
+            .compare_exchange(0, EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock_exclusive(&self) {
+        debug_assert!(self.is_locked_exclusive());
+
+        self.lock.fetch_and(!EXCLUSIVE, Ordering::Release);
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & !WRITER_WAITING != 0
+    }
+
+    #[inline]
+    fn is_locked_exclusive(&self) -> bool {
+        self.lock.load(Ordering::Relaxed) & EXCLUSIVE == EXCLUSIVE
+    }
+}
+
+unsafe impl RawRwLockRecursive for RawRwSpinLockWritePref {
+    #[inline]
+    fn lock_shared_recursive(&self) {
+        let mut backoff = Backoff::default();
+
+        while !self.try_lock_shared_recursive() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared_recursive(&self) -> bool {
+        self.try_lock_shared_unless(EXCLUSIVE)
+    }
+}
+
+unsafe impl RawRwLockDowngrade for RawRwSpinLockWritePref {
+    #[inline]
+    unsafe fn downgrade(&self) {
+        // Reserve the shared guard for ourselves
+        self.acquire_shared();
+
+        unsafe {
+            self.unlock_exclusive();
+        }
+    }
+}
+
+unsafe impl RawRwLockUpgrade for RawRwSpinLockWritePref {
+    #[inline]
+    fn lock_upgradable(&self) {
+        let mut backoff = Backoff::default();
+
+        while !self.try_lock_upgradable() {
+            backoff.relax();
+        }
+    }
+
+    #[inline]
+    fn try_lock_upgradable(&self) -> bool {
+        self.lock
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |value| {
+                (value & (UPGRADABLE | EXCLUSIVE | WRITER_WAITING) == 0)
+                    .then_some(value | UPGRADABLE)
+            })
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock_upgradable(&self) {
+        debug_assert!(self.is_locked_upgradable());
+
+        self.lock.fetch_and(!UPGRADABLE, Ordering::Release);
+    }
+
+    #[inline]
+    unsafe fn upgrade(&self) {
+        if unsafe { !self.try_upgrade() } {
+            self.wait_exclusive(UPGRADABLE);
+        }
+    }
+
+    #[inline]
+    unsafe fn try_upgrade(&self) -> bool {
+        self.lock
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |value| {
+                (value & !WRITER_WAITING == UPGRADABLE).then_some(EXCLUSIVE)
+            })
+            .is_ok()
+    }
+}
+
+unsafe impl RawRwLockUpgradeDowngrade for RawRwSpinLockWritePref {
+    #[inline]
+    unsafe fn downgrade_upgradable(&self) {
+        self.acquire_shared();
+
+        unsafe {
+            self.unlock_upgradable();
+        }
+    }
+
+    #[inline]
+    unsafe fn downgrade_to_upgradable(&self) {
+        debug_assert!(self.is_locked_exclusive());
+
+        self.lock
+            .fetch_xor(UPGRADABLE | EXCLUSIVE, Ordering::Release);
+    }
+}
+
+/// A [`lock_api::RwLock`] based on [`RawRwSpinLockWritePref`].
+pub type RwSpinLockWritePref<T> = lock_api::RwLock<RawRwSpinLockWritePref, T>;
+
+/// A [`lock_api::RwLockReadGuard`] based on [`RawRwSpinLockWritePref`].
+pub type RwSpinLockWritePrefReadGuard<'a, T> =
+    lock_api::RwLockReadGuard<'a, RawRwSpinLockWritePref, T>;
+
+/// A [`lock_api::RwLockUpgradableReadGuard`] based on [`RawRwSpinLockWritePref`].
+pub type RwSpinLockWritePrefUpgradableReadGuard<'a, T> =
+    lock_api::RwLockUpgradableReadGuard<'a, RawRwSpinLockWritePref, T>;
+
+/// A [`lock_api::RwLockWriteGuard`] based on [`RawRwSpinLockWritePref`].
+pub type RwSpinLockWritePrefWriteGuard<'a, T> =
+    lock_api::RwLockWriteGuard<'a, RawRwSpinLockWritePref, T>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use lock_api::{RwLockUpgradableReadGuard, RwLockWriteGuard};
+
+    use super::*;
+
+    #[test]
+    fn smoke() {
+        let l = RwSpinLockWritePref::new(());
+        drop(l.read());
+        drop(l.write());
+        drop((l.read(), l.read()));
+        drop(l.write());
+    }
+
+    #[test]
+    fn test_upgrade_downgrade() {
+        let m = RwSpinLockWritePref::new(());
+        {
+            let _r = m.read();
+            let upg = m.try_upgradable_read().unwrap();
+            assert!(m.try_read().is_some());
+            assert!(m.try_write().is_none());
+            assert!(RwLockUpgradableReadGuard::try_upgrade(upg).is_err());
+        }
+        {
+            let w = m.write();
+            assert!(m.try_upgradable_read().is_none());
+            let _r = RwLockWriteGuard::downgrade(w);
+            assert!(m.try_upgradable_read().is_some());
+            assert!(m.try_read().is_some());
+            assert!(m.try_write().is_none());
+        }
+
+        assert!(RwLockUpgradableReadGuard::try_upgrade(m.try_upgradable_read().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn writer_holds_back_readers() {
+        let m = RawRwSpinLockWritePref::INIT;
+        m.lock_shared();
+
+        thread::scope(|s| {
+            s.spawn(|| unsafe {
+                m.lock_exclusive();
+                m.unlock_exclusive();
+            });
+
+            while m.lock.load(Ordering::Relaxed) & WRITER_WAITING == 0 {
+                thread::yield_now();
+            }
+
+            assert!(!m.try_lock_shared());
+            assert!(m.try_lock_shared_recursive());
+            unsafe {
+                m.unlock_shared();
+                m.unlock_shared();
+            }
+        });
+
+        assert!(!m.is_locked());
+    }
+}
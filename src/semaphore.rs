@@ -0,0 +1,247 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::relax::{Backoff, Relax};
+use crate::{InterruptControl, NativeInterrupts};
+
+/// A counting semaphore.
+///
+/// A semaphore hands out a limited number of permits, for example, for a fixed pool of DMA buffers.
+/// [`acquire`](Self::acquire) spins with [exponential backoff] until a permit is available.
+/// Permits are returned when the [`SemaphoreGuard`] is dropped or via [`release`](Self::release).
+///
+/// [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::Semaphore;
+///
+/// static DMA_BUFFERS: Semaphore = Semaphore::new(2);
+///
+/// let a = DMA_BUFFERS.acquire();
+/// let b = DMA_BUFFERS.acquire();
+/// assert!(DMA_BUFFERS.try_acquire().is_none());
+///
+/// drop(a);
+/// assert_eq!(DMA_BUFFERS.available_permits(), 1);
+/// # drop(b);
+/// ```
+pub struct Semaphore {
+    permits: AtomicUsize,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with `permits` available permits.
+    #[inline]
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            permits: AtomicUsize::new(permits),
+        }
+    }
+
+    /// Acquires a permit, spinning until one is available.
+    #[inline]
+    pub fn acquire(&self) -> SemaphoreGuard<'_> {
+        let mut backoff = Backoff::default();
+        loop {
+            if let Some(guard) = self.try_acquire() {
+                return guard;
+            }
+            while self.permits.load(Ordering::Relaxed) == 0 {
+                backoff.relax();
+            }
+        }
+    }
+
+    /// Attempts to acquire a permit without spinning.
+    ///
+    /// Returns `None` if no permit is available.
+    #[inline]
+    pub fn try_acquire(&self) -> Option<SemaphoreGuard<'_>> {
+        self.permits
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |permits| {
+                permits.checked_sub(1)
+            })
+            .ok()
+            .map(|_| SemaphoreGuard { semaphore: self })
+    }
+
+    /// Adds a permit.
+    ///
+    /// This can be used to return permits of [forgotten](SemaphoreGuard::forget) guards or to hand out additional permits.
+    #[inline]
+    pub fn release(&self) {
+        self.permits.fetch_add(1, Ordering::Release);
+    }
+
+    /// Returns the number of currently available permits.
+    #[inline]
+    pub fn available_permits(&self) -> usize {
+        self.permits.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("permits", &self.available_permits())
+            .finish()
+    }
+}
+
+/// A permit of a [`Semaphore`].
+///
+/// The permit is returned when this guard is dropped.
+#[must_use = "if unused the permit will immediately be released"]
+pub struct SemaphoreGuard<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl SemaphoreGuard<'_> {
+    /// Consumes this guard without returning the permit.
+    ///
+    /// The permit can be returned later via [`Semaphore::release`].
+    #[inline]
+    pub fn forget(this: Self) {
+        core::mem::forget(this);
+    }
+}
+
+impl Drop for SemaphoreGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+impl fmt::Debug for SemaphoreGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemaphoreGuard").finish_non_exhaustive()
+    }
+}
+
+/// A [`Semaphore`] that can be shared with interrupt handlers.
+///
+/// [`acquire`](Self::acquire) and [`try_acquire`](Self::try_acquire) disable interrupts via [`InterruptControl`] `C` while manipulating the permit count.
+/// In contrast to [`InterruptMutex`](crate::InterruptMutex), interrupts are enabled again while spinning and while holding the permit.
+/// Thus, interrupt handlers can [`release`](Self::release) permits to kernel threads waiting in [`acquire`](Self::acquire).
+///
+/// Interrupt handlers must not call [`acquire`](Self::acquire), since the permit may only be released by the interrupted thread.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::InterruptSemaphore;
+///
+/// static RX_PACKETS: InterruptSemaphore = InterruptSemaphore::new(0);
+///
+/// fn rx_interrupt_handler() {
+///     RX_PACKETS.release();
+/// }
+///
+/// rx_interrupt_handler();
+///
+/// let packet = RX_PACKETS.acquire();
+/// // Process the packet here.
+/// hermit_sync::SemaphoreGuard::forget(packet);
+/// assert!(RX_PACKETS.try_acquire().is_none());
+/// ```
+pub struct InterruptSemaphore<C = NativeInterrupts> {
+    inner: Semaphore,
+    _control: PhantomData<fn() -> C>,
+}
+
+impl<C> InterruptSemaphore<C> {
+    /// Creates a new semaphore with `permits` available permits.
+    #[inline]
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            inner: Semaphore::new(permits),
+            _control: PhantomData,
+        }
+    }
+
+    /// Adds a permit.
+    ///
+    /// This is safe to call from interrupt handlers.
+    #[inline]
+    pub fn release(&self) {
+        self.inner.release();
+    }
+
+    /// Returns the number of currently available permits.
+    #[inline]
+    pub fn available_permits(&self) -> usize {
+        self.inner.available_permits()
+    }
+}
+
+impl<C: InterruptControl> InterruptSemaphore<C> {
+    /// Acquires a permit, spinning until one is available.
+    ///
+    /// Interrupts are enabled while spinning.
+    #[inline]
+    pub fn acquire(&self) -> SemaphoreGuard<'_> {
+        let mut backoff = Backoff::default();
+        loop {
+            if let Some(guard) = self.try_acquire() {
+                return guard;
+            }
+            while self.available_permits() == 0 {
+                backoff.relax();
+            }
+        }
+    }
+
+    /// Attempts to acquire a permit without spinning.
+    ///
+    /// Returns `None` if no permit is available.
+    #[inline]
+    pub fn try_acquire(&self) -> Option<SemaphoreGuard<'_>> {
+        let flags = C::save_disable();
+        let guard = self.inner.try_acquire();
+        C::restore(flags);
+        guard
+    }
+}
+
+impl<C> fmt::Debug for InterruptSemaphore<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterruptSemaphore")
+            .field("permits", &self.available_permits())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn bounds_concurrency() {
+        const PERMITS: usize = 2;
+
+        let semaphore = Semaphore::new(PERMITS);
+        let holders = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        let guard = semaphore.acquire();
+                        let previous = holders.fetch_add(1, Ordering::Relaxed);
+                        assert!(previous < PERMITS);
+                        holders.fetch_sub(1, Ordering::Relaxed);
+                        drop(guard);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(semaphore.available_permits(), PERMITS);
+    }
+}
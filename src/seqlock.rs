@@ -0,0 +1,320 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{self, AtomicUsize, Ordering};
+use core::{fmt, ptr};
+
+use lock_api::RawMutex;
+
+use crate::relax::{Backoff, Relax};
+use crate::{RawInterruptSpinMutex, RawSpinMutex};
+
+/// A [sequence counter] for protecting data with lockless readers.
+///
+/// In contrast to [`SeqLock`], this does not contain the data, so it can protect irregular data layouts, such as statistics split across several structures.
+/// Writers must be serialized by the caller, for example, via a mutex.
+///
+/// [sequence counter]: https://www.kernel.org/doc/html/latest/locking/seqlock.html
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// use hermit_sync::{SeqCount, SpinMutex};
+///
+/// static RX_PACKETS: AtomicU64 = AtomicU64::new(0);
+/// static RX_BYTES: AtomicU64 = AtomicU64::new(0);
+/// static RX_SEQ: SeqCount = SeqCount::new();
+/// static RX_LOCK: SpinMutex<()> = SpinMutex::new(());
+///
+/// let guard = RX_LOCK.lock();
+/// RX_SEQ.write_begin();
+/// RX_PACKETS.fetch_add(1, Ordering::Relaxed);
+/// RX_BYTES.fetch_add(1500, Ordering::Relaxed);
+/// RX_SEQ.write_end();
+/// drop(guard);
+///
+/// let (packets, bytes) = loop {
+///     let seq = RX_SEQ.read_begin();
+///     let packets = RX_PACKETS.load(Ordering::Relaxed);
+///     let bytes = RX_BYTES.load(Ordering::Relaxed);
+///     if !RX_SEQ.read_retry(seq) {
+///         break (packets, bytes);
+///     }
+/// };
+/// assert_eq!((packets, bytes), (1, 1500));
+/// ```
+#[derive(Default, Debug)]
+pub struct SeqCount {
+    /// Odd while a writer is active.
+    seq: AtomicUsize,
+}
+
+impl SeqCount {
+    /// Creates a new sequence counter.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+        }
+    }
+
+    /// Begins a read section, waiting while a writer is active.
+    ///
+    /// Returns the sequence to pass to [`read_retry`](Self::read_retry).
+    #[inline]
+    pub fn read_begin(&self) -> usize {
+        let mut backoff = Backoff::default();
+        loop {
+            if let Some(seq) = self.try_read_begin() {
+                return seq;
+            }
+            backoff.relax();
+        }
+    }
+
+    /// Begins a read section unless a writer is active.
+    #[inline]
+    pub fn try_read_begin(&self) -> Option<usize> {
+        let seq = self.seq.load(Ordering::Acquire);
+        (seq & 1 == 0).then_some(seq)
+    }
+
+    /// Ends a read section.
+    ///
+    /// Returns `true` if a writer was active since [`read_begin`](Self::read_begin) and the read has to be retried.
+    #[inline]
+    pub fn read_retry(&self, seq: usize) -> bool {
+        atomic::fence(Ordering::Acquire);
+        self.seq.load(Ordering::Relaxed) != seq
+    }
+
+    /// Begins a write section.
+    ///
+    /// Writers must be serialized.
+    /// Readers retry until [`write_end`](Self::write_end) is called.
+    #[inline]
+    pub fn write_begin(&self) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+    }
+
+    /// Ends a write section.
+    #[inline]
+    pub fn write_end(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// A [sequence lock] for small [`Copy`] data.
+///
+/// Readers never block writers and never write to shared memory.
+/// Instead, readers copy the data and retry if a writer was active in the meantime.
+/// Writers are serialized via the [`RawMutex`] `R`.
+/// For data that is not contained in a single value, see [`SeqCount`].
+///
+/// This is intended for data such as timekeeping state that is read from interrupt handlers.
+/// If interrupt handlers read data that is written with interrupts enabled on the same CPU core, readers spin forever.
+/// [`InterruptSeqLock`] disables interrupts while writing to avoid this.
+///
+/// [sequence lock]: https://en.wikipedia.org/wiki/Seqlock
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::SeqLock;
+///
+/// #[derive(Clone, Copy)]
+/// struct Clock {
+///     secs: u64,
+///     nanos: u32,
+/// }
+///
+/// static CLOCK: SeqLock<Clock> = SeqLock::new(Clock { secs: 0, nanos: 0 });
+///
+/// let mut clock = CLOCK.write();
+/// clock.secs = 1;
+/// clock.nanos = 500;
+/// drop(clock);
+///
+/// let clock = CLOCK.read();
+/// assert_eq!((clock.secs, clock.nanos), (1, 500));
+/// ```
+pub struct SeqLock<T: Copy, R = RawSpinMutex> {
+    seq: SeqCount,
+    writer: R,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Copy + Send, R: Sync> Sync for SeqLock<T, R> {}
+
+impl<T: Copy, R: RawMutex> SeqLock<T, R> {
+    /// Creates a new sequence lock.
+    #[inline]
+    pub const fn new(data: T) -> Self {
+        Self {
+            seq: SeqCount::new(),
+            writer: R::INIT,
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Reads the data, retrying while writers are active.
+    #[inline]
+    pub fn read(&self) -> T {
+        let mut backoff = Backoff::default();
+        loop {
+            if let Some(data) = self.try_read() {
+                return data;
+            }
+            backoff.relax();
+        }
+    }
+
+    /// Attempts to read the data once.
+    ///
+    /// Returns `None` if a writer was active.
+    #[inline]
+    pub fn try_read(&self) -> Option<T> {
+        let seq = self.seq.try_read_begin()?;
+
+        // SAFETY: The data is read as `MaybeUninit`, so torn reads are not assumed to be valid values.
+        let data = unsafe { ptr::read_volatile(self.data.get().cast::<MaybeUninit<T>>()) };
+
+        if self.seq.read_retry(seq) {
+            return None;
+        }
+
+        // SAFETY: No writer was active, so the read is not torn.
+        Some(unsafe { data.assume_init() })
+    }
+
+    /// Locks this sequence lock for writing.
+    ///
+    /// Readers retry until the returned guard is dropped.
+    #[inline]
+    pub fn write(&self) -> SeqLockWriteGuard<'_, T, R> {
+        self.writer.lock();
+        self.seq.write_begin();
+        SeqLockWriteGuard {
+            lock: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the lock mutably, no actual locking needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consumes this lock, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: Copy + Default, R: RawMutex> Default for SeqLock<T, R> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug, R: RawMutex> fmt::Debug for SeqLock<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeqLock")
+            .field("data", &self.read())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A write guard of a [`SeqLock`].
+#[must_use = "if unused the SeqLock will immediately unlock"]
+pub struct SeqLockWriteGuard<'a, T: Copy, R: RawMutex = RawSpinMutex> {
+    lock: &'a SeqLock<T, R>,
+    marker: PhantomData<R::GuardMarker>,
+}
+
+impl<T: Copy, R: RawMutex> Deref for SeqLockWriteGuard<'_, T, R> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: We are the only writer.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: Copy, R: RawMutex> DerefMut for SeqLockWriteGuard<'_, T, R> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: We are the only writer and readers discard what they read meanwhile.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: Copy, R: RawMutex> Drop for SeqLockWriteGuard<'_, T, R> {
+    #[inline]
+    fn drop(&mut self) {
+        self.lock.seq.write_end();
+        // SAFETY: We locked the writer mutex in `SeqLock::write`.
+        unsafe { self.lock.writer.unlock() };
+    }
+}
+
+impl<T: Copy + fmt::Debug, R: RawMutex> fmt::Debug for SeqLockWriteGuard<'_, T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// A [`SeqLock`] whose writers disable interrupts.
+pub type InterruptSeqLock<T> = SeqLock<T, RawInterruptSpinMutex>;
+
+/// A [`SeqLockWriteGuard`] of an [`InterruptSeqLock`].
+pub type InterruptSeqLockWriteGuard<'a, T> = SeqLockWriteGuard<'a, T, RawInterruptSpinMutex>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn try_read_while_writing() {
+        let lock = InterruptSeqLock::new((0, 0));
+        let mut guard = lock.write();
+        *guard = (1, 1);
+        assert_eq!(lock.try_read(), None);
+        drop(guard);
+        assert_eq!(lock.try_read(), Some((1, 1)));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn no_torn_reads() {
+        const N: u64 = 10000;
+
+        let lock = SeqLock::<_>::new((0, 0));
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 1..=N {
+                    *lock.write() = (i, i);
+                }
+            });
+            s.spawn(|| loop {
+                let (a, b) = lock.read();
+                assert_eq!(a, b);
+                if a == N {
+                    break;
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,249 @@
+//! Contention sampling.
+//!
+//! Instead of instrumenting every acquisition, a kernel timer periodically calls [`sample`].
+//! Each call snapshots the queue depth of all [`register`]ed locks into per-lock histograms.
+//! Over time, the histograms show which locks are hot.
+//!
+//! # Examples
+//!
+//! ```
+//! use hermit_sync::{stats, TicketMutex};
+//!
+//! static RUN_QUEUE: TicketMutex<()> = TicketMutex::new(());
+//!
+//! stats::register(&RUN_QUEUE, "RUN_QUEUE");
+//!
+//! // Call this from a timer interrupt.
+//! stats::sample();
+//! let guard = RUN_QUEUE.lock();
+//! stats::sample();
+//! drop(guard);
+//!
+//! assert_eq!(stats::histogram(&RUN_QUEUE).unwrap()[..2], [1, 1]);
+//!
+//! let mut output = String::new();
+//! stats::dump_stats(&mut output).unwrap();
+//! assert!(output.contains("RUN_QUEUE"));
+//! ```
+
+#[cfg(target_has_atomic = "ptr")]
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+#[cfg(target_has_atomic = "ptr")]
+use core::{fmt, mem, ptr, slice, str};
+
+#[cfg(target_has_atomic = "ptr")]
+use lock_api::Mutex;
+use lock_api::RawMutex;
+#[cfg(target_has_atomic = "ptr")]
+use one_shot_mutex::RawOneShotMutex;
+
+/// The maximum number of locks that can be registered for sampling.
+pub const MAX_SAMPLED_LOCKS: usize = 32;
+
+/// The number of buckets per histogram.
+///
+/// Bucket `i` counts the samples with a queue depth of `i`.
+/// The last bucket also counts all samples with larger queue depths.
+pub const HISTOGRAM_BUCKETS: usize = 8;
+
+#[cfg(target_has_atomic = "ptr")]
+const EMPTY: usize = 0;
+
+/// A raw mutex that can report its queue depth for [`sample`].
+pub trait RawMutexSample: RawMutex {
+    /// Returns the number of holders and waiters of this mutex.
+    ///
+    /// This is only a snapshot and might be outdated immediately.
+    /// By default, this only reports whether the mutex is locked.
+    #[inline]
+    fn queue_depth(&self) -> usize {
+        usize::from(self.is_locked())
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl RawMutexSample for RawOneShotMutex {}
+
+#[cfg(target_has_atomic = "ptr")]
+struct SampledLock {
+    lock: AtomicUsize,
+    name: AtomicPtr<u8>,
+    len: AtomicUsize,
+    /// A `fn(usize) -> usize` returning the queue depth of `lock`.
+    queue_depth: AtomicPtr<()>,
+    histogram: [AtomicUsize; HISTOGRAM_BUCKETS],
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl SampledLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Self = {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicUsize = AtomicUsize::new(0);
+
+        Self {
+            lock: AtomicUsize::new(EMPTY),
+            name: AtomicPtr::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
+            queue_depth: AtomicPtr::new(ptr::null_mut()),
+            histogram: [ZERO; HISTOGRAM_BUCKETS],
+        }
+    };
+
+    /// Returns the name of this lock.
+    ///
+    /// # Safety
+    ///
+    /// The caller must have loaded a non-null `queue_depth` with [`Ordering::Acquire`].
+    unsafe fn name(&self) -> &'static str {
+        let name = self.name.load(Ordering::Relaxed);
+        let len = self.len.load(Ordering::Relaxed);
+        // SAFETY: `name` and `len` were stored once from a `&'static str` before publishing `queue_depth`, which the caller has acquired.
+        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(name, len)) }
+    }
+
+    fn histogram(&self) -> [usize; HISTOGRAM_BUCKETS] {
+        let mut histogram = [0; HISTOGRAM_BUCKETS];
+        for (count, bucket) in histogram.iter_mut().zip(&self.histogram) {
+            *count = bucket.load(Ordering::Relaxed);
+        }
+        histogram
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+static SAMPLED_LOCKS: [SampledLock; MAX_SAMPLED_LOCKS] = [SampledLock::EMPTY; MAX_SAMPLED_LOCKS];
+
+#[cfg(target_has_atomic = "ptr")]
+fn queue_depth<R: RawMutexSample>(lock: usize) -> usize {
+    // SAFETY: `lock` is the address of a `&'static R`.
+    let lock = unsafe { &*(lock as *const R) };
+    lock.queue_depth()
+}
+
+#[cfg(target_has_atomic = "ptr")]
+fn lock_addr<R: RawMutex, T: ?Sized>(mutex: &Mutex<R, T>) -> usize {
+    // SAFETY: We only take the address of the raw mutex.
+    ptr::from_ref(unsafe { mutex.raw() }) as usize
+}
+
+/// Registers a lock for [`sample`].
+///
+/// `name` is usually the name of the static holding the lock.
+/// Registering a lock again has no effect and keeps its first name.
+/// If [`MAX_SAMPLED_LOCKS`] locks have already been registered, the lock is not sampled.
+#[cfg(target_has_atomic = "ptr")]
+pub fn register<R: RawMutexSample, T: ?Sized>(mutex: &'static Mutex<R, T>, name: &'static str) {
+    let lock = lock_addr(mutex);
+    for entry in &SAMPLED_LOCKS {
+        let claimed = entry
+            .lock
+            .compare_exchange(EMPTY, lock, Ordering::AcqRel, Ordering::Acquire);
+        if claimed == Err(lock) {
+            // The name and length must not change once published.
+            return;
+        }
+        if claimed.is_ok() {
+            entry.len.store(name.len(), Ordering::Relaxed);
+            entry
+                .name
+                .store(name.as_ptr().cast_mut(), Ordering::Relaxed);
+            entry
+                .queue_depth
+                .store(queue_depth::<R> as *mut (), Ordering::Release);
+            return;
+        }
+    }
+}
+
+/// Samples the queue depths of all registered locks.
+///
+/// This is intended to be called periodically, for example, from a timer interrupt.
+/// It does not lock anything.
+#[cfg(target_has_atomic = "ptr")]
+pub fn sample() {
+    for entry in &SAMPLED_LOCKS {
+        let lock = entry.lock.load(Ordering::Acquire);
+        let queue_depth = entry.queue_depth.load(Ordering::Acquire);
+        if lock == EMPTY || queue_depth.is_null() {
+            continue;
+        }
+
+        // SAFETY: Only `fn(usize) -> usize` are stored in `queue_depth`.
+        let queue_depth = unsafe { mem::transmute::<*mut (), fn(usize) -> usize>(queue_depth) };
+        let bucket = queue_depth(lock).min(HISTOGRAM_BUCKETS - 1);
+        entry.histogram[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Returns the histogram of a registered lock.
+///
+/// If the lock has not been registered, this returns `None`.
+#[cfg(target_has_atomic = "ptr")]
+pub fn histogram<R: RawMutex, T: ?Sized>(
+    mutex: &Mutex<R, T>,
+) -> Option<[usize; HISTOGRAM_BUCKETS]> {
+    let lock = lock_addr(mutex);
+    SAMPLED_LOCKS
+        .iter()
+        .find(|entry| entry.lock.load(Ordering::Acquire) == lock)
+        .map(SampledLock::histogram)
+}
+
+/// Writes the histograms of all registered locks to `writer`.
+///
+/// Each line lists the sample counts per queue depth, starting at zero.
+#[cfg(target_has_atomic = "ptr")]
+pub fn dump_stats(writer: &mut dyn fmt::Write) -> fmt::Result {
+    for entry in &SAMPLED_LOCKS {
+        let lock = entry.lock.load(Ordering::Acquire);
+        if lock == EMPTY || entry.queue_depth.load(Ordering::Acquire).is_null() {
+            continue;
+        }
+
+        writeln!(
+            writer,
+            "{} ({lock:#x}): {:?}",
+            // SAFETY: We have acquired a non-null `queue_depth` above.
+            unsafe { entry.name() },
+            entry.histogram()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InterruptSpinMutex;
+
+    #[test]
+    fn locked_samples() {
+        static MUTEX: InterruptSpinMutex<()> = InterruptSpinMutex::new(());
+        register(&MUTEX, "MUTEX");
+
+        sample();
+        let guard = MUTEX.lock();
+        sample();
+        sample();
+        drop(guard);
+
+        assert_eq!(histogram(&MUTEX).unwrap()[..2], [1, 2]);
+
+        let mut output = String::new();
+        dump_stats(&mut output).unwrap();
+        assert!(output.contains("MUTEX"));
+    }
+
+    #[test]
+    fn register_again_keeps_name() {
+        static MUTEX: InterruptSpinMutex<()> = InterruptSpinMutex::new(());
+        register(&MUTEX, "FIRST_NAME");
+        register(&MUTEX, "SECOND_NAME");
+
+        let mut output = String::new();
+        dump_stats(&mut output).unwrap();
+        assert!(output.contains("FIRST_NAME"));
+        assert!(!output.contains("SECOND_NAME"));
+    }
+}
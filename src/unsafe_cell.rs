@@ -0,0 +1,98 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+
+/// An [`UnsafeCell`] that is [`Sync`].
+///
+/// This is a stable version of [`core::cell::SyncUnsafeCell`] for replacing `static mut` and hand-written wrappers.
+///
+/// # Safety
+///
+/// Like [`UnsafeCell`], this does not synchronize accesses.
+/// Users have to ensure that the data is not accessed mutably while other accesses are possible, for example:
+///
+/// * by only accessing the data before other CPU cores are started,
+/// * by only accessing the data from the current CPU core with disabled interrupts, or
+/// * by protecting the data with an external lock.
+///
+/// # Examples
+///
+/// ```
+/// use hermit_sync::SyncUnsafeCell;
+///
+/// static BOOT_PAGE_TABLE: SyncUnsafeCell<[u64; 512]> = SyncUnsafeCell::new([0; 512]);
+///
+/// // SAFETY: Other CPU cores have not been started yet.
+/// unsafe {
+///     (*BOOT_PAGE_TABLE.get())[0] = 0x1000 | 0b11;
+/// }
+/// ```
+#[repr(transparent)]
+pub struct SyncUnsafeCell<T: ?Sized> {
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: Users of this cell have to synchronize accesses themselves.
+unsafe impl<T: ?Sized + Sync> Sync for SyncUnsafeCell<T> {}
+
+impl<T> SyncUnsafeCell<T> {
+    /// Creates a new cell containing `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the cell, returning the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> SyncUnsafeCell<T> {
+    /// Returns a mutable pointer to the wrapped value.
+    ///
+    /// See [`UnsafeCell::get`].
+    #[inline]
+    pub const fn get(&self) -> *mut T {
+        self.value.get()
+    }
+
+    /// Returns a mutable reference to the wrapped value.
+    ///
+    /// This is safe, since the `&mut` borrow guarantees exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Returns a mutable pointer to the wrapped value without creating a reference to the cell.
+    ///
+    /// See [`UnsafeCell::raw_get`].
+    #[inline]
+    pub const fn raw_get(this: *const Self) -> *mut T {
+        // `Self` is `repr(transparent)` over `UnsafeCell<T>`.
+        UnsafeCell::raw_get(this as *const UnsafeCell<T>)
+    }
+}
+
+impl<T: Default> Default for SyncUnsafeCell<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for SyncUnsafeCell<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for SyncUnsafeCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncUnsafeCell").finish_non_exhaustive()
+    }
+}
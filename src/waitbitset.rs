@@ -0,0 +1,205 @@
+use core::sync::atomic::{self, AtomicUsize, Ordering};
+
+use crate::relax::{Backoff, Relax};
+
+/// An eventcount whose waiters subscribe to subsets of events.
+///
+/// This is similar to [`EventCount`](crate::EventCount), but each wait and each notification carries a bitmask, similar to Linux's `FUTEX_WAIT_BITSET` and `FUTEX_WAKE_BITSET`.
+/// A waiter is only woken by notifications whose bitmask intersects with its own bitmask.
+/// This reduces thundering herds when one status word multiplexes several events.
+///
+/// Waiting spins with [exponential backoff].
+///
+/// [exponential backoff]: https://en.wikipedia.org/wiki/Exponential_backoff
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicU32, Ordering};
+///
+/// use hermit_sync::WaitBitset;
+///
+/// const RX: u32 = 1 << 0;
+/// const TX: u32 = 1 << 1;
+///
+/// static STATUS: AtomicU32 = AtomicU32::new(0);
+/// static EVENT: WaitBitset = WaitBitset::new();
+///
+/// std::thread::spawn(|| {
+///     STATUS.fetch_or(RX, Ordering::Release);
+///     EVENT.notify(RX);
+/// });
+///
+/// // Waiters for TX are not woken.
+/// EVENT.wait_until(RX, || STATUS.load(Ordering::Acquire) & RX != 0);
+/// ```
+#[derive(Default, Debug)]
+pub struct WaitBitset {
+    epoch: AtomicUsize,
+    /// The epoch of the last notification per bit.
+    notified: [AtomicUsize; 32],
+    waiters: AtomicUsize,
+}
+
+/// A key returned by [`WaitBitset::prepare_wait`].
+///
+/// The key has to be passed to either [`WaitBitset::commit_wait`] or [`WaitBitset::cancel_wait`].
+#[must_use = "the key has to be passed to either `commit_wait` or `cancel_wait`"]
+#[derive(Debug)]
+pub struct BitsetKey {
+    mask: u32,
+    epoch: usize,
+}
+
+impl WaitBitset {
+    /// Creates a new `WaitBitset`.
+    #[inline]
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicUsize = AtomicUsize::new(0);
+
+        Self {
+            epoch: AtomicUsize::new(0),
+            notified: [ZERO; 32],
+            waiters: AtomicUsize::new(0),
+        }
+    }
+
+    /// Announces that the caller is about to wait for events in `mask`.
+    ///
+    /// After calling this, the caller has to recheck its condition.
+    /// If the condition holds, the caller should call [`cancel_wait`](Self::cancel_wait).
+    /// Otherwise, the caller should call [`commit_wait`](Self::commit_wait).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask` is zero, since no notification could ever wake the waiter.
+    /// This corresponds to `FUTEX_WAIT_BITSET` failing with `EINVAL`.
+    #[inline]
+    #[track_caller]
+    pub fn prepare_wait(&self, mask: u32) -> BitsetKey {
+        assert!(mask != 0, "empty wait mask");
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        BitsetKey { mask, epoch }
+    }
+
+    /// Cancels a wait announced via [`prepare_wait`](Self::prepare_wait).
+    #[inline]
+    pub fn cancel_wait(&self, key: BitsetKey) {
+        let _ = key;
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn is_notified(&self, key: &BitsetKey) -> bool {
+        (0..32).filter(|bit| key.mask & (1 << bit) != 0).any(|bit| {
+            let notified = self.notified[bit].load(Ordering::Acquire);
+            // Compare wrapping epochs.
+            (notified.wrapping_sub(key.epoch) as isize) > 0
+        })
+    }
+
+    /// Waits until [`notify`](Self::notify) has been called with an intersecting mask since the corresponding [`prepare_wait`](Self::prepare_wait).
+    #[inline]
+    pub fn commit_wait(&self, key: BitsetKey) {
+        let mut backoff = Backoff::default();
+        while !self.is_notified(&key) {
+            backoff.relax();
+        }
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Wakes all waiters for events intersecting with `mask`.
+    ///
+    /// This has to be called after making the change that waiters are waiting for.
+    #[inline]
+    pub fn notify(&self, mask: u32) {
+        // Order the change that waiters are waiting for before reading `waiters`.
+        // This pairs with the `SeqCst` operations in `prepare_wait`.
+        atomic::fence(Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
+        for bit in (0..32).filter(|bit| mask & (1 << bit) != 0) {
+            // Concurrent notifications must not move the epoch of a bit backward.
+            let _ = self.notified[bit].fetch_update(Ordering::Release, Ordering::Relaxed, |old| {
+                ((epoch.wrapping_sub(old) as isize) > 0).then_some(epoch)
+            });
+        }
+    }
+
+    /// Waits until `condition` returns `true`, waking up on events in `mask`.
+    ///
+    /// This wraps the [`prepare_wait`](Self::prepare_wait) protocol in a loop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask` is zero and `condition` returns `false`.
+    #[inline]
+    #[track_caller]
+    pub fn wait_until<F>(&self, mask: u32, mut condition: F)
+    where
+        F: FnMut() -> bool,
+    {
+        while !condition() {
+            let key = self.prepare_wait(mask);
+            if condition() {
+                self.cancel_wait(key);
+                return;
+            }
+            self.commit_wait(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn selective_notify() {
+        let event = WaitBitset::new();
+
+        let key = event.prepare_wait(0b01);
+        event.notify(0b10);
+        assert!(!event.is_notified(&key));
+        event.notify(0b11);
+        assert!(event.is_notified(&key));
+        event.commit_wait(key);
+    }
+
+    #[test]
+    #[should_panic = "empty wait mask"]
+    fn empty_mask() {
+        let event = WaitBitset::new();
+        let _key = event.prepare_wait(0);
+    }
+
+    #[test]
+    fn producer_consumer() {
+        const N: u32 = 100;
+
+        let event = WaitBitset::new();
+        let items = AtomicU32::new(0);
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                for _ in 0..N {
+                    items.fetch_add(1, Ordering::Release);
+                    event.notify(0b10);
+                }
+            });
+
+            for consumed in 1..=N {
+                event.wait_until(0b10, || items.load(Ordering::Acquire) >= consumed);
+            }
+        });
+
+        assert_eq!(event.waiters.load(Ordering::Relaxed), 0);
+    }
+}